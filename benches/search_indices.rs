@@ -0,0 +1,89 @@
+//! Compares `search_indices` with the byte by byte search it replaced.
+//! Run with `cargo bench --bench search_indices`.
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use notitia::utils::{title_ready, search_indices, search_indices_batch};
+
+/// The previous `search_indices`: the title is compared at every byte of the data.
+fn search_indices_previous(data_a: &[u8], current_title: &str) -> Vec<usize> {
+    let title = title_ready(current_title);
+    let special_chrs: &[u8] = b":.'?;";
+    let title_a: &[u8] = title.as_bytes();
+    let mut indices: Vec<usize> = vec!();
+    let end_data = data_a.len();
+    let mut j = 0;
+    let mut last_eol: usize = 0;
+    while j < end_data {
+        let mut current_index: bool = true;
+        if data_a[j] == b'\n' || data_a[j] == b'\r' {
+            last_eol = j + 1;
+        }
+        let mut jj = j;
+        let mut i = 0;
+        while i < title_a.len() {
+            if jj >= end_data {
+                current_index = false;
+                break;
+            }
+            let chr = data_a[jj];
+            if special_chrs.contains(&chr) {
+                jj += 1;
+            } else if chr != title_a[i] && !(chr.is_ascii_uppercase() && chr + (b'a' - b'A') == title_a[i]) {
+                current_index = false;
+                break;
+            } else {
+                jj += 1;
+                i += 1;
+            }
+        }
+        if current_index {
+            indices.push(last_eol);
+        }
+        j += 1;
+    }
+    indices
+}
+
+static WORDS: &[&str] = &["Blade", "Runner", "The", "Night", "of", "Return", "Star", "Lost", "City", "Dark", "Love", "War", "Man", "Story", "Last", "King"];
+
+/// A title.basics-like file with `lines` titles.
+fn basics(lines: usize) -> Vec<u8> {
+    let mut data = String::from("tconst\ttitleType\tprimaryTitle\toriginalTitle\tisAdult\tstartYear\tendYear\truntimeMinutes\tgenres\n");
+    for line in 0..lines {
+        let title = format!("{} {} {}", WORDS[line % 16], WORDS[(line / 16) % 16], WORDS[(line / 256) % 16]);
+        data.push_str(&format!("tt{:07}\tmovie\t{}\t{}\t0\t{}\t\\N\t{}\tDrama\n", line, title, title, 1920 + line % 100, 80 + line % 60));
+    }
+    data.into_bytes()
+}
+
+fn time<R>(name: &str, runs: u32, mut run: impl FnMut() -> R) -> Duration {
+    let begin = Instant::now();
+    for _ in 0..runs {
+        black_box(run());
+    }
+    let elapsed = begin.elapsed() / runs;
+    println!("{:<40} {:>12.3?}", name, elapsed);
+    elapsed
+}
+
+fn main() {
+    let data = basics(100_000);
+    println!("data: {} MB", data.len() / 1_000_000);
+
+    let title = "\tLost City Dark\t".to_string();
+    // the previous search returned a line once per occurrence of the title (primaryTitle and originalTitle)
+    let mut expected = search_indices_previous(&data, &title);
+    expected.dedup();
+    assert_eq!(expected, search_indices(&data, &title).unwrap());
+    let previous = time("one title, previous", 5, || search_indices_previous(&data, &title));
+    let current = time("one title, search_indices", 5, || search_indices(&data, &title).unwrap());
+    println!("{:<40} {:>11.1}x", "speedup", previous.as_secs_f64() / current.as_secs_f64());
+
+    let titles: Vec<String> = (0..20).map(|i| format!("\t{} {} {}\t", WORDS[i % 16], WORDS[(i * 7) % 16], WORDS[(i * 3) % 16])).collect();
+    let previous = time("20 titles, previous (one pass each)", 1, || titles.iter().map(|title| search_indices_previous(&data, title)).collect::<Vec<Vec<usize>>>());
+    let current = time("20 titles, search_indices_batch", 1, || search_indices_batch(&data, &titles).unwrap());
+    println!("{:<40} {:>11.1}x", "speedup", previous.as_secs_f64() / current.as_secs_f64());
+}
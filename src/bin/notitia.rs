@@ -0,0 +1,69 @@
+use notitia::config::{USAGE, load_config};
+use notitia::pipeline::{build_indexes, reduce, review};
+use notitia::scan::{scan, read_node_list};
+use notitia::movies::log_reconciliation;
+use notitia::logging;
+use notitia::report::{build_report, write_report};
+use notitia::{Error, Pipeline};
+use tracing::info;
+
+#[tokio::main]
+async fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Error> {
+
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if args.iter().any(|arg| arg == "--help") {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        let (config, positional) = load_config(&args)?;
+        logging::init(&config.log)?;
+        let command = positional.first().map(|command| command.as_str());
+        match command {
+            None | Some("watch") => {},
+            Some("scan") if positional.len() > 1 => {},
+            Some("scan") => return Err(Error::Usage("scan needs the library root: scan <dir>".to_string())),
+            Some("index") => return build_indexes(&config),
+            Some("review") => return review(&config),
+            Some("reduce") if positional.len() == 3 => return reduce(&config, &positional[1], &positional[2]),
+            Some("reduce") => return Err(Error::Usage("reduce needs the imdb basics and akas files: reduce <title.basics.tsv.gz> <title.akas.tsv.gz>".to_string())),
+            Some(command) => return Err(Error::Usage(format!("unknown command '{}'", command))),
+        }
+
+        let mut pipeline = Pipeline::load(config)?;
+
+        info!("checking files");
+
+        if command == Some("watch") {
+            return pipeline.watch(positional.get(1).map(|root| root.as_str()));
+        }
+        let loaded = pipeline.movies.clone();
+
+        let nodes = match command {
+            Some("scan") => {
+                let nodes = scan(&positional[1], &pipeline.config.scan, &pipeline.config.filter_node_format).map_err(|err| Error::io(&positional[1], err))?;
+                info!(nodes = nodes.len(), "nodes found");
+                pipeline.add_nodes(&nodes);
+                pipeline.update_node_files(&nodes);
+                nodes
+            },
+            _ => {
+                let raw_list_movie_file = &pipeline.config.raw_list_movie_file;
+                let nodes = read_node_list(raw_list_movie_file).map_err(|err| Error::io(raw_list_movie_file, err))?;
+                pipeline.add_nodes(&nodes);
+                nodes
+            },
+        };
+        log_reconciliation(&pipeline.reconcile(&nodes));
+
+        pipeline.save()?;
+        write_report(&pipeline.config.report, &build_report(&loaded, &pipeline.movies))?;
+
+        Ok(())
+}
@@ -0,0 +1,338 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use crate::utils::DEFAULT_SIMILARITY_THRESHOLD;
+use crate::error::Error;
+use crate::node_name::FormatRule;
+use crate::scan::ScanConfig;
+use crate::watch::WatchConfig;
+use crate::logging::LogConfig;
+use crate::report::ReportConfig;
+use serde::{Deserialize, Serialize};
+
+static DEFAULT_CONFIG_FILE: &str = "notitia.json";
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ImdbConfig {
+    pub language: String,
+    pub basics: String,
+    pub akas: String,
+    pub ratings: String,
+    pub min_votes: u32,
+}
+impl Default for ImdbConfig {
+    fn default() -> ImdbConfig {
+        ImdbConfig {
+            language: "ES".to_string(),
+            basics: "files/imdb/title.basics.reduced.es.tsv".to_string(),
+            akas: "files/imdb/title.akas.reduced.es.tsv".to_string(),
+            ratings: "files/imdb/title.ratings.tsv".to_string(),
+            min_votes: 0,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct TmdbConfig {
+    pub movies_metadata: String,
+    pub archive: String,
+}
+impl Default for TmdbConfig {
+    fn default() -> TmdbConfig {
+        TmdbConfig {
+            movies_metadata: "files/tmdb/movies_metadata.csv".to_string(),
+            archive: "files/tmdb/archive.csv".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct JustwatchConfig {
+    pub files: Vec<String>,
+}
+impl Default for JustwatchConfig {
+    fn default() -> JustwatchConfig {
+        JustwatchConfig {
+            files: vec![
+                "files/justwatch/all.netflix.json".to_string(),
+                "files/justwatch/all.disney+.json".to_string(),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct MatchingConfig {
+    pub similarity_threshold: f32,
+    pub year_tolerance: u16,
+}
+impl Default for MatchingConfig {
+    fn default() -> MatchingConfig {
+        MatchingConfig {
+            similarity_threshold: DEFAULT_SIMILARITY_THRESHOLD,
+            year_tolerance: 1,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ScoringConfig {
+    pub auto_select_threshold: f32,
+}
+impl Default for ScoringConfig {
+    fn default() -> ScoringConfig {
+        ScoringConfig {
+            auto_select_threshold: 0.8,
+        }
+    }
+}
+
+fn default_format_rules() -> Vec<FormatRule> {
+    let rule = |directory: &str, extensions: &[&str], format: &str| FormatRule {
+        directory: directory.to_string(),
+        extensions: extensions.iter().map(|extension| extension.to_string()).collect(),
+        format: format.to_string(),
+    };
+    vec![
+        rule("Series/", &[], "tvSeries"),
+        rule("Shorts/", &[], "short"),
+        rule("", &["mkv", "mp4", "avi", "iso", "m4v", "mov", "wmv", "mpg", "mpeg", "ts", "m2ts", "webm", ""], "movie"),
+    ]
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    pub working_file: String,
+    pub output_file: String,
+    pub raw_list_movie_file: String,
+    pub overrides_file: String,
+    /// Folder the gzip datasets are decompressed to, next to them if it is empty.
+    pub extract_dir: String,
+    pub updating: bool,
+    pub prune_missing: bool,
+    pub threads: usize,
+    pub filter_node_format: Vec<String>,
+    pub format_rules: Vec<FormatRule>,
+
+    pub imdb: ImdbConfig,
+    pub tmdb: TmdbConfig,
+    pub justwatch: JustwatchConfig,
+    pub matching: MatchingConfig,
+    pub scoring: ScoringConfig,
+    pub scan: ScanConfig,
+    pub watch: WatchConfig,
+    pub log: LogConfig,
+    pub report: ReportConfig,
+}
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            working_file: "result/movies.json".to_string(),
+            output_file: "result/movies.result.json".to_string(),
+            raw_list_movie_file: "result/movies.txt".to_string(),
+            overrides_file: String::new(),
+            extract_dir: String::new(),
+            updating: true,
+            prune_missing: false,
+            threads: 0,
+            filter_node_format: vec!["movie".to_string(), "tvSeries".to_string(), "short".to_string()],
+            format_rules: default_format_rules(),
+
+            imdb: ImdbConfig { ..Default::default() },
+            tmdb: TmdbConfig { ..Default::default() },
+            justwatch: JustwatchConfig { ..Default::default() },
+            matching: MatchingConfig { ..Default::default() },
+            scoring: ScoringConfig { ..Default::default() },
+            scan: ScanConfig { ..Default::default() },
+            watch: WatchConfig { ..Default::default() },
+            log: LogConfig { ..Default::default() },
+            report: ReportConfig { ..Default::default() },
+        }
+    }
+}
+
+pub static USAGE: &str = "Usage: NotitiaRust [command] [options]
+
+Commands:
+    (none)                          identify the nodes of raw_list_movie_file
+    scan <dir>                      identify the nodes found walking the library under dir
+    watch [dir]                     identify the nodes again when the library under dir (or
+                                    raw_list_movie_file) changes, until it is stopped
+    index                           build the indexes for imdb basics and ratings
+    review                          confirm or pick the candidates of the movies of working_file
+                                    and write them to output_file
+    reduce <basics> <akas>          write imdb.basics and imdb.akas from the official imdb files
+                                    (.tsv or .tsv.gz) keeping valid title types and imdb.language akas
+
+Options:
+    --config <file>                 configuration file (json), default: notitia.json if it exists
+    --working-file <file>           file to get updated
+    --output-file <file>            file with the result
+    --raw-list-movie-file <file>    list of node names to identify
+    --overrides-file <file>         ids to use for some nodes instead of searching them
+    --extract-dir <dir>             folder the .gz datasets are decompressed to (they need the disk
+                                    space of the whole file), default: next to each dataset
+    --updating / --no-updating      update the working file or generate a new content
+    --prune-missing / --no-prune-missing
+                                    remove the movies whose nodes are all missing
+    --threads <count>               workers identifying the nodes, 0 for one per CPU core
+    --filter-node-format <a,b,...>  valid formats for input
+    --imdb-language <region>        region used for imdb akas
+    --imdb-basics <file>            imdb title.basics file
+    --imdb-akas <file>              imdb title.akas file
+    --imdb-ratings <file>           imdb title.ratings file
+    --imdb-min-votes <votes>        ignore imdb candidates with less votes when there are several
+    --tmdb-movies-metadata <file>   tmdb movies_metadata.csv file
+    --tmdb-archive <file>           tmdb archive.csv file
+    --justwatch-files <a,b,...>     justwatch json files
+    --similarity-threshold <0-1>    minimum title similarity to match a title
+    --year-tolerance <years>        maximum difference between the node year and the matched year
+    --auto-select-threshold <0-1>   minimum score to select an imdb candidate automatically
+    --watch-debounce-ms <ms>        time without changes before checking the nodes in watch mode
+    --log-level <level>             error, warn, info, debug or trace
+    --log-format <human|json>       log lines for people or one JSON object per line
+    --progress / --no-progress      progress bar while identifying the nodes (only on a terminal)
+    --quiet                         only warnings and errors, without progress bar or report table
+    --report-table / --no-report-table
+                                    print the report of the run as a table
+    --report-json <file>            write the report of the run as JSON
+    --report-html <file>            write the report of the run as an HTML page
+    --help                          show this message";
+
+fn split_list(value: &str) -> Vec<String> {
+    value.split(',').map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect()
+}
+
+pub fn load_config_file(config_fileName: &str) -> Result<Config, Error> {
+    let config_data = fs::read_to_string(config_fileName).map_err(|err| Error::io(config_fileName, err))?;
+    let config: Config = serde_json::from_str(&config_data).map_err(|err| Error::json(config_fileName, err))?;
+
+    Ok(config)
+}
+
+/// Option of the command line that can't be applied to the configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    UnknownOption { option: String },
+    /// Known option whose value can't be parsed to the type of the option.
+    InvalidValue { option: String, value: String },
+}
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::UnknownOption { option } => write!(f, "unknown option '{}'", option),
+            ConfigError::InvalidValue { option, value } => write!(f, "invalid value '{}' for '{}'", value, option),
+        }
+    }
+}
+impl std::error::Error for ConfigError {}
+impl From<ConfigError> for Error {
+    fn from(err: ConfigError) -> Error {
+        Error::Usage(err.to_string())
+    }
+}
+
+fn parse_value<T: FromStr>(option: &str, value: &str) -> Result<T, ConfigError> {
+    value.parse().map_err(|_| ConfigError::InvalidValue { option: option.to_string(), value: value.to_string() })
+}
+
+/// Applies one command line option to the configuration.
+pub fn apply_option(config: &mut Config, option: &str, value: &str) -> Result<(), ConfigError> {
+    match option {
+        "--working-file" => config.working_file = value.to_string(),
+        "--output-file" => config.output_file = value.to_string(),
+        "--raw-list-movie-file" => config.raw_list_movie_file = value.to_string(),
+        "--overrides-file" => config.overrides_file = value.to_string(),
+        "--extract-dir" => config.extract_dir = value.to_string(),
+        "--threads" => config.threads = parse_value(option, value)?,
+        "--filter-node-format" => config.filter_node_format = split_list(value),
+        "--imdb-language" => config.imdb.language = value.to_string(),
+        "--imdb-basics" => config.imdb.basics = value.to_string(),
+        "--imdb-akas" => config.imdb.akas = value.to_string(),
+        "--imdb-ratings" => config.imdb.ratings = value.to_string(),
+        "--imdb-min-votes" => config.imdb.min_votes = parse_value(option, value)?,
+        "--tmdb-movies-metadata" => config.tmdb.movies_metadata = value.to_string(),
+        "--tmdb-archive" => config.tmdb.archive = value.to_string(),
+        "--justwatch-files" => config.justwatch.files = split_list(value),
+        "--similarity-threshold" => config.matching.similarity_threshold = parse_value(option, value)?,
+        "--year-tolerance" => config.matching.year_tolerance = parse_value(option, value)?,
+        "--auto-select-threshold" => config.scoring.auto_select_threshold = parse_value(option, value)?,
+        "--watch-debounce-ms" => config.watch.debounce_ms = parse_value(option, value)?,
+        "--log-level" => config.log.level = value.to_string(),
+        "--log-format" => config.log.format = value.to_string(),
+        "--report-json" => config.report.json = value.to_string(),
+        "--report-html" => config.report.html = value.to_string(),
+        _ => return Err(ConfigError::UnknownOption { option: option.to_string() }),
+    }
+    Ok(())
+}
+
+/// Reads the configuration file (`--config` or `notitia.json` if present) and
+/// overrides it with the rest of the command line options.
+/// Returns the configuration and the positional arguments.
+pub fn load_config(args: &[String]) -> Result<(Config, Vec<String>), Error> {
+    let mut config_fileName: Option<String> = None;
+    let mut options: Vec<(String, Option<String>)> = Vec::new();
+    let mut positional: Vec<String> = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if !arg.starts_with("--") {
+            positional.push(arg.clone());
+            continue;
+        }
+        let (option, inline_value) = match arg.find('=') {
+            Some(index) => (arg[..index].to_string(), Some(arg[index + 1..].to_string())),
+            None => (arg.clone(), None),
+        };
+        match option.as_str() {
+            "--updating" | "--no-updating" | "--prune-missing" | "--no-prune-missing"
+                | "--progress" | "--no-progress" | "--report-table" | "--no-report-table" | "--quiet" => options.push((option, None)),
+            _ => {
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => match iter.next() {
+                        Some(value) => value.clone(),
+                        None => return Err(Error::Usage(format!("missing value for '{}'", option))),
+                    },
+                };
+                if option == "--config" {
+                    config_fileName = Some(value);
+                } else {
+                    options.push((option, Some(value)));
+                }
+            }
+        }
+    }
+
+    let mut config = match config_fileName {
+        Some(config_fileName) => load_config_file(&config_fileName)?,
+        None if Path::new(DEFAULT_CONFIG_FILE).exists() => load_config_file(DEFAULT_CONFIG_FILE)?,
+        None => Config { ..Default::default() },
+    };
+
+    for (option, value) in options {
+        match value {
+            None => match option.as_str() {
+                "--updating" | "--no-updating" => config.updating = option == "--updating",
+                "--progress" | "--no-progress" => config.log.progress = option == "--progress",
+                "--report-table" | "--no-report-table" => config.report.table = option == "--report-table",
+                "--quiet" => {
+                    config.log.quiet();
+                    config.report.table = false;
+                },
+                _ => config.prune_missing = option == "--prune-missing",
+            },
+            Some(value) => apply_option(&mut config, &option, &value)?,
+        }
+    }
+
+    Ok((config, positional))
+}
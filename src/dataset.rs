@@ -0,0 +1,148 @@
+use flate2::read::GzDecoder;
+use memmap2::Mmap;
+use std::fs;
+use std::io::{self, prelude::*, BufReader, BufWriter};
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use tracing::info;
+use crate::error::Error;
+
+/// Bytes of a file, memory mapped or (if it can't be mapped) read into memory.
+pub(crate) enum DatasetData {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+impl Deref for DatasetData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            DatasetData::Mapped(mmap) => mmap,
+            DatasetData::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// Read only dataset file. The file is memory mapped, so the whole dataset doesn't
+/// need to fit in memory: pages are read on demand and released by the OS.
+/// If the file can't be mapped it is read into memory.
+/// Gzip files (`.gz`) are decompressed once into `<file>.extracted` (see `extracted_path`), next to
+/// them or in `extract_dir`, which is mapped instead; `reduce` makes the imdb files much smaller.
+pub struct Dataset {
+    pub path: String,
+    data: DatasetData,
+    malformed_lines: AtomicUsize,
+}
+impl Dataset {
+    pub fn open(path: &str) -> Result<Dataset, Error> {
+        Dataset::open_extracting_in(path, "")
+    }
+
+    /// Opens the dataset `path`, decompressing it in `extract_dir` if it is a gzip file
+    /// (next to it if `extract_dir` is empty).
+    pub fn open_extracting_in(path: &str, extract_dir: &str) -> Result<Dataset, Error> {
+        let file = fs::File::open(path).map_err(|source| Error::MissingDataset { path: path.to_string(), source })?;
+        if is_gzip(path) {
+            let extracted = extract(path, file, extract_dir)?;
+            let file = fs::File::open(&extracted).map_err(|err| Error::io(&extracted, err))?;
+            return Ok(Dataset::new(path, map(&extracted, file)?));
+        }
+
+        Ok(Dataset::new(path, map(path, file)?))
+    }
+
+    pub fn from_bytes(path: &str, bytes: Vec<u8>) -> Dataset {
+        Dataset::new(path, DatasetData::Owned(bytes))
+    }
+
+    fn new(path: &str, data: DatasetData) -> Dataset {
+        Dataset { path: path.to_string(), data, malformed_lines: AtomicUsize::new(0) }
+    }
+
+    /// Malformed lines found searching the dataset, see `count_malformed_line`.
+    pub fn malformed_lines(&self) -> usize {
+        self.malformed_lines.load(AtomicOrdering::Relaxed)
+    }
+
+    /// Counts a malformed line found searching the dataset and returns the lines counted before it.
+    pub fn count_malformed_line(&self) -> usize {
+        self.malformed_lines.fetch_add(1, AtomicOrdering::Relaxed)
+    }
+}
+impl Deref for Dataset {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+pub(crate) fn map(path: &str, file: fs::File) -> Result<DatasetData, Error> {
+    // SAFETY: datasets are only read, they must not be modified while the program is running.
+    match unsafe { Mmap::map(&file) } {
+        Ok(mmap) => Ok(DatasetData::Mapped(mmap)),
+        Err(_) => {
+            let mut bytes = Vec::<u8>::new();
+            (&file).read_to_end(&mut bytes).map_err(|err| Error::io(path, err))?;
+            Ok(DatasetData::Owned(bytes))
+        }
+    }
+}
+
+pub fn is_gzip(path: &str) -> bool {
+    path.ends_with(".gz")
+}
+
+/// File a gzip dataset is decompressed to, in `extract_dir` or next to it if `extract_dir` is empty.
+pub fn extracted_path(path: &str, extract_dir: &str) -> String {
+    if extract_dir.is_empty() {
+        return format!("{}.extracted", path);
+    }
+    let file_name = Path::new(path).file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    Path::new(extract_dir).join(format!("{}.extracted", file_name)).to_string_lossy().to_string()
+}
+
+/// Decompresses the gzip dataset `path` to `extracted_path`, streaming it so it never is in memory,
+/// unless it was already decompressed after the last change of `path`. Returns the extracted path.
+/// The dataset is decompressed to a temporary file, removed if anything fails.
+fn extract(path: &str, file: fs::File, extract_dir: &str) -> Result<String, Error> {
+    let extracted = extracted_path(path, extract_dir);
+    let modified = |file: &fs::File| file.metadata().and_then(|metadata| metadata.modified());
+    if let Ok(extracted_file) = fs::File::open(&extracted) {
+        if let (Ok(extracted_modified), Ok(gzip_modified)) = (modified(&extracted_file), modified(&file)) {
+            if extracted_modified >= gzip_modified {
+                return Ok(extracted);
+            }
+        }
+    }
+
+    if !extract_dir.is_empty() {
+        fs::create_dir_all(extract_dir).map_err(|err| Error::io(extract_dir, err))?;
+    }
+    info!(path, %extracted, "decompressing the dataset");
+    let temporary = format!("{}.tmp", extracted);
+    let decompress = || -> Result<(), Error> {
+        let written = |err| Error::io(&temporary, err);
+        let mut writer = BufWriter::new(fs::File::create(&temporary).map_err(written)?);
+        io::copy(&mut GzDecoder::new(BufReader::new(file)), &mut writer).map_err(|err| Error::io(path, err))?;
+        writer.flush().map_err(written)?;
+        fs::rename(&temporary, &extracted).map_err(|err| Error::io(&extracted, err))
+    };
+    if let Err(err) = decompress() {
+        let _ = fs::remove_file(&temporary);
+        return Err(err);
+    }
+
+    Ok(extracted)
+}
+
+/// Opens a dataset to be read line by line, decompressing it if it is a gzip file.
+pub fn open_lines(path: &str) -> Result<Box<dyn BufRead>, Error> {
+    let file = fs::File::open(path).map_err(|source| Error::MissingDataset { path: path.to_string(), source })?;
+    if is_gzip(path) {
+        Ok(Box::new(BufReader::new(GzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
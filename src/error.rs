@@ -0,0 +1,68 @@
+use std::fmt;
+use std::io;
+
+/// Line of a dataset (or of an index) that can't be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MalformedLine {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+impl fmt::Display for MalformedLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+impl std::error::Error for MalformedLine {}
+
+/// Errors of notitia, with the file (or the argument) that caused them.
+#[derive(Debug)]
+pub enum Error {
+    /// A dataset that doesn't exist or can't be opened.
+    MissingDataset { path: String, source: io::Error },
+    MalformedLine(MalformedLine),
+    /// A JSON file (configuration, working file, justwatch) that can't be parsed or written.
+    Json { path: String, source: serde_json::Error },
+    /// Any other error reading or writing a file.
+    Io { path: String, source: io::Error },
+    /// Wrong command, option or configuration value.
+    Usage(String),
+    /// Titles or patterns of a search too many (or too big) for the automaton searching them.
+    Search(String),
+}
+impl Error {
+    pub fn io(path: &str, source: io::Error) -> Error {
+        Error::Io { path: path.to_string(), source }
+    }
+
+    pub fn json(path: &str, source: serde_json::Error) -> Error {
+        Error::Json { path: path.to_string(), source }
+    }
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MissingDataset { path, source } => write!(f, "can't open dataset '{}': {}", path, source),
+            Error::MalformedLine(malformed_line) => write!(f, "malformed line {}", malformed_line),
+            Error::Json { path, source } => write!(f, "invalid JSON in '{}': {}", path, source),
+            Error::Io { path, source } => write!(f, "'{}': {}", path, source),
+            Error::Usage(message) => write!(f, "{}", message),
+            Error::Search(message) => write!(f, "can't search the titles: {}", message),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::MissingDataset { source, .. } | Error::Io { source, .. } => Some(source),
+            Error::MalformedLine(malformed_line) => Some(malformed_line),
+            Error::Json { source, .. } => Some(source),
+            Error::Usage(_) | Error::Search(_) => None,
+        }
+    }
+}
+impl From<MalformedLine> for Error {
+    fn from(malformed_line: MalformedLine) -> Error {
+        Error::MalformedLine(malformed_line)
+    }
+}
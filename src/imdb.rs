@@ -1,11 +1,17 @@
-use crate::utils::{title_ready, get_line_by_index, search_indices, remove_diacritics};
+use crate::utils::{title_ready, SimilarTitle, year_delta, years_around, get_line_by_index, remove_diacritics, BatchIndices};
+use crate::pool::map_parallel;
+use crate::logging::{progress_stage, progress_inc};
+use crate::index::{DatasetIndex, build_index};
+use crate::dataset::{Dataset, open_lines};
+use crate::error::{Error, MalformedLine};
+use crate::config::{Config, MatchingConfig};
+use std::collections::{HashMap, HashSet};
+use memchr::memchr_iter;
 use std::fs;
+use std::io::{prelude::*, BufWriter};
 use serde::{Deserialize, Serialize};
+use tracing::{debug, info, info_span, warn};
 
-static LANGUAGE: &str = "ES";
-static DATA: &str = "files/imdb/title.basics.reduced.es.tsv";
-static DATA_AKAS: &str = "files/imdb/title.akas.reduced.es.tsv";
-static DATA_RATINGS: &str = "files/imdb/title.ratings.tsv";
 static TITLE_TYPES: &[&str] = &["movie", "short", "tvSeries", "tvMiniSeries", "tvMovie"];
 
 #[derive(Debug, Deserialize, Clone)]
@@ -22,35 +28,174 @@ pub struct TitleAkasRecord {
     pub isOriginalTitle: String,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Deserialize, Serialize)]
+#[serde(from = "String", into = "String")]
+pub enum Genre {
+    Action,
+    Adult,
+    Adventure,
+    Animation,
+    Biography,
+    Comedy,
+    Crime,
+    Documentary,
+    Drama,
+    Family,
+    Fantasy,
+    FilmNoir,
+    GameShow,
+    History,
+    Horror,
+    Music,
+    Musical,
+    Mystery,
+    News,
+    RealityTv,
+    Romance,
+    SciFi,
+    Short,
+    Sport,
+    TalkShow,
+    Thriller,
+    War,
+    Western,
+    Other(String),
+}
+impl From<&str> for Genre {
+    fn from(genre: &str) -> Genre {
+        match genre {
+            "Action" => Genre::Action,
+            "Adult" => Genre::Adult,
+            "Adventure" => Genre::Adventure,
+            "Animation" => Genre::Animation,
+            "Biography" => Genre::Biography,
+            "Comedy" => Genre::Comedy,
+            "Crime" => Genre::Crime,
+            "Documentary" => Genre::Documentary,
+            "Drama" => Genre::Drama,
+            "Family" => Genre::Family,
+            "Fantasy" => Genre::Fantasy,
+            "Film-Noir" => Genre::FilmNoir,
+            "Game-Show" => Genre::GameShow,
+            "History" => Genre::History,
+            "Horror" => Genre::Horror,
+            "Music" => Genre::Music,
+            "Musical" => Genre::Musical,
+            "Mystery" => Genre::Mystery,
+            "News" => Genre::News,
+            "Reality-TV" => Genre::RealityTv,
+            "Romance" => Genre::Romance,
+            "Sci-Fi" => Genre::SciFi,
+            "Short" => Genre::Short,
+            "Sport" => Genre::Sport,
+            "Talk-Show" => Genre::TalkShow,
+            "Thriller" => Genre::Thriller,
+            "War" => Genre::War,
+            "Western" => Genre::Western,
+            _ => Genre::Other(genre.to_string()),
+        }
+    }
+}
+impl From<String> for Genre {
+    fn from(genre: String) -> Genre {
+        Genre::from(genre.as_str())
+    }
+}
+impl From<Genre> for String {
+    fn from(genre: Genre) -> String {
+        match genre {
+            Genre::Action => "Action",
+            Genre::Adult => "Adult",
+            Genre::Adventure => "Adventure",
+            Genre::Animation => "Animation",
+            Genre::Biography => "Biography",
+            Genre::Comedy => "Comedy",
+            Genre::Crime => "Crime",
+            Genre::Documentary => "Documentary",
+            Genre::Drama => "Drama",
+            Genre::Family => "Family",
+            Genre::Fantasy => "Fantasy",
+            Genre::FilmNoir => "Film-Noir",
+            Genre::GameShow => "Game-Show",
+            Genre::History => "History",
+            Genre::Horror => "Horror",
+            Genre::Music => "Music",
+            Genre::Musical => "Musical",
+            Genre::Mystery => "Mystery",
+            Genre::News => "News",
+            Genre::RealityTv => "Reality-TV",
+            Genre::Romance => "Romance",
+            Genre::SciFi => "Sci-Fi",
+            Genre::Short => "Short",
+            Genre::Sport => "Sport",
+            Genre::TalkShow => "Talk-Show",
+            Genre::Thriller => "Thriller",
+            Genre::War => "War",
+            Genre::Western => "Western",
+            Genre::Other(genre) => return genre,
+        }.to_string()
+    }
+}
+
+/// IMDb uses `\N` (sometimes quoted) for null values.
+pub fn imdb_value(value: &str) -> Option<&str> {
+    let value = value.trim().trim_matches('"');
+    if value.is_empty() || value == "\\N" { None } else { Some(value) }
+}
+
+/// "Action,Drama,Sci-Fi" -> [Action, Drama, SciFi]
+pub fn parse_genres(genres: &str) -> Vec<Genre> {
+    match imdb_value(genres) {
+        Some(genres) => genres.split(',').map(Genre::from).collect(),
+        None => Vec::new(),
+    }
+}
+
+// previous versions of the working file stored every imdb value as a string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ValueOrString<T> {
+    Value(T),
+    String(String),
+}
+
+fn deserialize_optional_number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de> + std::str::FromStr,
+{
+    Ok(match Option::<ValueOrString<T>>::deserialize(deserializer)? {
+        None => None,
+        Some(ValueOrString::Value(value)) => Some(value),
+        Some(ValueOrString::String(value)) => imdb_value(&value).and_then(|value| value.parse().ok()),
+    })
+}
+
+fn deserialize_genres<'de, D>(deserializer: D) -> Result<Vec<Genre>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match ValueOrString::<Vec<Genre>>::deserialize(deserializer)? {
+        ValueOrString::Value(genres) => genres,
+        ValueOrString::String(genres) => parse_genres(&genres),
+    })
+}
+
 #[derive(Debug, Deserialize, Clone)]
 #[derive(Serialize)]
 #[allow(non_camel_case_types)]
-#[derive(PartialEq)]
+#[derive(PartialEq, Default)]
 pub struct TitleBasicsRecord {
     pub tconst: String,
     pub titleType: String,
     pub primaryTitle: String,
     pub originalTitle: String,
-    pub isAdult: String,
-    pub startYear: String,
-    pub endYear: String,
-    pub runtimeMinutes: String,
-    pub genres: String,
-}
-impl Default for TitleBasicsRecord {
-    fn default() -> TitleBasicsRecord {
-        TitleBasicsRecord {
-            tconst: String::new(),
-            titleType: String::new(),
-            primaryTitle: String::new(),
-            originalTitle: String::new(),
-            isAdult: String::new(),
-            startYear: String::new(),
-            endYear: String::new(),
-            runtimeMinutes: String::new(),
-            genres: String::new(),
-        }
-    }
+    pub isAdult: bool,
+    pub startYear: Option<u16>,
+    pub endYear: Option<u16>,
+    pub runtimeMinutes: Option<u32>,
+    pub genres: Vec<Genre>,
 }
 
 // title.ratings.tsv:
@@ -63,131 +208,348 @@ impl Default for TitleBasicsRecord {
 #[derive(PartialEq)]
 pub struct ImdbRatings {
     pub tconst: String,
-    pub averageRating: String,
-    pub numVotes: String,
+    pub averageRating: f32,
+    pub numVotes: u32,
 }
 impl Default for ImdbRatings {
     fn default() -> ImdbRatings {
         ImdbRatings {
             tconst: String::new(),
-            averageRating: String::new(),
-            numVotes: String::new(),
+            averageRating: 0.0,
+            numVotes: 0,
         }
     }
 }
 
+/// Rating stored on each `Imdb`, `numVotes` is 0 when it is unknown
+/// (working files from previous versions only have the average).
 #[derive(PartialEq)]
 #[derive(Debug, Deserialize, Clone)]
 #[derive(Serialize)]
+pub struct Rating {
+    pub averageRating: f32,
+    #[serde(default)]
+    pub numVotes: u32,
+}
+impl Default for Rating {
+    fn default() -> Rating {
+        Rating {
+            averageRating: 0.0,
+            numVotes: 0,
+        }
+    }
+}
+impl From<&ImdbRatings> for Rating {
+    fn from(ratings: &ImdbRatings) -> Rating {
+        Rating {
+            averageRating: ratings.averageRating,
+            numVotes: ratings.numVotes,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RatingOrValue {
+    Rating(Rating),
+    Value(f32),
+    String(String),
+}
+
+fn deserialize_rating<'de, D>(deserializer: D) -> Result<Option<Rating>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match Option::<RatingOrValue>::deserialize(deserializer)? {
+        None => None,
+        Some(RatingOrValue::Rating(rating)) => Some(rating),
+        Some(RatingOrValue::Value(averageRating)) => Some(Rating { averageRating, numVotes: 0 }),
+        Some(RatingOrValue::String(averageRating)) => imdb_value(&averageRating)
+            .and_then(|value| value.parse().ok())
+            .map(|averageRating| Rating { averageRating, numVotes: 0 }),
+    })
+}
+
+#[derive(PartialEq)]
+#[derive(Debug, Deserialize, Clone)]
+#[derive(Serialize, Default)]
 pub struct Imdb {
     pub tconst: String,
     pub titleType: String,
 
     pub primaryTitle: String,
     pub originalTitle: String,
-    pub year: String,
-    pub runtimeMinutes: String,
-    pub genres: String,
-    pub ratings: Option<String>,
-}
-impl Default for Imdb {
-    fn default() -> Imdb {
-        Imdb {
-            tconst: String::new(),
-            titleType: String::new(),
-            
-            primaryTitle: String::new(),
-            originalTitle: String::new(),
-            year: String::new(),
-            runtimeMinutes: String::new(),
-            genres: String::new(),
-            ratings: None,
+    #[serde(default, deserialize_with = "deserialize_optional_number")]
+    pub year: Option<u16>,
+    #[serde(default, deserialize_with = "deserialize_optional_number")]
+    pub runtimeMinutes: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_genres")]
+    pub genres: Vec<Genre>,
+    #[serde(default, deserialize_with = "deserialize_rating")]
+    pub ratings: Option<Rating>,
+    /// Confidence of the candidate for its node (see `scoring`).
+    #[serde(default)]
+    pub score: Option<f32>,
+    /// Similarity between the node title and the matched title (see `utils::title_similarity`).
+    #[serde(default)]
+    pub similarity: Option<f32>,
+    /// `year` minus the year of the node.
+    #[serde(default)]
+    pub year_delta: Option<i16>,
+}
+
+/// Positions of the columns of an IMDb dataset, read from its header,
+/// so the values are found by name even if IMDb adds or reorders columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Columns {
+    pub file: String,
+    separator: char,
+    names: HashMap<String, usize>,
+}
+impl Columns {
+    pub fn from_header(file: &str, header: &str, required: &[&str]) -> Result<Columns, MalformedLine> {
+        let header = header.trim_end_matches(&['\r', '\n'][..]);
+        let separator = if header.contains('\t') { '\t' } else { ',' };
+        let names: HashMap<String, usize> = header.split(separator).enumerate()
+            .map(|(position, name)| (name.trim().to_string(), position))
+            .collect();
+        for name in required {
+            if !names.contains_key(*name) {
+                return Err(MalformedLine { file: file.to_string(), line: 1, message: format!("missing column '{}' in header", name) });
+            }
         }
+
+        Ok(Columns { file: file.to_string(), separator, names })
     }
-}
 
-fn get_aka(line: &String) -> TitleAkasRecord {
-    let columns_tab = if line.find('\t') != None { line.split('\t') } else { line.split(',') };
-    let mut columns: Vec<&str> = columns_tab.collect();
-    TitleAkasRecord {
-        isOriginalTitle: columns.pop().unwrap().to_string(),
-        attributes: columns.pop().unwrap().to_string(),
-        types: columns.pop().unwrap().to_string(),
-        language: columns.pop().unwrap().to_string(),
-        region: columns.pop().unwrap().to_string(),
-        title: columns.pop().unwrap().to_string(),
-        ordering: columns.pop().unwrap().to_string(),
-        titleId: columns.pop().unwrap().to_string(),
+    pub fn from_dataset(data: &Dataset, required: &[&str]) -> Result<Columns, MalformedLine> {
+        let header_end = data.iter().position(|&c| c == b'\n').unwrap_or(data.len());
+        Columns::from_header(&data.path, &String::from_utf8_lossy(&data[..header_end]), required)
+    }
+
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+
+    pub fn split<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        line.trim_end_matches('\r').split(self.separator).collect()
+    }
+
+    /// Value of the column `name`, `None` if the dataset doesn't have that column.
+    fn value<'a>(&self, values: &[&'a str], name: &str) -> Result<Option<&'a str>, String> {
+        match self.position(name) {
+            None => Ok(None),
+            Some(position) => match values.get(position) {
+                Some(value) => Ok(Some(value)),
+                None => Err(format!("missing column '{}', expected {} columns and found {}", name, self.names.len(), values.len())),
+            },
+        }
+    }
+
+    fn text(&self, values: &[&str], name: &str) -> Result<String, String> {
+        Ok(self.value(values, name)?.unwrap_or("").to_string())
+    }
+
+    fn number<T: std::str::FromStr>(&self, values: &[&str], name: &str) -> Result<Option<T>, String> {
+        match self.value(values, name)?.and_then(imdb_value) {
+            None => Ok(None),
+            Some(value) => match value.parse() {
+                Ok(number) => Ok(Some(number)),
+                Err(_) => Err(format!("invalid {} '{}'", name, value)),
+            },
+        }
+    }
+
+    /// Error for the line beginning at `index` in `data`.
+    /// The lines before it are counted, so it is only built for the lines that are logged.
+    pub fn malformed_line(&self, data: &[u8], index: usize, message: String) -> MalformedLine {
+        let line = memchr_iter(b'\n', &data[..index]).count() + 1;
+        MalformedLine { file: self.file.clone(), line, message }
     }
-}
 
-fn get_basic(line: &String) -> TitleBasicsRecord {
-    let columns_tab = if line.find('\t') != None { line.split('\t') } else { line.split(',') };
-    let mut columns: Vec<&str> = columns_tab.collect();
-    TitleBasicsRecord {
-        genres: columns.pop().unwrap().to_string(),
-        runtimeMinutes: columns.pop().unwrap().to_string(),
-        endYear: columns.pop().unwrap().to_string(),
-        startYear: columns.pop().unwrap().to_string(),
-        isAdult: columns.pop().unwrap().to_string(),
-        originalTitle: columns.pop().unwrap().to_string(),
-        primaryTitle: columns.pop().unwrap().to_string(),
-        titleType: columns.pop().unwrap().to_string(),
-        tconst: columns.pop().unwrap().to_string(),
+    /// Logs the line beginning at `index` in `data` as malformed. Only the first
+    /// `MALFORMED_LINES_LOGGED` malformed lines of a dataset are logged, the rest are only counted
+    /// (see `log_malformed_lines`).
+    pub fn warn_malformed_line(&self, data: &Dataset, index: usize, message: String) {
+        let count = data.count_malformed_line();
+        if count < MALFORMED_LINES_LOGGED {
+            warn!("{}", self.malformed_line(data, index, message));
+        } else if count == MALFORMED_LINES_LOGGED {
+            warn!(file = %self.file, "more malformed lines, they are skipped without logging them");
+        }
     }
 }
 
-fn get_rating(line: &String) -> ImdbRatings {
-    let columns_tab = if line.find('\t') != None { line.split('\t') } else { line.split(',') };
-    let mut columns: Vec<&str> = columns_tab.collect();
-    ImdbRatings {
-        numVotes: columns.pop().unwrap().to_string(),
-        averageRating: columns.pop().unwrap().to_string(),
-        tconst: columns.pop().unwrap().to_string(),
+static MALFORMED_LINES_LOGGED: usize = 10;
+
+/// Logs how many malformed lines of each dataset were skipped.
+pub fn log_malformed_lines(datasets: &[&Dataset]) {
+    for data in datasets {
+        let malformed_lines = data.malformed_lines();
+        if malformed_lines > 0 {
+            warn!(file = %data.path, malformed_lines, "malformed lines skipped");
+        }
     }
 }
 
-fn search_basics(data: &String, imdbs: &mut Vec::<Imdb>, current_title: &String, current_year: &String, tab_at_the_beginning: bool) -> Result<(), Box<dyn std::error::Error>>  {
-    println!("search_basics...");
-    let title = title_ready(&current_title);
-    let title_tabs = if tab_at_the_beginning { format!("\t{}\t", title_ready(&current_title)) } else { format!("{}\t", title_ready(&current_title)) };
+static BASICS_COLUMNS: &[&str] = &["tconst", "titleType", "primaryTitle", "originalTitle", "startYear"];
+static AKAS_COLUMNS: &[&str] = &["titleId", "title", "region"];
+static RATINGS_COLUMNS: &[&str] = &["tconst", "averageRating", "numVotes"];
+
+pub fn get_aka(line: &str, columns: &Columns) -> Result<TitleAkasRecord, String> {
+    let values = columns.split(line);
+    Ok(TitleAkasRecord {
+        titleId: columns.text(&values, "titleId")?,
+        ordering: columns.text(&values, "ordering")?,
+        title: columns.text(&values, "title")?,
+        region: columns.text(&values, "region")?,
+        language: columns.text(&values, "language")?,
+        types: columns.text(&values, "types")?,
+        attributes: columns.text(&values, "attributes")?,
+        isOriginalTitle: columns.text(&values, "isOriginalTitle")?,
+    })
+}
+
+pub fn get_basic(line: &str, columns: &Columns) -> Result<TitleBasicsRecord, String> {
+    let values = columns.split(line);
+    Ok(TitleBasicsRecord {
+        tconst: columns.text(&values, "tconst")?,
+        titleType: columns.text(&values, "titleType")?,
+        primaryTitle: columns.text(&values, "primaryTitle")?,
+        originalTitle: columns.text(&values, "originalTitle")?,
+        isAdult: columns.number::<u8>(&values, "isAdult")? == Some(1),
+        startYear: columns.number(&values, "startYear")?,
+        endYear: columns.number(&values, "endYear")?,
+        runtimeMinutes: columns.number(&values, "runtimeMinutes")?,
+        genres: parse_genres(&columns.text(&values, "genres")?),
+    })
+}
+
+pub fn get_rating(line: &str, columns: &Columns) -> Result<ImdbRatings, String> {
+    let values = columns.split(line);
+    Ok(ImdbRatings {
+        tconst: columns.text(&values, "tconst")?,
+        averageRating: columns.number(&values, "averageRating")?.unwrap_or(0.0),
+        numVotes: columns.number(&values, "numVotes")?.unwrap_or(0),
+    })
+}
+
+/// Pattern searched in basics for a title (or a tconst when `tab_at_the_beginning` is false).
+fn basics_pattern(current_title: &str, tab_at_the_beginning: bool) -> String {
+    if tab_at_the_beginning { format!("\t{}\t", title_ready(current_title)) } else { format!("{}\t", title_ready(current_title)) }
+}
+
+/// Patterns of the lines checked for similar titles in basics.
+fn basics_year_patterns(current_year: &str, year_tolerance: u16) -> Vec<String> {
+    years_around(current_year, year_tolerance).iter().map(|year| format!("\t{}", year)).collect()
+}
+
+fn akas_pattern(current_title: &str) -> String {
+    remove_diacritics(&title_ready(current_title))
+}
+
+/// Pattern of the lines checked for similar titles in akas.
+fn akas_language_pattern(language: &str) -> String {
+    format!("\t{}\t", language)
+}
+
+/// Where basics are searched: the dataset, its index if it was built and the lines already found
+/// for a batch of nodes (see `BatchIndices`).
+#[derive(Clone, Copy)]
+struct SearchContext<'a> {
+    data: &'a Dataset,
+    index: Option<&'a DatasetIndex>,
+    batch: &'a BatchIndices,
+}
+
+/// Searches `current_title` (or a tconst when `tab_at_the_beginning` is false) in basics.
+/// Titles are compared with `title_similarity`; if the exact search finds nothing, the similar
+/// titles of the index (or the lines of the years around `current_year` without index) are checked too.
+/// `startYear` can differ from `current_year` up to `matching.year_tolerance` years.
+fn search_basics(context: &SearchContext, imdbs: &mut Vec::<Imdb>, current_title: &String, current_year: &str, tab_at_the_beginning: bool, matching: &MatchingConfig) -> Result<(), Error>  {
+    let SearchContext { data, index, batch } = *context;
+    let MatchingConfig { similarity_threshold, year_tolerance } = *matching;
+    debug!(title = %current_title, "search_basics");
+    let title = title_ready(current_title);
+    let title_tabs = basics_pattern(current_title, tab_at_the_beginning);
     let titleTypes:  &[_] = TITLE_TYPES;
-    for i in search_indices(&data, &title_tabs) {
-        let line = get_line_by_index(&data, &i, true);
-        let basic = get_basic(&line);
-
-        if titleTypes.contains(&basic.titleType.as_str()) {
-            if basic.tconst.to_ascii_lowercase() == title 
-                || title_ready(&basic.primaryTitle.to_ascii_lowercase()) == title 
-                || title_ready(&basic.originalTitle.to_ascii_lowercase()) == title {
-                if &basic.startYear == current_year {
-                    if imdbs.iter().find(|&item| item.tconst == basic.tconst) == None {
-                        imdbs.push(Imdb {
-                            tconst: basic.tconst,
-                            titleType: basic.titleType,
-                            
-                            primaryTitle: basic.primaryTitle,
-                            originalTitle: basic.originalTitle,
-                            year: basic.startYear,
-                            runtimeMinutes: basic.runtimeMinutes,
-                            genres: basic.genres,
-                            ratings: None,
-                        });
+    let columns = Columns::from_dataset(data, BASICS_COLUMNS)?;
+    let similar = SimilarTitle::new(current_title);
+    let mut indices = match index {
+        Some(index) if tab_at_the_beginning => index.title_indices(&title),
+        Some(index) => index.id_indices(&title),
+        None => batch.search_indices(data, &title_tabs)?,
+    };
+    let imdbs_len = imdbs.len();
+    let mut fuzzy = false;
+    loop {
+        for i in indices {
+            let line = get_line_by_index(data, &i, true);
+            let basic = match get_basic(&line, &columns) {
+                Ok(basic) => basic,
+                Err(message) => {
+                    columns.warn_malformed_line(data, i, message);
+                    continue;
+                }
+            };
+
+            if titleTypes.contains(&basic.titleType.as_str()) {
+                let similarity = if basic.tconst.to_ascii_lowercase() == title { 1.0 } else {
+                    similar.similarity(&basic.primaryTitle, similarity_threshold).max(similar.similarity(&basic.originalTitle, similarity_threshold))
+                };
+                if similarity >= similarity_threshold {
+                    if let Some(delta) = year_delta(basic.startYear, current_year, year_tolerance) {
+                        if !imdbs.iter().any(|item| item.tconst == basic.tconst) {
+                            imdbs.push(Imdb {
+                                tconst: basic.tconst,
+                                titleType: basic.titleType,
+
+                                primaryTitle: basic.primaryTitle,
+                                originalTitle: basic.originalTitle,
+                                year: basic.startYear,
+                                runtimeMinutes: basic.runtimeMinutes,
+                                genres: basic.genres,
+                                ratings: None,
+                                score: None,
+                                similarity: Some(similarity),
+                                year_delta: Some(delta),
+                            });
+                        }
                     }
                 }
             }
         }
+        if fuzzy || !tab_at_the_beginning || imdbs.len() > imdbs_len {
+            break;
+        }
+        debug!(title = %current_title, "search_basics (similar titles)");
+        fuzzy = true;
+        indices = match index {
+            Some(index) => index.similar_title_indices(current_title, similarity_threshold),
+            None => batch.line_indices_containing(data, &basics_year_patterns(current_year, year_tolerance))?,
+        };
     }
 
     Ok(())
 }
 
-fn search_ratings(data: &String, ratings: &mut ImdbRatings, imdb_id: &String) -> Result<(), Box<dyn std::error::Error>>  {    
-    println!("search_ratings...");
+fn search_ratings(data: &Dataset, index: Option<&DatasetIndex>, batch: &BatchIndices, ratings: &mut ImdbRatings, imdb_id: &String) -> Result<(), Error>  {    
+    debug!(tconst = %imdb_id, "search_ratings");
     let id = format!("{}\t", &imdb_id);
-    for i in search_indices(&data, &id) {
-        let line = get_line_by_index(&data, &i, true);
-        let rating = get_rating(&line);
+    let columns = Columns::from_dataset(data, RATINGS_COLUMNS)?;
+    let indices = match index {
+        Some(index) => index.id_indices(imdb_id),
+        None => batch.search_indices(data, &id)?,
+    };
+    for i in indices {
+        let line = get_line_by_index(data, &i, true);
+        let rating = match get_rating(&line, &columns) {
+            Ok(rating) => rating,
+            Err(message) => {
+                columns.warn_malformed_line(data, i, message);
+                continue;
+            }
+        };
         ratings.tconst = rating.tconst;
         ratings.averageRating = rating.averageRating;
         ratings.numVotes = rating.numVotes;
@@ -197,89 +559,276 @@ fn search_ratings(data: &String, ratings: &mut ImdbRatings, imdb_id: &String) ->
     Ok(())
 }
 
-fn search_akas(data: &String, imdbs: &mut Vec::<Imdb>, current_title: &String) -> Result<(), Box<dyn std::error::Error>> {
-    println!("search_akas...");
-    let title = remove_diacritics(&title_ready(&current_title));
-    println!("{:?}", title);
-    for i in search_indices(&data, &title) {
-        let line = get_line_by_index(&data, &i, true);
-        let aka = get_aka(&line);
-
-        if title_ready(&aka.titleId.to_ascii_lowercase()) == title 
-            || title_ready(&aka.title.to_ascii_lowercase()) == title {
-            if aka.region == LANGUAGE {
-                if imdbs.iter().find(|&item| item.tconst == aka.titleId) == None {
-                    let mut imdb = Imdb { ..Default::default() };
-                    imdb.tconst = aka.titleId;
-                    imdb.titleType = String::new();
-                    imdbs.push(imdb);
+fn search_akas(data: &Dataset, batch: &BatchIndices, imdbs: &mut Vec::<Imdb>, current_title: &String, language: &str, similarity_threshold: f32) -> Result<(), Error> {
+    let title = akas_pattern(current_title);
+    debug!(title = %current_title, pattern = %title, "search_akas");
+    let columns = Columns::from_dataset(data, AKAS_COLUMNS)?;
+    let similar = SimilarTitle::new(current_title);
+    let mut indices = batch.search_indices_without_diacritics(data, &title)?;
+    let imdbs_len = imdbs.len();
+    let mut fuzzy = false;
+    loop {
+        for i in indices {
+            let line = get_line_by_index(data, &i, true);
+            let aka = match get_aka(&line, &columns) {
+                Ok(aka) => aka,
+                Err(message) => {
+                    columns.warn_malformed_line(data, i, message);
+                    continue;
                 }
+            };
+
+            let similarity = if title_ready(&aka.titleId.to_ascii_lowercase()) == title { 1.0 } else { similar.similarity(&aka.title, similarity_threshold) };
+            if similarity >= similarity_threshold && aka.region == language && !imdbs.iter().any(|item| item.tconst == aka.titleId) {
+                let mut imdb = Imdb { ..Default::default() };
+                imdb.tconst = aka.titleId;
+                imdb.titleType = String::new();
+                imdb.similarity = Some(similarity);
+                imdbs.push(imdb);
             }
         }
+        if fuzzy || imdbs.len() > imdbs_len {
+            break;
+        }
+        debug!(title = %current_title, "search_akas (similar titles)");
+        fuzzy = true;
+        indices = batch.line_indices_containing(data, &[akas_language_pattern(language)])?;
     }
     
     Ok(())
 }
 
-pub fn load_basics() -> Result<String, Box<dyn std::error::Error>> {
-    println!("Loading data for imdb basics...");
-    let path = DATA;
-    let data = fs::read_to_string(path).expect("Something went wrong reading the file");
+pub fn load_basics(path: &str, extract_dir: &str) -> Result<Dataset, Error> {
+    info!(path, "loading imdb basics");
+    let data = Dataset::open_extracting_in(path, extract_dir)?;
+    // a dataset without the columns needed can't be searched
+    Columns::from_dataset(&data, BASICS_COLUMNS)?;
 
     Ok(data)
 }
         
-pub fn load_akas() -> Result<String, Box<dyn std::error::Error>> {
-    println!("Loading data for imdb akas...");
-    let path = DATA_AKAS;
-    let data_akas = remove_diacritics(&fs::read_to_string(path).expect("Something went wrong reading the file"));
+pub fn load_akas(path: &str, extract_dir: &str) -> Result<Dataset, Error> {
+    info!(path, "loading imdb akas");
+    let data_akas = Dataset::open_extracting_in(path, extract_dir)?;
+    Columns::from_dataset(&data_akas, AKAS_COLUMNS)?;
 
     Ok(data_akas)    
 }
 
-pub fn load_ratings() -> Result<String, Box<dyn std::error::Error>> {
-    println!("Loading data for imdb ratings...");
-    let path = DATA_RATINGS;
-    let data = fs::read_to_string(path).expect("Something went wrong reading the file");
+pub fn load_ratings(path: &str, extract_dir: &str) -> Result<Dataset, Error> {
+    info!(path, "loading imdb ratings");
+    let data = Dataset::open_extracting_in(path, extract_dir)?;
+    Columns::from_dataset(&data, RATINGS_COLUMNS)?;
 
     Ok(data)
 }
 
-pub fn get_basics(data: &String, (title, title_optional, year): (&String, &String, &str)) -> Vec<Imdb> {
+/// Title, optional title and year of a node, the batch variants take one per node.
+pub type NodeTitle = (String, String, String);
+
+fn find_basics(context: &SearchContext, (title, title_optional, year): (&String, &String, &str), matching: &MatchingConfig) -> Vec<Imdb> {
+    let _movie = info_span!("movie", %title, %year).entered();
     let mut imdbs = Vec::<Imdb>::new();
-    search_basics(&data, &mut imdbs, &title.to_string(), &year.to_string(), true).unwrap();
-    if imdbs.len() == 0 && title_optional.len() > 0 {
-        search_basics(&data, &mut imdbs, &title_optional.to_string(), &year.to_string(), true).unwrap();
+    if let Err(err) = search_basics(context, &mut imdbs, title, year, true, matching) {
+        warn!("{}", err);
     }
+    if imdbs.is_empty() && !title_optional.is_empty() {
+        if let Err(err) = search_basics(context, &mut imdbs, title_optional, year, true, matching) {
+            warn!("{}", err);
+        }
+    }
+    imdbs.sort_by_key(|imdb| imdb.year_delta.map_or(0, |delta| delta.abs()));
     imdbs
 }
 
-pub fn get_akas(data_akas: &String, (title, title_optional): (&String, &String)) -> Vec<Imdb> {
+pub fn get_basics(data: &Dataset, index: Option<&DatasetIndex>, (title, title_optional, year): (&String, &String, &str), matching: &MatchingConfig) -> Vec<Imdb> {
+    find_basics(&SearchContext { data, index, batch: &BatchIndices::default() }, (title, title_optional, year), matching)
+}
+
+/// Same as `get_basics` for every node, in the order of `nodes`. Without index, the titles of all
+/// the nodes are searched in one pass over basics, and the years of the nodes whose titles are
+/// nowhere in another one; the nodes are then checked on `config.threads` workers, matched as
+/// `config.matching` says.
+pub fn get_basics_batch(data: &Dataset, index: Option<&DatasetIndex>, nodes: &[NodeTitle], config: &Config) -> Vec<Vec<Imdb>> {
+    progress_stage("imdb basics", nodes.len());
+    let mut batch = BatchIndices::default();
+    if index.is_none() {
+        let titles = |(title, title_optional, _): &NodeTitle| {
+            let mut titles = vec![basics_pattern(title, true)];
+            if !title_optional.is_empty() {
+                titles.push(basics_pattern(title_optional, true));
+            }
+            titles
+        };
+        debug!(nodes = nodes.len(), "search_basics batch");
+        if let Err(err) = batch.add_titles(data, nodes.iter().flat_map(titles).collect()) {
+            warn!("{}", err);
+        }
+        let years = nodes.iter()
+            .filter(|node| titles(node).iter().any(|title| batch.is_missing(title)))
+            .flat_map(|(_, _, year)| basics_year_patterns(year, config.matching.year_tolerance))
+            .collect::<Vec<String>>();
+        if let Err(err) = batch.add_lines(data, years) {
+            warn!("{}", err);
+        }
+    }
+    let context = SearchContext { data, index, batch: &batch };
+    map_parallel(nodes, config.threads, |(title, title_optional, year)| find_basics(&context, (title, title_optional, year), &config.matching), progress_inc)
+}
+
+fn find_akas(data_akas: &Dataset, batch: &BatchIndices, (title, title_optional): (&String, &String), language: &str, similarity_threshold: f32) -> Vec<Imdb> {
+    let _movie = info_span!("movie", %title).entered();
     let mut imdbs_akas = Vec::<Imdb>::new();
-    search_akas(&data_akas, &mut imdbs_akas, &title.to_string()).unwrap();
-    if imdbs_akas.len() == 0 && title_optional.len() > 0 {
-        search_akas(&data_akas, &mut imdbs_akas, &title_optional.to_string()).unwrap();
+    if let Err(err) = search_akas(data_akas, batch, &mut imdbs_akas, title, language, similarity_threshold) {
+        warn!("{}", err);
+    }
+    if imdbs_akas.is_empty() && !title_optional.is_empty() {
+        if let Err(err) = search_akas(data_akas, batch, &mut imdbs_akas, title_optional, language, similarity_threshold) {
+            warn!("{}", err);
+        }
     }
     imdbs_akas
 }
 
-pub fn get_ratings(data: &String, imdb_id: &String) -> ImdbRatings {
+pub fn get_akas(data_akas: &Dataset, (title, title_optional): (&String, &String), language: &str, similarity_threshold: f32) -> Vec<Imdb> {
+    find_akas(data_akas, &BatchIndices::default(), (title, title_optional), language, similarity_threshold)
+}
+
+/// Same as `get_akas` for every node, in the order of `nodes`, searching the titles of all the
+/// nodes in one pass over akas (and the lines of `config.imdb.language` in another one, if a title
+/// is nowhere).
+pub fn get_akas_batch(data_akas: &Dataset, nodes: &[NodeTitle], config: &Config) -> Vec<Vec<Imdb>> {
+    let (language, similarity_threshold) = (config.imdb.language.as_str(), config.matching.similarity_threshold);
+    progress_stage("imdb akas", nodes.len());
+    let mut batch = BatchIndices::default();
+    let titles = nodes.iter()
+        .flat_map(|(title, title_optional, _)| [title, title_optional])
+        .filter(|title| !title.is_empty())
+        .map(|title| akas_pattern(title.as_str()))
+        .collect::<Vec<String>>();
+    debug!(nodes = nodes.len(), "search_akas batch");
+    if let Err(err) = batch.add_titles_without_diacritics(data_akas, titles.clone()) {
+        warn!("{}", err);
+    }
+    if titles.iter().any(|title| batch.is_missing(title)) {
+        if let Err(err) = batch.add_lines(data_akas, vec![akas_language_pattern(language)]) {
+            warn!("{}", err);
+        }
+    }
+    map_parallel(nodes, config.threads, |(title, title_optional, _)| find_akas(data_akas, &batch, (title, title_optional), language, similarity_threshold), progress_inc)
+}
+
+fn find_ratings(data: &Dataset, index: Option<&DatasetIndex>, batch: &BatchIndices, imdb_id: &String) -> ImdbRatings {
     let mut ratings = ImdbRatings { ..Default::default() };
-    search_ratings(&data, &mut ratings, &imdb_id.to_string()).unwrap();
+    if let Err(err) = search_ratings(data, index, batch, &mut ratings, imdb_id) {
+        warn!("{}", err);
+    }
     ratings
 }
 
-pub fn update_imdbs_with_akas(imdbs: &mut Vec<Imdb>, imdbs_akas: &Vec<Imdb>, year: &str, data: &String) -> () {
-    if imdbs_akas.len() > 0 {
+pub fn get_ratings(data: &Dataset, index: Option<&DatasetIndex>, imdb_id: &String) -> ImdbRatings {
+    find_ratings(data, index, &BatchIndices::default(), imdb_id)
+}
+
+/// Same as `get_ratings` for every tconst of `imdb_ids`, searching them in one pass over ratings when there is no index,
+/// on `config.threads` workers.
+pub fn get_ratings_batch(data: &Dataset, index: Option<&DatasetIndex>, imdb_ids: &[String], config: &Config) -> HashMap<String, ImdbRatings> {
+    progress_stage("imdb ratings", imdb_ids.len());
+    let mut batch = BatchIndices::default();
+    if index.is_none() {
+        debug!(titles = imdb_ids.len(), "search_ratings batch");
+        if let Err(err) = batch.add_titles(data, imdb_ids.iter().map(|imdb_id| format!("{}\t", imdb_id)).collect()) {
+            warn!("{}", err);
+        }
+    }
+    let ratings = map_parallel(imdb_ids, config.threads, |imdb_id| find_ratings(data, index, &batch, imdb_id), progress_inc);
+    imdb_ids.iter().cloned().zip(ratings).collect()
+}
+
+/// The basics of `tconst` whatever its title type and year, without ratings.
+fn find_basics_by_tconst(data: &Dataset, index: Option<&DatasetIndex>, batch: &BatchIndices, columns: &Columns, tconst: &str) -> Result<Option<Imdb>, Error> {
+    let id = title_ready(tconst);
+    let indices = match index {
+        Some(index) => index.id_indices(&id),
+        None => batch.search_indices(data, &basics_pattern(&id, false))?,
+    };
+    for i in indices {
+        let line = get_line_by_index(data, &i, true);
+        let basic = match get_basic(&line, columns) {
+            Ok(basic) if basic.tconst.eq_ignore_ascii_case(tconst) => basic,
+            Ok(_) => continue,
+            Err(message) => {
+                columns.warn_malformed_line(data, i, message);
+                continue;
+            }
+        };
+        return Ok(Some(Imdb {
+            tconst: basic.tconst,
+            titleType: basic.titleType,
+
+            primaryTitle: basic.primaryTitle,
+            originalTitle: basic.originalTitle,
+            year: basic.startYear,
+            runtimeMinutes: basic.runtimeMinutes,
+            genres: basic.genres,
+            ratings: None,
+            score: None,
+            similarity: None,
+            year_delta: None,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// The basics and ratings of imdb, with their indexes if they were built.
+pub struct ImdbDatasets<'a> {
+    pub basics: &'a Dataset,
+    pub index_basics: Option<&'a DatasetIndex>,
+    pub ratings: &'a Dataset,
+    pub index_ratings: Option<&'a DatasetIndex>,
+}
+
+/// The basics and ratings of `tconst` whatever its title type and year, for a tconst given by the user.
+pub fn get_imdb_by_tconst(datasets: &ImdbDatasets, tconst: &str) -> Result<Option<Imdb>, Error> {
+    let columns = Columns::from_dataset(datasets.basics, BASICS_COLUMNS)?;
+    Ok(find_basics_by_tconst(datasets.basics, datasets.index_basics, &BatchIndices::default(), &columns, tconst)?.map(|mut imdb| {
+        let ratings = get_ratings(datasets.ratings, datasets.index_ratings, &imdb.tconst);
+        imdb.ratings = if ratings.tconst.is_empty() { None } else { Some(Rating::from(&ratings)) };
+        imdb
+    }))
+}
+
+/// The basics of every tconst of `tconsts` (`None` if it isn't in basics), searching them in one
+/// pass over basics when there is no index, on `config.threads` workers. Their ratings are left to
+/// `get_ratings_batch`.
+pub fn get_basics_by_tconst_batch(data: &Dataset, index: Option<&DatasetIndex>, tconsts: &[String], config: &Config) -> Result<Vec<Option<Imdb>>, Error> {
+    let columns = Columns::from_dataset(data, BASICS_COLUMNS)?;
+    progress_stage("imdb tconsts", tconsts.len());
+    let mut batch = BatchIndices::default();
+    if index.is_none() {
+        debug!(titles = tconsts.len(), "search_basics by tconst batch");
+        if let Err(err) = batch.add_titles(data, tconsts.iter().map(|tconst| basics_pattern(&title_ready(tconst), false)).collect()) {
+            warn!("{}", err);
+        }
+    }
+    map_parallel(tconsts, config.threads, |tconst| find_basics_by_tconst(data, index, &batch, &columns, tconst), progress_inc).into_iter().collect()
+}
+
+fn add_basics_of_akas(imdbs: &mut Vec<Imdb>, imdbs_akas: &Vec<Imdb>, year: &str, context: &SearchContext, year_tolerance: u16) {
+    let matching = MatchingConfig { similarity_threshold: 1.0, year_tolerance };
+    if !imdbs_akas.is_empty() {
         for aka in imdbs_akas {
 
             let mut basic_imdbs = Vec::<Imdb>::new();
-            println!("search_basics (akas)...");
-            search_basics(&data, &mut basic_imdbs, &aka.tconst, &year.to_string(), false).unwrap();
+            debug!(tconst = %aka.tconst, "search_basics (akas)");
+            if let Err(err) = search_basics(context, &mut basic_imdbs, &aka.tconst, year, false, &matching) {
+                warn!("{}", err);
+            }
 
-            if basic_imdbs.len() > 0 {
+            if !basic_imdbs.is_empty() {
                 for basic_imdb in basic_imdbs {
-                    if imdbs.iter().find(|&item| item.tconst == basic_imdb.tconst) == None {
+                    if !imdbs.iter().any(|item| item.tconst == basic_imdb.tconst) {
                         imdbs.push(Imdb {
                             tconst: String::from(&basic_imdb.tconst),
                             titleType: String::from(&basic_imdb.titleType),
@@ -290,10 +839,109 @@ pub fn update_imdbs_with_akas(imdbs: &mut Vec<Imdb>, imdbs_akas: &Vec<Imdb>, yea
                             runtimeMinutes: basic_imdb.runtimeMinutes,
                             genres: basic_imdb.genres,
                             ratings: None,
+                            score: None,
+                            similarity: aka.similarity,
+                            year_delta: basic_imdb.year_delta,
                         });
                     }
                 }
             }
         }
     }
+    imdbs.sort_by_key(|imdb| imdb.year_delta.map_or(0, |delta| delta.abs()));
+}
+
+pub fn update_imdbs_with_akas(imdbs: &mut Vec<Imdb>, imdbs_akas: &Vec<Imdb>, year: &str, data: &Dataset, index: Option<&DatasetIndex>, year_tolerance: u16) {
+    add_basics_of_akas(imdbs, imdbs_akas, year, &SearchContext { data, index, batch: &BatchIndices::default() }, year_tolerance)
+}
+
+/// Same as `update_imdbs_with_akas` for every node, `imdbs` and `imdbs_akas` having one item per node.
+/// Without index, the tconsts of all the akas are searched in one pass over basics.
+pub fn update_imdbs_with_akas_batch(imdbs: &mut [Vec<Imdb>], imdbs_akas: &[Vec<Imdb>], nodes: &[NodeTitle], data: &Dataset, index: Option<&DatasetIndex>, year_tolerance: u16) {
+    let mut batch = BatchIndices::default();
+    if index.is_none() {
+        if let Err(err) = batch.add_titles(data, imdbs_akas.iter().flatten().map(|aka| basics_pattern(&aka.tconst, false)).collect()) {
+            warn!("{}", err);
+        }
+    }
+    let context = SearchContext { data, index, batch: &batch };
+    for ((imdbs, imdbs_akas), (_, _, year)) in imdbs.iter_mut().zip(imdbs_akas).zip(nodes) {
+        add_basics_of_akas(imdbs, imdbs_akas, year, &context, year_tolerance);
+    }
+}
+
+/// Sorts the candidates by year delta (exact years first) and number of votes and, when there is
+/// more than one, removes the ones with less than `min_votes` (unless none of them has enough votes).
+pub fn rank_imdbs_by_votes(imdbs: &mut Vec<Imdb>, min_votes: u32) {
+    let votes = |imdb: &Imdb| imdb.ratings.as_ref().map_or(0, |rating| rating.numVotes);
+    let delta = |imdb: &Imdb| imdb.year_delta.map_or(0, |delta| delta.abs());
+    imdbs.sort_by(|a, b| delta(a).cmp(&delta(b)).then(votes(b).cmp(&votes(a))));
+    if imdbs.len() > 1 && imdbs.iter().any(|imdb| votes(imdb) >= min_votes) {
+        imdbs.retain(|imdb| votes(imdb) >= min_votes);
+    }
+}
+
+pub fn build_basics_index(data: &Dataset, path: &str) -> Result<DatasetIndex, Error> {
+    info!("building index for imdb basics");
+    let columns = Columns::from_dataset(data, BASICS_COLUMNS)?;
+    let title_columns = [columns.position("primaryTitle").unwrap(), columns.position("originalTitle").unwrap()];
+    build_index(path, data, columns.position("tconst").unwrap(), &title_columns)
+}
+
+pub fn build_ratings_index(data: &Dataset, path: &str) -> Result<DatasetIndex, Error> {
+    info!("building index for imdb ratings");
+    let columns = Columns::from_dataset(data, RATINGS_COLUMNS)?;
+    build_index(path, data, columns.position("tconst").unwrap(), &[])
+}
+
+/// Writes to `output` the basics of `input` (tsv or tsv.gz) with a valid title type.
+/// Returns the tconsts written.
+pub fn reduce_basics(input: &str, output: &str) -> Result<HashSet<String>, Error> {
+    info!(input, output, "reducing imdb basics");
+    let titleTypes:  &[_] = TITLE_TYPES;
+    let mut tconsts = HashSet::<String>::new();
+    let written = |err| Error::io(output, err);
+    let mut writer = BufWriter::new(fs::File::create(output).map_err(written)?);
+    let mut lines = open_lines(input)?.lines();
+    let header = lines.next().unwrap_or(Ok(String::new())).map_err(|err| Error::io(input, err))?;
+    let columns = Columns::from_header(input, &header, &["tconst", "titleType"])?;
+    writeln!(writer, "{}", header).map_err(written)?;
+    for (number, line) in lines.enumerate() {
+        let line = line.map_err(|err| Error::io(input, err))?;
+        let values = columns.split(&line);
+        let titleType = columns.value(&values, "titleType").unwrap_or(None).unwrap_or("");
+        if titleTypes.contains(&titleType) {
+            // the header is line 1
+            let tconst = columns.text(&values, "tconst").map_err(|message| MalformedLine { file: input.to_string(), line: number + 2, message })?;
+            tconsts.insert(tconst);
+            writeln!(writer, "{}", line).map_err(written)?;
+        }
+    }
+    writer.flush().map_err(written)?;
+
+    Ok(tconsts)
+}
+
+/// Writes to `output` the akas of `input` (tsv or tsv.gz) for the region `language`
+/// whose title is in `tconsts`.
+pub fn reduce_akas(input: &str, output: &str, language: &str, tconsts: &HashSet<String>) -> Result<(), Error> {
+    info!(input, output, "reducing imdb akas");
+    let written = |err| Error::io(output, err);
+    let mut writer = BufWriter::new(fs::File::create(output).map_err(written)?);
+    let mut lines = open_lines(input)?.lines();
+    let header = lines.next().unwrap_or(Ok(String::new())).map_err(|err| Error::io(input, err))?;
+    let columns = Columns::from_header(input, &header, AKAS_COLUMNS)?;
+    writeln!(writer, "{}", header).map_err(written)?;
+    for line in lines {
+        let line = line.map_err(|err| Error::io(input, err))?;
+        let values = columns.split(&line);
+        let region = columns.value(&values, "region").unwrap_or(None);
+        let titleId = columns.value(&values, "titleId").unwrap_or(None).unwrap_or("");
+        if region == Some(language) && tconsts.contains(titleId) {
+            writeln!(writer, "{}", line).map_err(written)?;
+        }
+    }
+    writer.flush().map_err(written)?;
+
+    Ok(())
 }
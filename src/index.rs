@@ -0,0 +1,455 @@
+use crate::utils::{title_ready, normalize_title, SimilarTitle};
+use crate::dataset::{DatasetData, map};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::fs;
+use std::io::{self, prelude::*, BufReader, BufWriter, SeekFrom};
+use std::path::Path;
+use crate::error::Error;
+use tracing::warn;
+
+// the version changes with the format of the index, older indexes have to be built again
+static INDEX_HEADER: &str = "notitia-index-4";
+// bytes of the beginning and of the end of the dataset in its fingerprint
+static FINGERPRINT_BYTES: usize = 64 * 1024;
+// records of each section sorted in memory while an index is built, about 64 MB of titles
+static INDEX_CHUNK_RECORDS: usize = 1 << 20;
+
+/// Persistent index for a tsv dataset: normalized title -> line offsets and id -> line offset.
+/// Offsets are the beginning of each line, the same values `search_indices` returns.
+///
+/// The index is kept in the file it is built in, memory mapped like the datasets, and searched
+/// there with binary searches, so it doesn't need to fit in memory. The file is a header line
+/// (`notitia-index-4\t<data_len>\t<fingerprint>`) followed by the offsets of the titles, ids and
+/// similar titles sections. Each section is the number of entries, the offsets of the entries and
+/// the entries (`key_len: u32, key, indices_len: u32, indices: u64...`), all little endian.
+/// Titles and ids are sorted by key; similar titles (titles normalized for fuzzy comparisons, see
+/// `normalize_title`) by length in chars and then by key, so only the titles of similar lengths
+/// are compared.
+pub struct DatasetIndex {
+    pub data_len: usize,
+    data: DatasetData,
+    titles: Section,
+    ids: Section,
+    similar_titles: Section,
+}
+impl fmt::Debug for DatasetIndex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DatasetIndex")
+            .field("data_len", &self.data_len)
+            .field("titles", &self.titles.count)
+            .field("ids", &self.ids.count)
+            .field("similar_titles", &self.similar_titles.count)
+            .finish()
+    }
+}
+impl PartialEq for DatasetIndex {
+    fn eq(&self, other: &DatasetIndex) -> bool {
+        self.data[..] == other.data[..]
+    }
+}
+impl DatasetIndex {
+    fn new(data: DatasetData, data_len: usize) -> Option<DatasetIndex> {
+        let header_end = data.iter().position(|&c| c == b'\n')? + 1;
+        let section = |number: usize| Section::read(&data, read_u64(&data, header_end + number * 8)?);
+        let (titles, ids, similar_titles) = (section(0)?, section(1)?, section(2)?);
+
+        Some(DatasetIndex { data_len, data, titles, ids, similar_titles })
+    }
+
+    pub fn title_indices(&self, title: &str) -> Vec<usize> {
+        match self.find(&self.titles, title_ready(title).as_bytes()) {
+            Some(entry) => entry.indices(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Titles of the index with their line offsets, sorted.
+    pub fn titles(&self) -> impl Iterator<Item = (String, Vec<usize>)> + '_ {
+        self.entries(&self.titles).map(|entry| (String::from_utf8_lossy(entry.key).to_string(), entry.indices()))
+    }
+
+    /// Lines of the titles similar to `title` (see `title_similarity`).
+    pub fn similar_title_indices(&self, title: &str, threshold: f32) -> Vec<usize> {
+        let similar = SimilarTitle::new(title);
+        let len = similar.normalized().chars().count();
+        // with `threshold` the lengths can't differ more than (1 - threshold) of the longest one
+        let (min_len, max_len) = if threshold > 0.0 {
+            ((len as f32 * threshold).floor() as usize, (len as f32 / threshold).ceil() as usize)
+        } else {
+            (0, usize::MAX)
+        };
+        let first = self.partition_point(&self.similar_titles, |entry| entry.chars() < min_len);
+        let mut indices: Vec<usize> = (first..self.similar_titles.count)
+            .map_while(|number| self.entry(&self.similar_titles, number))
+            .take_while(|entry| entry.chars() <= max_len)
+            .filter(|entry| similar.similarity_normalized(&String::from_utf8_lossy(entry.key), threshold) >= threshold)
+            .flat_map(|entry| entry.indices())
+            .collect();
+        indices.sort();
+        indices.dedup();
+        indices
+    }
+
+    pub fn id_indices(&self, id: &String) -> Vec<usize> {
+        match self.find(&self.ids, id.as_bytes()) {
+            Some(entry) => entry.indices(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Entry `number` of `section`, `None` if the index is corrupt.
+    fn entry(&self, section: &Section, number: usize) -> Option<Entry<'_>> {
+        if number >= section.count {
+            return None;
+        }
+        let offset = read_u64(&self.data, section.entries + number * 8)?;
+        Entry::read(&self.data, offset)
+    }
+
+    fn entries<'a>(&'a self, section: &'a Section) -> impl Iterator<Item = Entry<'a>> + 'a {
+        (0..section.count).map_while(move |number| self.entry(section, number))
+    }
+
+    fn find(&self, section: &Section, key: &[u8]) -> Option<Entry<'_>> {
+        let (mut low, mut high) = (0, section.count);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let entry = self.entry(section, middle)?;
+            match entry.key.cmp(key) {
+                Ordering::Less => low = middle + 1,
+                Ordering::Greater => high = middle,
+                Ordering::Equal => return Some(entry),
+            }
+        }
+        None
+    }
+
+    /// First entry of `section` for which `before` is false (`before` has to be true for all the
+    /// entries before it and false for all the entries after it).
+    fn partition_point(&self, section: &Section, before: impl Fn(&Entry) -> bool) -> usize {
+        let (mut low, mut high) = (0, section.count);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            match self.entry(section, middle) {
+                Some(entry) if before(&entry) => low = middle + 1,
+                _ => high = middle,
+            }
+        }
+        low
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Section {
+    count: usize,
+    /// Offset of the table with the offsets of the entries.
+    entries: usize,
+}
+impl Section {
+    fn read(data: &[u8], offset: usize) -> Option<Section> {
+        let count = read_u64(data, offset)?;
+        let entries = offset + 8;
+        if entries.checked_add(count.checked_mul(8)?)? > data.len() {
+            return None;
+        }
+        Some(Section { count, entries })
+    }
+}
+
+struct Entry<'a> {
+    key: &'a [u8],
+    indices: &'a [u8],
+}
+impl Entry<'_> {
+    fn read(data: &[u8], offset: usize) -> Option<Entry<'_>> {
+        let key_len = read_u32(data, offset)?;
+        let key_end = (offset + 4).checked_add(key_len)?;
+        let key = data.get(offset + 4..key_end)?;
+        let indices_len = read_u32(data, key_end)?;
+        let indices = data.get(key_end + 4..(key_end + 4).checked_add(indices_len.checked_mul(8)?)?)?;
+        Some(Entry { key, indices })
+    }
+
+    fn indices(&self) -> Vec<usize> {
+        self.indices.chunks_exact(8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap_or_default()) as usize).collect()
+    }
+
+    fn chars(&self) -> usize {
+        String::from_utf8_lossy(self.key).chars().count()
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<usize> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?) as usize)
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<usize> {
+    let bytes = data.get(offset..offset.checked_add(8)?)?;
+    usize::try_from(u64::from_le_bytes(bytes.try_into().ok()?)).ok()
+}
+
+/// FNV-1a hash of the length, the beginning and the end of `data`, to tell a dataset from
+/// another one of the same length without reading all of it.
+fn fingerprint(data: &[u8]) -> u64 {
+    let head = &data[..data.len().min(FINGERPRINT_BYTES)];
+    let tail = &data[data.len().saturating_sub(FINGERPRINT_BYTES)..];
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in (data.len() as u64).to_le_bytes().iter().chain(head).chain(tail) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn header(data: &[u8]) -> String {
+    format!("{}\t{}\t{:016x}\n", INDEX_HEADER, data.len(), fingerprint(data))
+}
+
+pub fn index_path(data_path: &str) -> String {
+    format!("{}.idx", data_path)
+}
+
+/// Temporary files of the index being built, removed when it is dropped (built or not).
+#[derive(Default)]
+struct TemporaryFiles(Vec<String>);
+impl TemporaryFiles {
+    fn create(&mut self, path: String) -> io::Result<(String, BufWriter<fs::File>)> {
+        let file = fs::File::create(&path)?;
+        self.0.push(path.clone());
+        Ok((path, BufWriter::new(file)))
+    }
+}
+impl Drop for TemporaryFiles {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// A key of a section and the line it was found in. The records are sorted by rank (the length in
+/// chars of the similar titles, 0 for titles and ids), key and line.
+type Record = (u32, String, u64);
+
+fn write_record(writer: &mut impl Write, (rank, key, index): &Record) -> io::Result<()> {
+    writer.write_all(&rank.to_le_bytes())?;
+    writer.write_all(&(key.len() as u32).to_le_bytes())?;
+    writer.write_all(key.as_bytes())?;
+    writer.write_all(&index.to_le_bytes())
+}
+
+fn read_record(reader: &mut impl Read) -> io::Result<Option<Record>> {
+    let mut rank = [0; 4];
+    match reader.read_exact(&mut rank) {
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+    let mut key_len = [0; 4];
+    reader.read_exact(&mut key_len)?;
+    let mut key = vec![0; u32::from_le_bytes(key_len) as usize];
+    reader.read_exact(&mut key)?;
+    let mut index = [0; 8];
+    reader.read_exact(&mut index)?;
+    let key = String::from_utf8(key).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok(Some((u32::from_le_bytes(rank), key, u64::from_le_bytes(index))))
+}
+
+/// Records of a section, sorted in runs of `chunk_records` written to temporary files, so only
+/// one run is in memory while the dataset is read.
+struct Runs {
+    name: String,
+    records: Vec<Record>,
+    chunk_records: usize,
+    files: Vec<String>,
+}
+impl Runs {
+    fn new(name: String, chunk_records: usize) -> Runs {
+        Runs { name, records: Vec::new(), chunk_records, files: Vec::new() }
+    }
+
+    fn push(&mut self, record: Record, temporary: &mut TemporaryFiles) -> io::Result<()> {
+        self.records.push(record);
+        if self.records.len() >= self.chunk_records {
+            self.write_run(temporary)?;
+        }
+        Ok(())
+    }
+
+    fn write_run(&mut self, temporary: &mut TemporaryFiles) -> io::Result<()> {
+        if self.records.is_empty() {
+            return Ok(());
+        }
+        self.records.sort_unstable();
+        self.records.dedup();
+        let (path, mut writer) = temporary.create(format!("{}.run{}", self.name, self.files.len()))?;
+        for record in self.records.drain(..) {
+            write_record(&mut writer, &record)?;
+        }
+        writer.flush()?;
+        self.files.push(path);
+        Ok(())
+    }
+
+    /// Merges the runs and writes the entries of the section (each key with its lines, or only
+    /// with its first line if `first_line_only`) to `entries` and their offsets, relative to the
+    /// first one, to `offsets`. Returns the number of entries.
+    fn merge(mut self, first_line_only: bool, temporary: &mut TemporaryFiles, entries: &mut impl Write, offsets: &mut impl Write) -> io::Result<usize> {
+        self.write_run(temporary)?;
+        let mut readers = self.files.iter()
+            .map(|path| fs::File::open(path).map(BufReader::new))
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut heap = BinaryHeap::new();
+        for (run, reader) in readers.iter_mut().enumerate() {
+            if let Some(record) = read_record(reader)? {
+                heap.push(Reverse((record, run)));
+            }
+        }
+
+        let (mut count, mut offset) = (0, 0);
+        let mut current: Option<(u32, String, Vec<u64>)> = None;
+        while let Some(Reverse(((rank, key, index), run))) = heap.pop() {
+            if let Some(record) = read_record(&mut readers[run])? {
+                heap.push(Reverse((record, run)));
+            }
+            match &mut current {
+                Some((current_rank, current_key, indices)) if *current_rank == rank && *current_key == key => {
+                    if !first_line_only && indices.last() != Some(&index) {
+                        indices.push(index);
+                    }
+                }
+                _ => {
+                    if let Some((_, key, indices)) = current.replace((rank, key, vec![index])) {
+                        offsets.write_all(&(offset as u64).to_le_bytes())?;
+                        offset += write_entry(entries, &key, &indices)?;
+                        count += 1;
+                    }
+                }
+            }
+        }
+        if let Some((_, key, indices)) = current {
+            offsets.write_all(&(offset as u64).to_le_bytes())?;
+            write_entry(entries, &key, &indices)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// Writes an entry of a section and returns its length.
+fn write_entry(writer: &mut impl Write, key: &str, indices: &[u64]) -> io::Result<usize> {
+    writer.write_all(&(key.len() as u32).to_le_bytes())?;
+    writer.write_all(key.as_bytes())?;
+    writer.write_all(&(indices.len() as u32).to_le_bytes())?;
+    for index in indices {
+        writer.write_all(&index.to_le_bytes())?;
+    }
+    Ok(4 + key.len() + 4 + indices.len() * 8)
+}
+
+/// Merges the runs of a section and appends the section to `output`. Returns its offset.
+fn append_section(output: &mut BufWriter<fs::File>, runs: Runs, first_line_only: bool, temporary: &mut TemporaryFiles) -> io::Result<u64> {
+    let (entries_path, mut entries) = temporary.create(format!("{}.entries", runs.name))?;
+    let (offsets_path, mut offsets) = temporary.create(format!("{}.offsets", runs.name))?;
+    let count = runs.merge(first_line_only, temporary, &mut entries, &mut offsets)?;
+    entries.flush()?;
+    offsets.flush()?;
+
+    let section = output.stream_position()?;
+    let first_entry = section + 8 + count as u64 * 8;
+    output.write_all(&(count as u64).to_le_bytes())?;
+    let mut offsets = BufReader::new(fs::File::open(&offsets_path)?);
+    let mut offset = [0; 8];
+    for _ in 0..count {
+        offsets.read_exact(&mut offset)?;
+        output.write_all(&(first_entry + u64::from_le_bytes(offset)).to_le_bytes())?;
+    }
+    io::copy(&mut fs::File::open(&entries_path)?, output)?;
+    Ok(section)
+}
+
+/// Builds in `path` the index of a tsv dataset by `id_column` and by the normalized title
+/// of `title_columns`, and maps it. The header line is skipped.
+pub fn build_index(path: &str, data: &[u8], id_column: usize, title_columns: &[usize]) -> Result<DatasetIndex, Error> {
+    build_index_in_chunks(path, data, id_column, title_columns, INDEX_CHUNK_RECORDS)
+}
+
+/// `build_index` keeping at most `chunk_records` records of each section in memory: the keys of
+/// the dataset are sorted in chunks written to temporary files next to `path`, merged in the
+/// sections of the index.
+pub fn build_index_in_chunks(path: &str, data: &[u8], id_column: usize, title_columns: &[usize], chunk_records: usize) -> Result<DatasetIndex, Error> {
+    write_index(path, data, id_column, title_columns, chunk_records).map_err(|err| Error::io(path, err))?;
+    load_index(path, data)?.ok_or_else(|| Error::io(path, io::Error::new(io::ErrorKind::InvalidData, "index not written")))
+}
+
+fn write_index(path: &str, data: &[u8], id_column: usize, title_columns: &[usize], chunk_records: usize) -> io::Result<()> {
+    let mut temporary = TemporaryFiles::default();
+    let mut titles = Runs::new(format!("{}.titles", path), chunk_records);
+    let mut ids = Runs::new(format!("{}.ids", path), chunk_records);
+    let mut similar_titles = Runs::new(format!("{}.similar", path), chunk_records);
+
+    let mut line_begin = match data.iter().position(|&c| c == b'\n') {
+        Some(header_end) => header_end + 1,
+        None => data.len(),
+    };
+    while line_begin < data.len() {
+        let line_end = match data[line_begin..].iter().position(|&c| c == b'\n') {
+            Some(end) => line_begin + end,
+            None => data.len(),
+        };
+        let line = String::from_utf8_lossy(&data[line_begin..line_end]);
+        let line = line.trim_end_matches('\r');
+        let columns: Vec<&str> = line.split('\t').collect();
+        let index = line_begin as u64;
+        if let Some(id) = columns.get(id_column) {
+            if !id.is_empty() {
+                ids.push((0, id.to_string(), index), &mut temporary)?;
+            }
+        }
+        for title_column in title_columns {
+            if let Some(title) = columns.get(*title_column) {
+                let title = title_ready(title);
+                let normalized = normalize_title(&title);
+                similar_titles.push((normalized.chars().count() as u32, normalized, index), &mut temporary)?;
+                titles.push((0, title, index), &mut temporary)?;
+            }
+        }
+        line_begin = line_end + 1;
+    }
+
+    let (temporary_path, mut output) = temporary.create(format!("{}.tmp", path))?;
+    output.write_all(header(data).as_bytes())?;
+    let sections = output.stream_position()?;
+    output.write_all(&[0; 3 * 8])?;
+    let titles = append_section(&mut output, titles, false, &mut temporary)?;
+    let ids = append_section(&mut output, ids, true, &mut temporary)?;
+    let similar_titles = append_section(&mut output, similar_titles, false, &mut temporary)?;
+    output.seek(SeekFrom::Start(sections))?;
+    for section in [titles, ids, similar_titles] {
+        output.write_all(&section.to_le_bytes())?;
+    }
+    output.into_inner().map_err(|err| err.into_error())?.sync_all()?;
+    fs::rename(&temporary_path, path)
+}
+
+/// Maps the index saved for the dataset `data`.
+/// Returns `None` if there is no index or it was built for a different version of the dataset
+/// (another length or another fingerprint of its beginning and end).
+pub fn load_index(path: &str, data: &[u8]) -> Result<Option<DatasetIndex>, Error> {
+    if !Path::new(path).exists() {
+        return Ok(None);
+    }
+    let file = fs::File::open(path).map_err(|err| Error::io(path, err))?;
+    let index = map(path, file)?;
+
+    if !index.starts_with(header(data).as_bytes()) {
+        warn!(path, "index out of date, run the index command again");
+        return Ok(None);
+    }
+    match DatasetIndex::new(index, data.len()) {
+        Some(index) => Ok(Some(index)),
+        None => Err(Error::io(path, io::Error::new(io::ErrorKind::InvalidData, "corrupt index, run the index command again"))),
+    }
+}
@@ -1,11 +1,8 @@
 use crate::imdb::{Imdb};
+use crate::error::Error;
 use std::fs;
 use serde::{Deserialize, Serialize};
-
-static JUSTWATCH_EDGES: &[&str] = &[
-    "files/justwatch/all.netflix.json",
-    "files/justwatch/all.disney+.json",
-    ];
+use tracing::{debug, info, trace};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TvPackage {
@@ -107,30 +104,21 @@ pub struct JustWatchList {
 }
 
 #[derive(Debug, Deserialize, Clone)]
-#[derive(Serialize)]
+#[derive(Serialize, Default)]
 pub struct Justwatch {
     pub id: String, // "id": "tm372685"
 
     pub edge: Option<Edge>,
 }
-impl Default for Justwatch {
-    fn default() -> Justwatch {
-        Justwatch {
-            id: String::new(),
 
-            edge: None,
-        }
-    }
-}
-
-pub fn load_justwatch_file(justwatch_fileName: &str) -> Result<Vec<Edge>, Box<dyn std::error::Error>> {
-    let justwatch_data = fs::read_to_string(justwatch_fileName.to_string()).expect("Something went wrong reading the file");
-    let justwatch_edges: Vec<Edge> = serde_json::from_str(&format!("{}", justwatch_data))?;
+pub fn load_justwatch_file(justwatch_fileName: &str) -> Result<Vec<Edge>, Error> {
+    let justwatch_data = fs::read_to_string(justwatch_fileName).map_err(|source| Error::MissingDataset { path: justwatch_fileName.to_string(), source })?;
+    let justwatch_edges: Vec<Edge> = serde_json::from_str(&justwatch_data).map_err(|err| Error::json(justwatch_fileName, err))?;
     
     Ok(justwatch_edges)
 }
 
-pub fn load_justwatch_files(justwatch_data_files: &[&str]) -> Result<Vec<Edge>, Box<dyn std::error::Error>> {
+pub fn load_justwatch_files(justwatch_data_files: &[&str]) -> Result<Vec<Edge>, Error> {
     let mut justwatch_edges: Vec<Edge> = vec!();
     for justwatch_fileName in justwatch_data_files {
         let justwatch_edges_temp = load_justwatch_file(justwatch_fileName)?;
@@ -142,29 +130,46 @@ pub fn load_justwatch_files(justwatch_data_files: &[&str]) -> Result<Vec<Edge>,
     Ok(justwatch_edges)
 }
 
-pub fn load_justwatchs() -> Result<Vec<Edge>, Box<dyn std::error::Error>> {
-    println!("Loading data for just watch...");
-    load_justwatch_files(&JUSTWATCH_EDGES)
+pub fn load_justwatchs(justwatch_data_files: &[String]) -> Result<Vec<Edge>, Error> {
+    info!(files = justwatch_data_files.len(), "loading justwatch");
+    let justwatch_data_files: Vec<&str> = justwatch_data_files.iter().map(|file| file.as_str()).collect();
+    load_justwatch_files(&justwatch_data_files)
+}
+
+/// Justwatch of an edge, without its credits.
+fn new_justwatch(edge: &Edge) -> Justwatch {
+    let mut edge_copy = edge.clone();
+    edge_copy.node.content.credits = Vec::new();
+    Justwatch {
+        id: String::from(&edge.node.id),
+
+        edge: Some(edge_copy),
+    }
+}
+
+/// The justwatch `id`, for an id given by the user.
+pub fn get_justwatch_by_id(justwatch_edges: &[Edge], id: &str) -> Option<Justwatch> {
+    justwatch_edges.iter().find(|edge| edge.node.id == id).map(new_justwatch)
 }
 
-pub fn get_justwatchs(justwatch_edges: &Vec<Edge>, imdbs: &Vec<Imdb>, title: &String) -> Vec<Justwatch> {
+/// Justwatchs of the imdb `tconst` (`externalIds.imdbId`), without searching the title.
+pub fn get_justwatchs_by_tconst(justwatch_edges: &[Edge], tconst: &str) -> Vec<Justwatch> {
+    justwatch_edges.iter()
+        .filter(|edge| edge.node.content.externalIds.imdbId.as_ref().is_some_and(|imdbId| imdbId.eq_ignore_ascii_case(tconst)))
+        .map(new_justwatch)
+        .collect()
+}
+
+pub fn get_justwatchs(justwatch_edges: &[Edge], imdbs: &[Imdb], title: &str) -> Vec<Justwatch> {
     // TODO: to check this maybe we should use English version of the title, so if akas...
-    println!("justwatch_edges...");
+    debug!(%title, "search_justwatchs");
     let mut justwatchs: Vec<Justwatch> = Vec::<Justwatch>::new();
     for edge in justwatch_edges {
-        if (edge.node.content.externalIds.imdbId.as_ref() != None && 
-            imdbs.iter().find(|&item| &item.tconst == edge.node.content.externalIds.imdbId.as_ref().unwrap()) != None)
+        if edge.node.content.externalIds.imdbId.as_ref().is_some_and(|imdbId| imdbs.iter().any(|item| &item.tconst == imdbId))
             || edge.node.content.title == *title
         {
-            println!("{:?}", edge.node.id);
-            
-            let mut edge_copy = edge.clone();
-            edge_copy.node.content.credits = Vec::new();
-            justwatchs.push(Justwatch { 
-                id: String::from(&edge.node.id),
-
-                edge: Some(edge_copy),
-            });
+            trace!(id = %edge.node.id, "justwatch found");
+            justwatchs.push(new_justwatch(edge));
         }
     }
     justwatchs
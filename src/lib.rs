@@ -0,0 +1,33 @@
+#![allow(non_snake_case)]
+
+//! Identifies movie nodes (files or folders named after a movie) against the imdb, tmdb and
+//! justwatch datasets. `Pipeline` runs what the commands of the `NotitiaRust` binary do, the
+//! matchers (`imdb`, `tmdb`, `just_watch`, `scoring`) can also be used on their own.
+
+extern crate base64;
+extern crate serde_derive;
+extern crate csv;
+
+pub mod utils;
+pub mod config;
+pub mod dataset;
+pub mod index;
+pub mod imdb;
+pub mod tmdb;
+pub mod just_watch;
+pub mod scoring;
+pub mod node_name;
+pub mod scan;
+pub mod watch;
+pub mod pool;
+pub mod error;
+pub mod logging;
+pub mod movies;
+pub mod pipeline;
+pub mod report;
+pub mod review;
+pub mod overrides;
+
+pub use error::Error;
+pub use movies::{MovieRecord, NodeRecord};
+pub use pipeline::{Datasets, Pipeline};
@@ -0,0 +1,107 @@
+use std::io::{self, IsTerminal, Write};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::fmt::MakeWriter;
+use crate::error::Error;
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct LogConfig {
+    /// Most detailed level written: error, warn, info, debug or trace.
+    pub level: String,
+    /// `human` or `json` (one JSON object per line).
+    pub format: String,
+    /// Progress bar while the nodes are identified, only shown on a terminal.
+    pub progress: bool,
+}
+impl Default for LogConfig {
+    fn default() -> LogConfig {
+        LogConfig {
+            level: "info".to_string(),
+            format: "human".to_string(),
+            progress: true,
+        }
+    }
+}
+impl LogConfig {
+    /// Only warnings and errors, without progress bar (for cron jobs).
+    pub fn quiet(&mut self) {
+        self.level = "warn".to_string();
+        self.progress = false;
+    }
+}
+
+/// Progress bar of the stage running, hidden until `init` enables it.
+static PROGRESS: OnceLock<Mutex<ProgressBar>> = OnceLock::new();
+
+fn progress_bar() -> &'static Mutex<ProgressBar> {
+    PROGRESS.get_or_init(|| Mutex::new(ProgressBar::hidden()))
+}
+
+/// Writes the log lines to stderr, hiding the progress bar while they are written.
+struct LogWriter;
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        progress_bar().lock().unwrap().suspend(|| io::stderr().write_all(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+impl<'a> MakeWriter<'a> for LogWriter {
+    type Writer = LogWriter;
+
+    fn make_writer(&'a self) -> LogWriter {
+        LogWriter
+    }
+}
+
+/// Writes the events of notitia to stderr with the level and format of `config`.
+/// The library only emits `tracing` events, a program embedding it can install its own subscriber instead.
+pub fn init(config: &LogConfig) -> Result<(), Error> {
+    let level = LevelFilter::from_str(&config.level).map_err(|_| Error::Usage(format!("unknown log level '{}'", config.level)))?;
+    let builder = tracing_subscriber::fmt().with_max_level(level).with_writer(LogWriter).with_target(false).with_ansi(io::stderr().is_terminal());
+    // a subscriber already installed (by a program embedding notitia) is kept
+    let _ = match config.format.as_str() {
+        "human" => builder.try_init(),
+        "json" => builder.json().flatten_event(true).try_init(),
+        format => return Err(Error::Usage(format!("unknown log format '{}'", format))),
+    };
+
+    if config.progress {
+        progress_bar().lock().unwrap().set_draw_target(ProgressDrawTarget::stderr());
+    }
+
+    Ok(())
+}
+
+/// Starts a stage of `len` nodes on the progress bar, with its elapsed time and ETA.
+pub fn progress_stage(name: &str, len: usize) {
+    let progress = progress_bar().lock().unwrap();
+    if progress.is_hidden() {
+        return;
+    }
+    progress.set_style(ProgressStyle::with_template("{msg:>14} [{bar:40}] {pos}/{len} {elapsed_precise} ETA {eta}").unwrap().progress_chars("=> "));
+    progress.set_message(name.to_string());
+    progress.reset();
+    progress.set_length(len as u64);
+    progress.enable_steady_tick(Duration::from_millis(200));
+}
+
+/// One more node done in the stage running.
+pub fn progress_inc() {
+    progress_bar().lock().unwrap().inc(1);
+}
+
+/// Clears the progress bar once the nodes are identified.
+pub fn progress_finish() {
+    let progress = progress_bar().lock().unwrap();
+    progress.disable_steady_tick();
+    progress.finish_and_clear();
+}
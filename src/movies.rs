@@ -0,0 +1,175 @@
+use crate::just_watch::Justwatch;
+use crate::imdb::Imdb;
+use crate::tmdb::Tmdb;
+use crate::node_name::{NodeExtra, deserialize_node_extra};
+use crate::scan::ScannedNode;
+use crate::utils::write_atomically;
+use crate::error::Error;
+use std::collections::HashSet;
+use std::fs;
+use serde::{Deserialize, Serialize};
+use chrono::Local;
+use tracing::info;
+
+#[derive(Debug, Deserialize, Clone)]
+#[derive(Serialize)]
+pub struct NodeRecord {
+    #[serde(default, deserialize_with = "deserialize_node_extra")]
+    pub extra: NodeExtra,
+    pub format: String,
+    pub full_node_name: String,
+    pub node_name: String,
+    /// Bytes of the media files, only known for scanned nodes.
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// Last modification of the media files (seconds since the unix epoch), only known for scanned nodes.
+    #[serde(default)]
+    pub modified: Option<u64>,
+    /// When the node was found missing from the node list or the library, `None` while it is present.
+    #[serde(default)]
+    pub missing: Option<String>,
+}
+impl Default for NodeRecord {
+    fn default() -> NodeRecord {
+        NodeRecord {
+            extra: NodeExtra { ..Default::default() },
+            format: String::new(),
+            node_name: String::new(),
+            full_node_name: String::new(),
+            size: None,
+            modified: None,
+            missing: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[derive(Serialize, Default)]
+pub struct MovieRecord {
+    pub title: String,
+    pub year: String,
+
+    pub imdb_confirmed: bool,
+    pub imdb: Vec<Imdb>,
+    /// tconst of the candidate selected by its score, `imdb` is sorted by score.
+    #[serde(default)]
+    pub imdb_selected: Option<String>,
+    pub justwatch_confirmed: bool,
+    pub justwatch: Vec<Justwatch>,
+    pub node: Vec<NodeRecord>,
+    pub tmdb_confirmed: bool,
+    pub tmdb: Vec<Tmdb>,
+}
+
+/// Loads the working file, no movies if it doesn't exist or is blank. A working file that can't
+/// be parsed is an error, so it is never replaced by a file with only the new movies.
+pub fn load_movies(movies_fileName: &str) -> Result<Vec<MovieRecord>, Error> {
+    let movies_data = match fs::read_to_string(movies_fileName) {
+        Ok(movies_data) => movies_data,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(Error::io(movies_fileName, err)),
+    };
+    if movies_data.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str(&movies_data).map_err(|err| Error::json(movies_fileName, err))
+}
+
+pub fn save_movies(path: &str, movies: &[MovieRecord]) -> Result<(), Error> {
+    info!(path, movies = movies.len(), "writing movies");
+    if std::path::Path::new(path).exists() {
+        let date = Local::now();
+        fs::copy(path, format!("{}-{}", path, date.format("%Y%m%d%H%M%S"))).map_err(|err| Error::io(path, err))?;
+    }
+    write_movies(path, movies)?;
+
+    Ok(())
+}
+
+pub fn write_movies(path: &str, movies: &[MovieRecord]) -> Result<(), Error> {
+    let json = serde_json::to_string(movies).map_err(|err| Error::json(path, err))?;
+    write_atomically(path, json.as_bytes()).map_err(|err| Error::io(path, err))?;
+
+    Ok(())
+}
+
+/// Stores the size and modification of a scanned node.
+pub fn update_node_file(movies: &mut [MovieRecord], scanned: &ScannedNode) {
+    let node = movies.iter_mut()
+        .flat_map(|movie| movie.node.iter_mut())
+        .find(|node| node.full_node_name == scanned.full_node_name);
+    if let Some(node) = node {
+        node.size = Some(scanned.size);
+        node.modified = scanned.modified;
+    }
+}
+
+/// Nodes of the movies still present, with the size and modification stored when they were scanned.
+pub fn known_nodes(movies: &[MovieRecord]) -> Vec<ScannedNode> {
+    movies.iter()
+        .flat_map(|movie| movie.node.iter())
+        .filter(|node| node.missing.is_none())
+        .map(|node| ScannedNode { full_node_name: node.full_node_name.clone(), size: node.size.unwrap_or(0), modified: node.modified })
+        .collect()
+}
+
+pub struct Reconciliation {
+    /// Full node names found missing in this run.
+    pub missing: Vec<String>,
+    /// Movies removed because all their nodes are missing.
+    pub pruned: Vec<String>,
+}
+
+/// Marks the nodes that are not in `nodes` as missing (and clears the mark of the ones back),
+/// then removes the movies without present nodes if `prune` is set.
+pub fn reconcile_nodes(movies: &mut Vec<MovieRecord>, nodes: &[ScannedNode], prune: bool) -> Reconciliation {
+    let now = Local::now().to_rfc3339();
+    let mut missing: Vec<String> = Vec::new();
+    let present_names: HashSet<&str> = nodes.iter().map(|node| node.full_node_name.as_str()).collect();
+    for node in movies.iter_mut().flat_map(|movie| movie.node.iter_mut()) {
+        let present = present_names.contains(node.full_node_name.as_str());
+        if present {
+            node.missing = None;
+        } else if node.missing.is_none() {
+            node.missing = Some(now.clone());
+            missing.push(node.full_node_name.clone());
+        }
+    }
+
+    let mut pruned: Vec<String> = Vec::new();
+    if prune {
+        movies.retain(|movie| {
+            let keep = movie.node.iter().any(|node| node.missing.is_none());
+            if !keep {
+                pruned.push(format!("{} ({})", movie.title, movie.year));
+            }
+            keep
+        });
+    }
+
+    Reconciliation { missing, pruned }
+}
+
+pub fn log_reconciliation(reconciliation: &Reconciliation) {
+    if reconciliation.missing.is_empty() && reconciliation.pruned.is_empty() {
+        return;
+    }
+    info!(missing = reconciliation.missing.len(), pruned = reconciliation.pruned.len(), "nodes reconciled");
+    for full_node_name in reconciliation.missing.iter() {
+        info!(node = %full_node_name, "missing");
+    }
+    for movie in reconciliation.pruned.iter() {
+        info!(%movie, "pruned");
+    }
+}
+
+/// Name of a node without its folders.
+pub fn get_node_name(full_node_name: &str) -> String {
+    let separators: &[_] = &['\\', '/'];
+    // folders can end with a separator
+    let full_node_name = full_node_name.trim_end_matches(separators);
+    match full_node_name.rfind(separators) {
+        Some(found_index) => full_node_name[found_index + 1..].to_string(),
+        None => full_node_name.to_string(),
+    }
+}
@@ -0,0 +1,441 @@
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+
+/// Quality hints recognized in the extra text and tags (compared in lowercase).
+static RESOLUTION_HINTS: &[&str] = &["480p", "576p", "720p", "1080p", "1080i", "2160p", "4k", "uhd"];
+static CODEC_HINTS: &[&str] = &["x264", "x265", "h264", "h265", "hevc", "avc", "xvid", "av1"];
+static OTHER_QUALITY_HINTS: &[&str] = &[
+    "bluray", "blu-ray", "bdrip", "brrip", "remux", "web-dl", "webrip", "web", "hdtv", "dvdrip",
+    "hdr", "hdr10", "dv", "atmos", "dts", "ac3", "aac",
+];
+
+/// Words of scene-style names that come after the year, like the quality hints.
+static RELEASE_HINTS: &[&str] = &["proper", "repack", "rerip", "internal", "limited", "dubbed", "subbed", "complete", "readnfo"];
+
+/// Languages recognized in the extra text and tags: (word in lowercase, language).
+static LANGUAGE_HINTS: &[(&str, &str)] = &[
+    ("english", "English"), ("eng", "English"),
+    ("spanish", "Spanish"), ("spa", "Spanish"), ("castellano", "Spanish"), ("español", "Spanish"),
+    ("latino", "Latin American Spanish"),
+    ("french", "French"), ("fre", "French"), ("fra", "French"),
+    ("german", "German"), ("ger", "German"),
+    ("italian", "Italian"), ("ita", "Italian"),
+    ("japanese", "Japanese"), ("jpn", "Japanese"),
+    ("multi", "Multi"), ("dual", "Dual"), ("vose", "VOSE"),
+];
+
+/// Editions recognized in the extra text and tags: (text in lowercase, edition).
+static EDITION_HINTS: &[(&str, &str)] = &[
+    ("director's cut", "Director's Cut"),
+    ("directors cut", "Director's Cut"),
+    ("extended", "Extended"),
+    ("unrated", "Unrated"),
+    ("theatrical", "Theatrical"),
+    ("remastered", "Remastered"),
+    ("special edition", "Special Edition"),
+    ("criterion", "Criterion"),
+    ("final cut", "Final Cut"),
+    ("imax", "IMAX"),
+];
+
+/// Parts of a node name like `Title ~ Optional title (2001) [tags].movie`
+/// or a scene-style name like `Blade.Runner.2049.2017.1080p.mkv`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParsedNodeName {
+    /// Titles separated by `~`, the first one is the main title (empty if the name begins with `~`).
+    pub titles: Vec<String>,
+    pub year: Option<u16>,
+    /// Last year of a range like `(2001-2005)`.
+    pub year_end: Option<u16>,
+    /// Text between the year and the format.
+    pub extra: String,
+    /// Contents of the `[...]` groups.
+    pub tags: Vec<String>,
+    pub format: String,
+    pub quality: Vec<String>,
+    pub resolution: Option<String>,
+    pub codec: Option<String>,
+    pub edition: Option<String>,
+    pub languages: Vec<String>,
+    /// Ids in the name, like `[tt0083658]`, `{imdb-tt0083658}` or `{tmdb-78}`.
+    pub imdb_id: Option<String>,
+    pub tmdb_id: Option<String>,
+}
+impl ParsedNodeName {
+    pub fn title(&self) -> String {
+        self.titles.first().cloned().unwrap_or_default()
+    }
+
+    pub fn title_optional(&self) -> String {
+        self.titles.get(1).cloned().unwrap_or_default()
+    }
+
+    /// The year as the searches use it, empty if there is no year.
+    pub fn year_text(&self) -> String {
+        self.year.map_or(String::new(), |year| year.to_string())
+    }
+}
+
+/// Structured extra information of a node, stored in `NodeRecord.extra`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct NodeExtra {
+    /// Text between the year and the format, as it is in the node name.
+    pub text: String,
+    pub tags: Vec<String>,
+    pub resolution: Option<String>,
+    pub codec: Option<String>,
+    pub edition: Option<String>,
+    pub languages: Vec<String>,
+}
+impl From<&ParsedNodeName> for NodeExtra {
+    fn from(parsed: &ParsedNodeName) -> NodeExtra {
+        NodeExtra {
+            text: parsed.extra.clone(),
+            tags: parsed.tags.clone(),
+            resolution: parsed.resolution.clone(),
+            codec: parsed.codec.clone(),
+            edition: parsed.edition.clone(),
+            languages: parsed.languages.clone(),
+        }
+    }
+}
+impl NodeExtra {
+    /// Extra information from the text after the year (`extra` in previous versions).
+    pub fn from_text(text: &str) -> NodeExtra {
+        let (_, tags) = split_tags(text);
+        let mut parsed = ParsedNodeName { extra: text.trim().to_string(), tags, ..Default::default() };
+        find_hints(&mut parsed);
+        NodeExtra::from(&parsed)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NodeExtraOrText {
+    Extra(NodeExtra),
+    Text(String),
+}
+
+/// Reads `NodeRecord.extra`, migrating the plain text of previous versions.
+pub fn deserialize_node_extra<'de, D>(deserializer: D) -> Result<NodeExtra, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match NodeExtraOrText::deserialize(deserializer)? {
+        NodeExtraOrText::Extra(extra) => extra,
+        NodeExtraOrText::Text(text) => NodeExtra::from_text(&text),
+    })
+}
+
+/// Maps node names to a format: the nodes in `directory` (any if empty) with one of
+/// `extensions` (any if empty, an empty extension is a folder) are `format`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct FormatRule {
+    pub directory: String,
+    pub extensions: Vec<String>,
+    pub format: String,
+}
+impl FormatRule {
+    fn matches(&self, full_node_name: &str, extension: &str) -> bool {
+        let path = full_node_name.replace('\\', "/").to_lowercase();
+        let directory = self.directory.replace('\\', "/").to_lowercase();
+        (directory.is_empty() || path.starts_with(&directory) || path.contains(&format!("/{}", directory)))
+            && (self.extensions.is_empty() || self.extensions.iter().any(|item| item.eq_ignore_ascii_case(extension)))
+    }
+}
+
+/// Format of a node: its own format if it is one of `formats` (`.movie`, `.tvSeries`...),
+/// otherwise the format of the first rule that matches, otherwise its extension.
+pub fn node_format(parsed: &ParsedNodeName, full_node_name: &str, rules: &[FormatRule], formats: &[String]) -> String {
+    if formats.contains(&parsed.format) {
+        return parsed.format.clone();
+    }
+    match rules.iter().find(|rule| rule.matches(full_node_name, &parsed.format)) {
+        Some(rule) => rule.format.clone(),
+        None => parsed.format.clone(),
+    }
+}
+
+fn is_quality(word: &str) -> bool {
+    let word = word.to_lowercase();
+    RESOLUTION_HINTS.contains(&word.as_str()) || CODEC_HINTS.contains(&word.as_str()) || OTHER_QUALITY_HINTS.contains(&word.as_str())
+}
+
+fn language(word: &str) -> Option<&'static str> {
+    let word = word.to_lowercase();
+    LANGUAGE_HINTS.iter().find(|(hint, _)| *hint == word).map(|(_, language)| *language)
+}
+
+/// Latest year a movie can be from, next year (movies are announced before their release).
+fn latest_year() -> u16 {
+    (chrono::Local::now().year() + 1) as u16
+}
+
+/// A year of a scene-style name ends the title if it is followed by the end of the name, another
+/// year or a quality, release, language or edition hint (`Blade.Runner.2049.2017` and not
+/// `Blade.Runner.2049.Final.Cut.2017`), and it isn't after `latest_year` (`Blade.Runner.2049.1080p`
+/// has no year).
+fn is_scene_year(words: &[&str], index: usize) -> bool {
+    if index == 0 || parse_year(words[index]).is_none_or(|year| year > latest_year()) {
+        return false;
+    }
+    let next = match words.get(index + 1) {
+        Some(next) => next.to_lowercase(),
+        None => return true,
+    };
+    let rest = words[index + 1..].join(" ").to_lowercase();
+    parse_year(&next).is_some()
+        || is_quality(&next)
+        || RELEASE_HINTS.contains(&next.as_str())
+        || language(&next).is_some()
+        || EDITION_HINTS.iter().any(|(hint, _)| rest.starts_with(hint))
+}
+
+fn parse_year(text: &str) -> Option<u16> {
+    let text = text.trim();
+    if text.len() != 4 || !text.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let year: u16 = text.parse().ok()?;
+    if (1870..=2100).contains(&year) { Some(year) } else { None }
+}
+
+/// `2001`, `2001-2005` or `2001-` (an open range).
+fn parse_year_range(text: &str) -> Option<(u16, Option<u16>)> {
+    match text.split_once('-') {
+        None => parse_year(text).map(|year| (year, None)),
+        Some((begin, end)) => {
+            let begin = parse_year(begin)?;
+            if end.trim().is_empty() {
+                Some((begin, None))
+            } else {
+                Some((begin, Some(parse_year(end)?)))
+            }
+        }
+    }
+}
+
+/// Extensions split off the node names as their format: the extensions and formats of `rules`
+/// and the node formats (`formats`).
+pub fn format_extensions(rules: &[FormatRule], formats: &[String]) -> Vec<String> {
+    let mut extensions: Vec<String> = rules.iter()
+        .flat_map(|rule| rule.extensions.iter().chain(std::iter::once(&rule.format)))
+        .chain(formats)
+        .filter(|extension| !extension.is_empty())
+        .cloned()
+        .collect();
+    extensions.sort();
+    extensions.dedup();
+    extensions
+}
+
+/// Splits the format (the text after the last dot) if it is one of `extensions`. In names with
+/// spaces (`Title (2001) [tags].format`) any text that looks like a format is split too, dots only
+/// separate the format there; in scene-style names (`Mr.Robot`) they separate words.
+fn split_format<'a>(node_name: &'a str, extensions: &[String]) -> (&'a str, String) {
+    if let Some(index) = node_name.rfind('.') {
+        let format = &node_name[index + 1..];
+        let known = extensions.iter().any(|extension| extension.eq_ignore_ascii_case(format));
+        if index > 0
+            && !format.is_empty()
+            && format.chars().all(|c| c.is_ascii_alphanumeric() || c == '+')
+            && !format.starts_with(|c: char| c.is_ascii_digit())
+            && !is_quality(format)
+            && (known || node_name.contains(' ')) {
+            return (&node_name[..index], format.to_string());
+        }
+    }
+    (node_name, String::new())
+}
+
+/// Removes the `[...]` groups of `text`, returning the text left and the groups.
+fn split_tags(text: &str) -> (String, Vec<String>) {
+    let mut rest = String::new();
+    let mut tags = Vec::new();
+    let mut remaining = text;
+    while let Some(open) = remaining.find('[') {
+        match remaining[open..].find(']') {
+            Some(close) => {
+                rest.push_str(&remaining[..open]);
+                let tag = remaining[open + 1..open + close].trim();
+                if !tag.is_empty() {
+                    tags.push(tag.to_string());
+                }
+                remaining = &remaining[open + close + 1..];
+            },
+            None => break,
+        }
+    }
+    rest.push_str(remaining);
+    (rest, tags)
+}
+
+fn is_tconst(text: &str) -> bool {
+    text.len() > 2 && text.starts_with("tt") && text[2..].chars().all(|c| c.is_ascii_digit())
+}
+
+/// Stores the id of a group like `tt0083658`, `imdb-tt0083658`, `imdbid-tt0083658`, `tmdb-78` or
+/// `tmdbid-78` (the first one of each kind). Returns `false` if the group is not an id.
+fn find_id(parsed: &mut ParsedNodeName, group: &str) -> bool {
+    let group = group.trim().to_lowercase();
+    let (kind, id) = group.split_once(['-', '=']).unwrap_or(("", &group));
+    match kind {
+        "" | "imdb" | "imdbid" if is_tconst(id) => {
+            parsed.imdb_id.get_or_insert_with(|| id.to_string());
+            true
+        },
+        "tmdb" | "tmdbid" if !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()) => {
+            parsed.tmdb_id.get_or_insert_with(|| id.to_string());
+            true
+        },
+        _ => false,
+    }
+}
+
+/// Removes the `{...}` groups of `text` that are ids (media servers name files like
+/// `Title (2001) {imdb-tt0000000}.mkv`), the other groups are left in the text.
+fn split_id_groups(parsed: &mut ParsedNodeName, text: &str) -> String {
+    let mut rest = String::new();
+    let mut remaining = text;
+    while let Some(open) = remaining.find('{') {
+        match remaining[open..].find('}') {
+            Some(close) => {
+                if find_id(parsed, &remaining[open + 1..open + close]) {
+                    rest.push_str(&remaining[..open]);
+                } else {
+                    rest.push_str(&remaining[..open + close + 1]);
+                }
+                remaining = &remaining[open + close + 1..];
+            },
+            None => break,
+        }
+    }
+    rest.push_str(remaining);
+    rest
+}
+
+/// Title without dots (dots in titles are ignored by the searches). Spaces are kept as they are:
+/// the movies of the working file are found by this title (`Mr. Smith` is `Mr  Smith`).
+fn clean_title(title: &str) -> String {
+    title.replace('.', " ").trim_matches(' ').to_string()
+}
+
+/// Title as the searches use it, with single spaces.
+pub fn search_title(title: &str) -> String {
+    title.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// The last `(...)` group with a year or a year range: (open, close, year, year_end).
+fn find_year_group(stem: &str) -> Option<(usize, usize, u16, Option<u16>)> {
+    let mut found = None;
+    for (open, _) in stem.match_indices('(') {
+        if let Some(close) = stem[open..].find(')') {
+            if let Some((year, year_end)) = parse_year_range(&stem[open + 1..open + close]) {
+                found = Some((open, open + close, year, year_end));
+            }
+        }
+    }
+    found
+}
+
+fn split_words(text: &str) -> Vec<&str> {
+    text.split(|c: char| c.is_whitespace() || c == '.' || c == '_' || c == ',' || c == '[' || c == ']' || c == '/' || c == '+')
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+fn push_language(parsed: &mut ParsedNodeName, language: &str) {
+    if !parsed.languages.iter().any(|item| item == language) {
+        parsed.languages.push(language.to_string());
+    }
+}
+
+fn find_hints(parsed: &mut ParsedNodeName) {
+    // a tag is a language tag only if all its words are languages, "[Spanish movie]" is not
+    for tag in parsed.tags.clone() {
+        let languages: Vec<Option<&str>> = split_words(&tag).into_iter().map(language).collect();
+        if !languages.is_empty() && languages.iter().all(|language| language.is_some()) {
+            for language in languages.into_iter().flatten() {
+                push_language(parsed, language);
+            }
+        }
+    }
+    // outside tags (scene-style names) every word can be a language
+    let (extra_text, _) = split_tags(&parsed.extra);
+    for language in split_words(&extra_text).into_iter().filter_map(language) {
+        push_language(parsed, language);
+    }
+
+    let mut texts: Vec<String> = parsed.tags.clone();
+    texts.push(parsed.extra.clone());
+    for text in texts {
+        let text = text.to_lowercase();
+        for word in split_words(&text) {
+            if is_quality(word) && !parsed.quality.iter().any(|quality| quality == word) {
+                parsed.quality.push(word.to_string());
+            }
+            if parsed.resolution.is_none() && RESOLUTION_HINTS.contains(&word) {
+                parsed.resolution = Some(word.to_string());
+            }
+            if parsed.codec.is_none() && CODEC_HINTS.contains(&word) {
+                parsed.codec = Some(word.to_string());
+            }
+        }
+        if parsed.edition.is_none() {
+            parsed.edition = EDITION_HINTS.iter()
+                .find(|(hint, _)| text.contains(hint))
+                .map(|(_, edition)| edition.to_string());
+        }
+    }
+}
+
+/// Parses a node name, `extensions` are the formats it can end with (see `format_extensions`).
+pub fn parse_node_name(node_name: &str, extensions: &[String]) -> ParsedNodeName {
+    let (stem, format) = split_format(node_name.trim(), extensions);
+    let mut parsed = ParsedNodeName { format, ..Default::default() };
+    let stem = split_id_groups(&mut parsed, stem);
+    let stem = stem.as_str();
+
+    let title_part = match find_year_group(stem) {
+        Some((open, close, year, year_end)) => {
+            parsed.year = Some(year);
+            parsed.year_end = year_end;
+            parsed.extra = stem[close + 1..].trim().to_string();
+            stem[..open].to_string()
+        },
+        None if !stem.contains(' ') => {
+            // scene-style name: words separated by dots, the last year (see `is_scene_year`) ends the title
+            let (stem, tags) = split_tags(stem);
+            parsed.tags = tags;
+            let words: Vec<&str> = stem.split(['.', '_']).filter(|word| !word.is_empty()).collect();
+            match (0..words.len()).rev().find(|&index| is_scene_year(&words, index)) {
+                Some(index) => {
+                    parsed.year = parse_year(words[index]);
+                    parsed.extra = words[index + 1..].join(" ");
+                    words[..index].join(" ")
+                },
+                None => {
+                    // without year, the title ends at the first quality hint
+                    let end = words.iter().position(|word| is_quality(word)).unwrap_or(words.len());
+                    parsed.extra = words[end..].join(" ");
+                    words[..end].join(" ")
+                },
+            }
+        },
+        None => stem.to_string(),
+    };
+
+    let (title_part, title_tags) = split_tags(&title_part);
+    let (_, extra_tags) = split_tags(&parsed.extra);
+    parsed.tags.extend(title_tags);
+    parsed.tags.extend(extra_tags);
+    parsed.titles = title_part.split('~').map(clean_title).collect();
+    for tag in parsed.tags.clone() {
+        find_id(&mut parsed, &tag);
+    }
+    find_hints(&mut parsed);
+
+    parsed
+}
@@ -0,0 +1,40 @@
+use crate::error::Error;
+use std::fs;
+use serde::{Deserialize, Serialize};
+
+/// Ids given by the user for the nodes that are never matched (or matched wrong): the node name, or
+/// the title and year parsed from it, with the tconst, the tmdb id and the justwatch id to use.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Override {
+    /// Node name without its folders, like `Amélie (2001).movie`.
+    pub node_name: Option<String>,
+    /// Title and year parsed from the node name, for all the nodes of a movie.
+    pub title: Option<String>,
+    pub year: Option<String>,
+
+    pub tconst: Option<String>,
+    pub tmdb_id: Option<String>,
+    pub justwatch_id: Option<String>,
+}
+
+/// Reads the overrides of `path` (a JSON list), none if `path` is empty.
+pub fn load_overrides(path: &str) -> Result<Vec<Override>, Error> {
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+    let data = fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+    serde_json::from_str(&data).map_err(|err| Error::json(path, err))
+}
+
+/// The override of a node: the first one with its node name or, if none, the first one with its
+/// title (case insensitive) and year.
+pub fn find_override<'a>(overrides: &'a [Override], node_name: &str, title: &str, year: &str) -> Option<&'a Override> {
+    overrides.iter()
+        .find(|item| item.node_name.as_deref() == Some(node_name))
+        .or_else(|| overrides.iter().find(|item| {
+            item.node_name.is_none()
+                && item.title.as_ref().is_some_and(|item_title| item_title.to_lowercase() == title.to_lowercase())
+                && item.year.as_deref().unwrap_or("") == year
+        }))
+}
@@ -0,0 +1,563 @@
+use crate::just_watch::{Edge, Justwatch, get_justwatchs, get_justwatchs_by_tconst, get_justwatch_by_id, load_justwatchs};
+use crate::imdb::{Imdb, NodeTitle, Rating, get_basics_batch, get_akas_batch, get_ratings_batch, update_imdbs_with_akas_batch, load_basics, load_akas, load_ratings, build_basics_index, build_ratings_index, reduce_basics, reduce_akas, rank_imdbs_by_votes, get_imdb_by_tconst, get_basics_by_tconst_batch, log_malformed_lines, ImdbDatasets};
+use crate::dataset::Dataset;
+use crate::index::{DatasetIndex, index_path, load_index};
+use crate::tmdb::{Tmdb, get_tmdbs_batch, get_tmdbs_by_id_batch, get_tmdb_by_id, load_tmdbs, load_tmdbs_big};
+use crate::config::Config;
+use crate::scoring::{ScoreContext, score_imdbs, select_imdb};
+use crate::node_name::{NodeExtra, ParsedNodeName, parse_node_name, node_format, format_extensions, search_title};
+use crate::scan::{ScannedNode, scan, read_node_list};
+use crate::watch::{Watcher, diff_nodes};
+use crate::movies::{MovieRecord, NodeRecord, Reconciliation, load_movies, save_movies, write_movies, update_node_file, known_nodes, reconcile_nodes, log_reconciliation, get_node_name};
+use crate::error::Error;
+use crate::review::review_movies;
+use crate::overrides::{Override, load_overrides, find_override};
+use crate::logging::{progress_stage, progress_inc, progress_finish};
+use crate::pool::map_parallel;
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tracing::{info, info_span, warn};
+
+/// The datasets searched, loaded once and shared by all the nodes.
+pub struct Datasets {
+    pub data: Dataset,
+    pub index_basics: Option<DatasetIndex>,
+    pub data_akas: Dataset,
+    pub justwatch_edges: Vec<Edge>,
+    pub data_tmdbs: Dataset,
+    pub data_tmdbs_big: Dataset,
+    pub data_ratings: Dataset,
+    pub index_ratings: Option<DatasetIndex>,
+    /// Ids given by the user, see `apply_overrides`.
+    pub overrides: Vec<Override>,
+}
+
+impl Datasets {
+    /// The imdb datasets searched by tconst (see `get_imdb_by_tconst`).
+    pub fn imdb(&self) -> ImdbDatasets<'_> {
+        ImdbDatasets { basics: &self.data, index_basics: self.index_basics.as_ref(), ratings: &self.data_ratings, index_ratings: self.index_ratings.as_ref() }
+    }
+
+    /// Loads the datasets of `config`, with the imdb indexes when they are up to date.
+    pub fn load(config: &Config) -> Result<Datasets, Error> {
+        let start = Instant::now();
+        let data = load_basics(&config.imdb.basics, &config.extract_dir)?;
+        let index_basics = load_index(&index_path(&config.imdb.basics), &data)?;
+        let data_akas = load_akas(&config.imdb.akas, &config.extract_dir)?;
+        let justwatch_edges = load_justwatchs(&config.justwatch.files)?;
+        let data_tmdbs = load_tmdbs(&config.tmdb.movies_metadata, &config.extract_dir)?;
+        let data_tmdbs_big = load_tmdbs_big(&config.tmdb.archive, &config.extract_dir)?;
+        let data_ratings = load_ratings(&config.imdb.ratings, &config.extract_dir)?;
+        let index_ratings = load_index(&index_path(&config.imdb.ratings), &data_ratings)?;
+        let overrides = load_overrides(&config.overrides_file)?;
+        if index_basics.is_none() || index_ratings.is_none() {
+            warn!("imdb indexes not found, searching the whole files (run the index command to build them)");
+        }
+        info!(elapsed_ms = elapsed_ms(start), "datasets loaded");
+
+        Ok(Datasets {
+            data,
+            index_basics,
+            data_akas,
+            justwatch_edges,
+            data_tmdbs,
+            data_tmdbs_big,
+            data_ratings,
+            index_ratings,
+            overrides,
+        })
+    }
+}
+
+/// Builds the indexes of the imdb basics and ratings (the `index` command).
+pub fn build_indexes(config: &Config) -> Result<(), Error> {
+    let data = load_basics(&config.imdb.basics, &config.extract_dir)?;
+    build_basics_index(&data, &index_path(&config.imdb.basics))?;
+    let data_ratings = load_ratings(&config.imdb.ratings, &config.extract_dir)?;
+    build_ratings_index(&data_ratings, &index_path(&config.imdb.ratings))?;
+    info!("indexes generated");
+
+    Ok(())
+}
+
+/// Writes the imdb basics and akas of `config` from the official files (the `reduce` command).
+pub fn reduce(config: &Config, basics: &str, akas: &str) -> Result<(), Error> {
+    let tconsts = reduce_basics(basics, &config.imdb.basics)?;
+    reduce_akas(akas, &config.imdb.akas, &config.imdb.language, &tconsts)?;
+    info!("imdb files reduced");
+
+    Ok(())
+}
+
+fn elapsed_ms(start: Instant) -> u64 {
+    start.elapsed().as_millis() as u64
+}
+
+/// Logs the time a source took to search `nodes` nodes, `found` of them with something.
+fn log_search(source: &str, nodes: usize, found: usize, start: Instant) {
+    info!(source, nodes, found, elapsed_ms = elapsed_ms(start), "searched");
+}
+
+/// Asks the user to confirm the candidates of the movies of the working file and writes them to
+/// the output file (the `review` command). The tconsts entered are searched in the imdb basics.
+pub fn review(config: &Config) -> Result<(), Error> {
+    let mut movies = load_movies(&config.working_file)?;
+    let data = load_basics(&config.imdb.basics, &config.extract_dir)?;
+    let index_basics = load_index(&index_path(&config.imdb.basics), &data)?;
+    let data_ratings = load_ratings(&config.imdb.ratings, &config.extract_dir)?;
+    let index_ratings = load_index(&index_path(&config.imdb.ratings), &data_ratings)?;
+    let datasets = ImdbDatasets { basics: &data, index_basics: index_basics.as_ref(), ratings: &data_ratings, index_ratings: index_ratings.as_ref() };
+    let lookup = |tconst: &str| match get_imdb_by_tconst(&datasets, tconst) {
+        Ok(imdb) => imdb,
+        Err(err) => {
+            warn!("{}", err);
+            None
+        },
+    };
+
+    let summary = review_movies(&mut movies, lookup, &mut std::io::stdin().lock(), &mut std::io::stdout()).map_err(|err| Error::io("stdin", err))?;
+    info!(reviewed = summary.reviewed, confirmed = summary.confirmed, "movies reviewed");
+    save_movies(&config.output_file, &movies)
+}
+
+/// A node to identify, with its name parsed.
+pub struct NodeJob {
+    pub node_name: String,
+    pub full_node_name: String,
+    pub parsed: ParsedNodeName,
+    pub format: String,
+}
+impl NodeJob {
+    pub fn new(config: &Config, full_node_name: &str) -> NodeJob {
+        let node_name = get_node_name(full_node_name);
+        let parsed = parse_node_name(&node_name, &format_extensions(&config.format_rules, &config.filter_node_format));
+        let format = node_format(&parsed, full_node_name, &config.format_rules, &config.filter_node_format);
+        NodeJob { node_name, full_node_name: full_node_name.to_string(), parsed, format }
+    }
+}
+
+/// What was found for a node, `None` for what is confirmed or was not searched.
+/// What comes from an override is confirmed.
+#[derive(Default)]
+pub struct MovieMatch {
+    pub imdb: Option<(Vec<Imdb>, Option<String>)>,
+    pub justwatch: Option<Vec<Justwatch>>,
+    pub tmdb: Option<Vec<Tmdb>>,
+    pub imdb_confirmed: bool,
+    pub justwatch_confirmed: bool,
+    pub tmdb_confirmed: bool,
+}
+
+/// Takes the ids of the override of each node (see `find_override`) from the datasets instead of
+/// searching them, even for the sources already confirmed. An id that isn't found is searched as usual.
+fn apply_overrides(found: &mut [MovieMatch], datasets: &Datasets, jobs: &[&NodeJob]) {
+    for (job, found) in jobs.iter().zip(found.iter_mut()) {
+        let item = match find_override(&datasets.overrides, &job.node_name, &job.parsed.title(), &job.parsed.year_text()) {
+            Some(item) => item,
+            None => continue,
+        };
+        let _movie = info_span!("movie", title = %job.parsed.title(), year = %job.parsed.year_text()).entered();
+        info!(node = %job.full_node_name, tconst = item.tconst, tmdb_id = item.tmdb_id, justwatch_id = item.justwatch_id, "overridden");
+
+        if let Some(tconst) = &item.tconst {
+            match get_imdb_by_tconst(&datasets.imdb(), tconst) {
+                Ok(Some(imdb)) => {
+                    let imdb_selected = Some(imdb.tconst.clone());
+                    found.imdb = Some((vec![imdb], imdb_selected));
+                    found.imdb_confirmed = true;
+                },
+                Ok(None) => warn!(%tconst, "override not found in imdb, searching the node"),
+                Err(err) => warn!(%tconst, "override not found in imdb, searching the node: {}", err),
+            }
+        }
+        if let Some(tmdb_id) = &item.tmdb_id {
+            match get_tmdb_by_id(&datasets.data_tmdbs, &datasets.data_tmdbs_big, tmdb_id, &job.parsed.year_text()) {
+                Ok(Some(tmdb)) => {
+                    found.tmdb = Some(vec![tmdb]);
+                    found.tmdb_confirmed = true;
+                },
+                Ok(None) => warn!(%tmdb_id, "override not found in tmdb, searching the node"),
+                Err(err) => warn!(%tmdb_id, "override not found in tmdb, searching the node: {}", err),
+            }
+        }
+        if let Some(justwatch_id) = &item.justwatch_id {
+            match get_justwatch_by_id(&datasets.justwatch_edges, justwatch_id) {
+                Some(justwatch) => {
+                    found.justwatch = Some(vec![justwatch]);
+                    found.justwatch_confirmed = true;
+                },
+                None => warn!(%justwatch_id, "override not found in justwatch, searching the node"),
+            }
+        }
+    }
+}
+
+/// Searches the datasets for the nodes of `movies` (one movie per job), without modifying them.
+/// Every stage searches each dataset once for all the nodes (see `get_basics_batch`...).
+fn match_movies(
+        movies: &[&MovieRecord],
+        config: &Config,
+        datasets: &Datasets,
+
+        jobs: &[&NodeJob],
+    ) -> Vec<MovieMatch> {
+
+    let year_tolerance = config.matching.year_tolerance;
+    let titles: Vec<NodeTitle> = jobs.iter().map(|job| (search_title(&job.parsed.title()), search_title(&job.parsed.title_optional()), job.parsed.year_text())).collect();
+    let mut found: Vec<MovieMatch> = jobs.iter().map(|_| MovieMatch::default()).collect();
+    apply_overrides(&mut found, datasets, jobs);
+
+    // the nodes with a tconst in their name are looked up by it, their titles are not searched
+    let named: Vec<usize> = (0..jobs.len()).filter(|&i| !movies[i].imdb_confirmed && !found[i].imdb_confirmed && jobs[i].parsed.imdb_id.is_some()).collect();
+    let named_tconsts: Vec<String> = named.iter().filter_map(|&i| jobs[i].parsed.imdb_id.clone()).collect();
+    let start = Instant::now();
+    let named_imdbs = get_basics_by_tconst_batch(&datasets.data, datasets.index_basics.as_ref(), &named_tconsts, config).unwrap_or_else(|err| {
+        warn!("{}", err);
+        vec![None; named.len()]
+    });
+    let mut by_tconst = vec![false; jobs.len()];
+    let mut named_found: Vec<(usize, Vec<Imdb>)> = Vec::new();
+    for ((&i, tconst), imdb) in named.iter().zip(&named_tconsts).zip(named_imdbs) {
+        match imdb {
+            Some(imdb) => {
+                by_tconst[i] = true;
+                named_found.push((i, vec![imdb]));
+            },
+            None => warn!(node = %jobs[i].full_node_name, %tconst, "tconst of the node name not found in imdb, searching its title"),
+        }
+    }
+    log_search("imdb tconsts", named.len(), named_found.len(), start);
+
+    let searched: Vec<usize> = (0..jobs.len()).filter(|&i| !movies[i].imdb_confirmed && !found[i].imdb_confirmed && !by_tconst[i] && !titles[i].2.is_empty()).collect();
+    let searched_titles: Vec<NodeTitle> = searched.iter().map(|&i| titles[i].clone()).collect();
+    let start = Instant::now();
+    let mut imdbs = get_basics_batch(&datasets.data, datasets.index_basics.as_ref(), &searched_titles, config);
+
+    let without_basics: Vec<usize> = (0..searched.len()).filter(|&i| imdbs[i].is_empty()).collect();
+    log_search("imdb basics", searched.len(), searched.len() - without_basics.len(), start);
+    let start = Instant::now();
+    let without_basics_titles: Vec<NodeTitle> = without_basics.iter().map(|&i| searched_titles[i].clone()).collect();
+    let mut imdbs_akas = vec![Vec::<Imdb>::new(); searched.len()];
+    for (i, akas) in without_basics.iter().zip(get_akas_batch(&datasets.data_akas, &without_basics_titles, config)) {
+        imdbs_akas[*i] = akas;
+    }
+    log_search("imdb akas", without_basics.len(), without_basics.iter().filter(|&&i| !imdbs_akas[i].is_empty()).count(), start);
+    update_imdbs_with_akas_batch(&mut imdbs, &imdbs_akas, &searched_titles, &datasets.data, datasets.index_basics.as_ref(), year_tolerance);
+    let (named, named_imdbs): (Vec<usize>, Vec<Vec<Imdb>>) = named_found.into_iter().unzip();
+    let searched: Vec<usize> = searched.into_iter().chain(named).collect();
+    imdbs.extend(named_imdbs);
+    imdbs_akas.resize(searched.len(), Vec::new());
+
+    let mut tconsts: Vec<String> = imdbs.iter().flatten().map(|imdb| imdb.tconst.clone()).collect();
+    tconsts.sort();
+    tconsts.dedup();
+    let start = Instant::now();
+    let ratings = get_ratings_batch(&datasets.data_ratings, datasets.index_ratings.as_ref(), &tconsts, config);
+    log_search("imdb ratings", tconsts.len(), ratings.values().filter(|ratings| !ratings.tconst.is_empty()).count(), start);
+
+    for ((&i, mut imdbs), imdbs_akas) in searched.iter().zip(imdbs).zip(&imdbs_akas) {
+        for imdb in imdbs.iter_mut() {
+            let ratings = &ratings[&imdb.tconst];
+            imdb.ratings = if ratings.tconst.is_empty() { None } else { Some(Rating::from(ratings)) };
+        }
+        rank_imdbs_by_votes(&mut imdbs, config.imdb.min_votes);
+
+        let (title, title_optional, _) = &titles[i];
+        let aka_tconsts: Vec<String> = imdbs_akas.iter().map(|aka| aka.tconst.clone()).collect();
+        score_imdbs(&mut imdbs, &ScoreContext { title, title_optional, format: &jobs[i].format, aka_tconsts: &aka_tconsts });
+        let imdb_selected = match by_tconst[i] {
+            true => imdbs.first(),
+            false => select_imdb(&imdbs, config.scoring.auto_select_threshold),
+        }.map(|imdb| imdb.tconst.clone());
+
+        found[i].imdb = Some((imdbs, imdb_selected));
+    }
+
+    // justwatch and tmdb are searched with the selected imdb, or all the candidates, and only by id
+    // for the nodes with ids in their name
+    let mut tmdb_nodes: Vec<(Vec<Imdb>, NodeTitle)> = Vec::new();
+    let mut tmdb_searched: Vec<usize> = Vec::new();
+    let mut tmdb_id_nodes: Vec<(Option<String>, Option<String>, String)> = Vec::new();
+    let mut tmdb_id_searched: Vec<usize> = Vec::new();
+    let start = Instant::now();
+    let justwatch_searched: Vec<usize> = (0..movies.len()).filter(|&i| !movies[i].justwatch_confirmed && !found[i].justwatch_confirmed).collect();
+    let mut justwatch_nodes: Vec<(Vec<Imdb>, Option<String>, &String)> = Vec::new();
+    for (i, movie) in movies.iter().enumerate() {
+        let (movie_imdbs, imdb_selected) = match &found[i].imdb {
+            Some((imdbs, imdb_selected)) => (imdbs, imdb_selected),
+            None => (&movie.imdb, &movie.imdb_selected),
+        };
+        let imdbs: Vec<Imdb> = match imdb_selected {
+            Some(tconst) if !movie.imdb_confirmed => movie_imdbs.iter().filter(|imdb| &imdb.tconst == tconst).cloned().collect(),
+            _ => movie_imdbs.clone(),
+        };
+        let tconst = if by_tconst[i] { imdbs.first().map(|imdb| imdb.tconst.clone()) } else { None };
+        if !movie.justwatch_confirmed && !found[i].justwatch_confirmed { 
+            justwatch_nodes.push((imdbs.clone(), tconst.clone(), &titles[i].0));
+        };
+        if !movie.tmdb_confirmed && !found[i].tmdb_confirmed { 
+            if tconst.is_some() || jobs[i].parsed.tmdb_id.is_some() {
+                tmdb_id_nodes.push((jobs[i].parsed.tmdb_id.clone(), tconst, titles[i].2.clone()));
+                tmdb_id_searched.push(i);
+            } else {
+                tmdb_nodes.push((imdbs, titles[i].clone()));
+                tmdb_searched.push(i);
+            }
+        };
+    }
+    progress_stage("justwatch", justwatch_nodes.len());
+    let justwatchs = map_parallel(&justwatch_nodes, config.threads, |(imdbs, tconst, title)| match tconst {
+        Some(tconst) => get_justwatchs_by_tconst(&datasets.justwatch_edges, tconst),
+        None => get_justwatchs(&datasets.justwatch_edges, imdbs, title),
+    }, progress_inc);
+    for (&i, justwatchs) in justwatch_searched.iter().zip(justwatchs) {
+        found[i].justwatch = Some(justwatchs);
+    }
+    log_search("justwatch", justwatch_searched.len(), justwatch_searched.iter().filter(|&&i| found[i].justwatch.as_ref().is_some_and(|justwatchs| !justwatchs.is_empty())).count(), start);
+    let start = Instant::now();
+    for (&i, tmdbs) in tmdb_id_searched.iter().zip(get_tmdbs_by_id_batch(&datasets.data_tmdbs, &datasets.data_tmdbs_big, &tmdb_id_nodes)) {
+        if let (Some(tmdb_id), true) = (&jobs[i].parsed.tmdb_id, tmdbs.is_empty()) {
+            warn!(node = %jobs[i].full_node_name, %tmdb_id, "tmdb id of the node name not found in tmdb");
+        }
+        found[i].tmdb = Some(tmdbs);
+    }
+    log_search("tmdb ids", tmdb_id_searched.len(), tmdb_id_searched.iter().filter(|&&i| found[i].tmdb.as_ref().is_some_and(|tmdbs| !tmdbs.is_empty())).count(), start);
+    let start = Instant::now();
+    let tmdbs = get_tmdbs_batch(&datasets.data_tmdbs, &datasets.data_tmdbs_big, &tmdb_nodes, config);
+    for (&i, tmdbs) in tmdb_searched.iter().zip(tmdbs) {
+        found[i].tmdb = Some(tmdbs);
+    }
+    log_search("tmdb", tmdb_searched.len(), tmdb_searched.iter().filter(|&&i| found[i].tmdb.as_ref().is_some_and(|tmdbs| !tmdbs.is_empty())).count(), start);
+    progress_finish();
+
+    for (job, found) in jobs.iter().zip(&found) {
+        let _movie = info_span!("movie", title = %job.parsed.title(), year = %job.parsed.year_text()).entered();
+        info!(
+            node = %job.full_node_name,
+            format = %job.format,
+            imdb = found.imdb.as_ref().map(|(imdbs, _)| imdbs.len()),
+            imdb_selected = found.imdb.as_ref().and_then(|(_, imdb_selected)| imdb_selected.as_deref()),
+            justwatch = found.justwatch.as_ref().map(|justwatchs| justwatchs.len()),
+            tmdb = found.tmdb.as_ref().map(|tmdbs| tmdbs.len()),
+            "matched",
+        );
+    }
+
+    found
+}
+
+/// Stores what was found for a node in its movie and adds the node (or updates it).
+pub fn update_movie(movie: &mut MovieRecord, job: &NodeJob, found: MovieMatch) {
+    if let Some((imdbs, imdb_selected)) = found.imdb {
+        movie.imdb = imdbs;
+        movie.imdb_selected = imdb_selected;
+    }
+    if let Some(justwatchs) = found.justwatch {
+        movie.justwatch = justwatchs;
+    }
+    if let Some(tmdbs) = found.tmdb {
+        movie.tmdb = tmdbs;
+    }
+    movie.imdb_confirmed |= found.imdb_confirmed;
+    movie.justwatch_confirmed |= found.justwatch_confirmed;
+    movie.tmdb_confirmed |= found.tmdb_confirmed;
+
+    movie.title = job.parsed.title();
+    movie.year = job.parsed.year_text();
+    
+    let extra = NodeExtra::from(&job.parsed);
+    match movie.node.iter_mut().find(|node| node.node_name == job.node_name) { 
+        None => { 
+            let mut nodeRecord : NodeRecord = NodeRecord { ..Default::default() };
+            nodeRecord.node_name = job.node_name.clone();
+            nodeRecord.full_node_name = job.full_node_name.clone();
+            nodeRecord.extra = extra;
+            nodeRecord.format = job.format.clone();
+            movie.node.push(nodeRecord);
+        },
+        Some(node) => { 
+            node.full_node_name = job.full_node_name.clone();
+            node.extra = extra;
+            node.format = job.format.clone();
+    } };
+}
+
+/// Nodes without title (problems with the node name) are kept in a movie without title and year.
+fn add_untitled_node(movies: &mut Vec<MovieRecord>, job: &NodeJob) {
+    if movies.iter().any(|movie| movie.node.iter().any(|node| node.node_name == job.node_name)) {
+        return;
+    }
+    let mut nodeRecord : NodeRecord = NodeRecord { ..Default::default() };
+    nodeRecord.node_name = job.node_name.clone();
+    nodeRecord.full_node_name = job.full_node_name.clone();
+    match movies.iter_mut().find(|movie| movie.title.is_empty() && movie.year.is_empty()) {
+        None => {
+            // add (push)
+            let mut movie : MovieRecord = MovieRecord { ..Default::default() };
+            movie.node.push(nodeRecord);
+            movies.push(movie);
+        },
+        Some(movie) => {
+            // update
+            movie.node.push(nodeRecord);
+        }
+    }
+}
+
+/// Adds the nodes to the movies with their title and year, identifying them all together (each
+/// dataset is searched once per stage) on `config.threads` workers. The movies are updated in the
+/// order of `nodes`; only the first node of each movie (title and year) is searched, the next ones
+/// keep what was found for it (see `next_node_match`).
+pub fn add_nodes(movies: &mut Vec<MovieRecord>, config: &Config, datasets: &Datasets, nodes: &[ScannedNode]) {
+    let jobs: Vec<NodeJob> = nodes.iter()
+        .map(|node| NodeJob::new(config, &node.full_node_name))
+        .filter(|job| config.filter_node_format.contains(&job.format))
+        .collect();
+
+    let mut first_jobs: HashMap<(String, String), &NodeJob> = HashMap::new();
+    let mut searched: Vec<&NodeJob> = Vec::new();
+    for job in jobs.iter().filter(|job| !job.parsed.title().is_empty()) {
+        if let Entry::Vacant(entry) = first_jobs.entry((job.parsed.title(), job.parsed.year_text())) {
+            entry.insert(job);
+            searched.push(job);
+        }
+    }
+    // the nodes are searched against the movies as they were before adding any of them
+    let new_movie = MovieRecord { ..Default::default() };
+    let searched_movies: Vec<&MovieRecord> = searched.iter().map(|job| {
+        let (title, year) = (job.parsed.title(), job.parsed.year_text());
+        movies.iter().find(|movie| movie.title == title && movie.year == year).unwrap_or(&new_movie)
+    }).collect();
+    info!(nodes = searched.len(), "matching nodes");
+    let mut found = match_movies(&searched_movies, config, datasets, &searched).into_iter();
+
+    for job in jobs.iter() {
+        let (title, year) = (job.parsed.title(), job.parsed.year_text());
+        if title.is_empty() {
+            add_untitled_node(movies, job);
+            continue;
+        }
+        let first_job = first_jobs[&(title.clone(), year.clone())];
+        let found = match std::ptr::eq(first_job, job) {
+            true => found.next().unwrap(),
+            false => next_node_match(datasets, first_job, job),
+        };
+        match movies.iter_mut().find(|movie| movie.title == title && movie.year == year) {
+            None => {
+                // add (push)
+                let mut movie : MovieRecord = MovieRecord { ..Default::default() };
+                update_movie(&mut movie, job, found);
+                movies.push(movie);
+            },
+            Some(movie) => {
+                // update
+                update_movie(movie, job, found);
+            },
+        }
+    }
+    log_malformed_lines(&[&datasets.data, &datasets.data_akas, &datasets.data_ratings]);
+}
+
+/// What is found for a node of the same movie as `first_job`, added after it: nothing, so the
+/// movie keeps the candidates and confirmations found for the first node, unless the node has an
+/// override of its own.
+fn next_node_match(datasets: &Datasets, first_job: &NodeJob, job: &NodeJob) -> MovieMatch {
+    let mut found = [MovieMatch::default()];
+    let find = |job: &NodeJob| find_override(&datasets.overrides, &job.node_name, &job.parsed.title(), &job.parsed.year_text());
+    if let (Some(item), first_item) = (find(job), find(first_job)) {
+        if !first_item.is_some_and(|first_item| std::ptr::eq(first_item, item)) {
+            apply_overrides(&mut found, datasets, &[job]);
+        }
+    }
+    let [found] = found;
+    found
+}
+
+/// Identifies nodes and keeps their movies up to date, what the commands do:
+/// `add_nodes` for the nodes of the node list (or the library), `reconcile` with all of them and `save`.
+pub struct Pipeline {
+    pub config: Config,
+    pub datasets: Datasets,
+    pub movies: Vec<MovieRecord>,
+}
+impl Pipeline {
+    pub fn new(config: Config, datasets: Datasets, movies: Vec<MovieRecord>) -> Pipeline {
+        Pipeline { config, datasets, movies }
+    }
+
+    /// Loads the working file (when `config.updating`) and then the datasets.
+    pub fn load(config: Config) -> Result<Pipeline, Error> {
+        // the working file is checked before loading the datasets, which takes a while
+        let mut movies: Vec<MovieRecord> = Vec::new();
+        if config.updating {
+            movies = load_movies(&config.working_file)?;
+        }
+        let datasets = Datasets::load(&config)?;
+
+        Ok(Pipeline::new(config, datasets, movies))
+    }
+
+    /// Identifies the nodes and adds them to their movies, see `add_nodes`.
+    pub fn add_nodes(&mut self, nodes: &[ScannedNode]) {
+        add_nodes(&mut self.movies, &self.config, &self.datasets, nodes);
+    }
+
+    /// The nodes `add_nodes` identifies, the ones with a format of `config.filter_node_format`.
+    /// The others are never in the movies.
+    pub fn filter_nodes(&self, nodes: &[ScannedNode]) -> Vec<ScannedNode> {
+        nodes.iter()
+            .filter(|node| self.config.filter_node_format.contains(&NodeJob::new(&self.config, &node.full_node_name).format))
+            .cloned()
+            .collect()
+    }
+
+    /// Stores the size and modification of the scanned nodes.
+    pub fn update_node_files(&mut self, nodes: &[ScannedNode]) {
+        for node in nodes.iter() {
+            update_node_file(&mut self.movies, node);
+        }
+    }
+
+    /// Marks the nodes missing from `nodes` (all the nodes) and prunes their movies if `config.prune_missing`.
+    pub fn reconcile(&mut self, nodes: &[ScannedNode]) -> Reconciliation {
+        reconcile_nodes(&mut self.movies, nodes, self.config.prune_missing)
+    }
+
+    /// Writes the movies to `config.output_file`, keeping a copy of the previous one.
+    pub fn save(&self) -> Result<(), Error> {
+        save_movies(&self.config.output_file, &self.movies)
+    }
+
+    /// Identifies the nodes of `root` (or `raw_list_movie_file`) whenever they change and writes the
+    /// output, only added, changed or removed nodes are identified again.
+    pub fn watch(&mut self, root: Option<&str>) -> Result<(), Error> {
+        let path = root.unwrap_or(&self.config.raw_list_movie_file).to_string();
+        let watcher = Watcher::new(Path::new(&path)).map_err(|err| Error::io(&path, std::io::Error::other(err)))?;
+        info!(%path, "watching");
+
+        loop {
+            let nodes = match root {
+                Some(root) => scan(root, &self.config.scan, &self.config.filter_node_format).map_err(|err| Error::io(root, err))?,
+                None => read_node_list(&self.config.raw_list_movie_file).map_err(|err| Error::io(&self.config.raw_list_movie_file, err))?,
+            };
+            // the ignored nodes would be added again on every change
+            let nodes = self.filter_nodes(&nodes);
+            let changes = diff_nodes(&known_nodes(&self.movies), &nodes);
+            if !changes.is_empty() {
+                info!(added = changes.added.len(), changed = changes.changed.len(), removed = changes.removed.len(), "nodes changed");
+                let updated: Vec<ScannedNode> = changes.added.into_iter().chain(changes.changed).collect();
+                self.add_nodes(&updated);
+                if root.is_some() {
+                    self.update_node_files(&updated);
+                }
+                log_reconciliation(&self.reconcile(&nodes));
+                write_movies(&self.config.output_file, &self.movies)?;
+                info!(path = %self.config.output_file, "movies updated");
+            }
+            watcher.wait(Duration::from_millis(self.config.watch.debounce_ms), None);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Number of workers for `threads`, 0 means one per CPU core.
+pub fn worker_count(threads: usize) -> usize {
+    match threads {
+        0 => thread::available_parallelism().map(|count| count.get()).unwrap_or(1),
+        threads => threads,
+    }
+}
+
+/// Applies `map` to every item on `threads` workers (see `worker_count`) and returns the results
+/// in the order of `items`. `progress` is called after each item.
+pub fn map_parallel<T, R, M, P>(items: &[T], threads: usize, map: M, progress: P) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    M: Fn(&T) -> R + Sync,
+    P: Fn() + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+    let workers = worker_count(threads).min(items.len());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let item = match items.get(index) {
+                    Some(item) => item,
+                    None => break,
+                };
+                let result = map(item);
+                results.lock().unwrap()[index] = Some(result);
+                progress();
+            });
+        }
+    });
+
+    results.into_inner().unwrap().into_iter().map(|result| result.unwrap()).collect()
+}
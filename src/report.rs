@@ -0,0 +1,185 @@
+use crate::movies::MovieRecord;
+use crate::error::Error;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ReportConfig {
+    /// Prints the report as a table at the end of the run.
+    pub table: bool,
+    /// Writes the report as JSON to this file, nothing if empty.
+    pub json: String,
+    /// Writes the report as an HTML page to this file, nothing if empty.
+    pub html: String,
+}
+impl Default for ReportConfig {
+    fn default() -> ReportConfig {
+        ReportConfig {
+            table: true,
+            json: String::new(),
+            html: String::new(),
+        }
+    }
+}
+
+/// How a run went: the movies of the output compared with the ones of the working file.
+/// The movie without title and year (the nodes whose name can't be parsed) is only counted in
+/// `unparseable_nodes`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Report {
+    pub movies: usize,
+    /// Movies not in the working file.
+    pub added: usize,
+    /// Movies of the working file that changed.
+    pub updated: usize,
+    /// Movies of the working file no longer in the output (pruned).
+    pub removed: usize,
+    pub imdb_none: usize,
+    pub imdb_one: usize,
+    pub imdb_many: usize,
+    /// Movies with a candidate selected by its score.
+    pub imdb_selected: usize,
+    /// Movies with `imdb_confirmed`, the rest need a review.
+    pub confirmed: usize,
+    pub unconfirmed: usize,
+    /// Movies with at least one tmdb (or justwatch) match.
+    pub tmdb: usize,
+    pub justwatch: usize,
+    /// Full node names of the nodes without title.
+    pub unparseable_nodes: Vec<String>,
+}
+
+fn is_untitled(movie: &MovieRecord) -> bool {
+    movie.title.is_empty() && movie.year.is_empty()
+}
+
+/// Compares the movies of the working file (`before`) with the ones written (`after`).
+pub fn build_report(before: &[MovieRecord], after: &[MovieRecord]) -> Report {
+    let before: HashMap<(&str, &str), &MovieRecord> = before.iter()
+        .filter(|movie| !is_untitled(movie))
+        .map(|movie| ((movie.title.as_str(), movie.year.as_str()), movie))
+        .collect();
+    let mut report = Report { ..Default::default() };
+    let mut kept = HashSet::new();
+
+    for movie in after.iter() {
+        if is_untitled(movie) {
+            report.unparseable_nodes.extend(movie.node.iter().map(|node| node.full_node_name.clone()));
+            continue;
+        }
+        report.movies += 1;
+        match before.get(&(movie.title.as_str(), movie.year.as_str())) {
+            None => report.added += 1,
+            Some(previous) => {
+                kept.insert((movie.title.as_str(), movie.year.as_str()));
+                // records aren't comparable, their JSON is
+                if serde_json::to_string(previous).ok() != serde_json::to_string(movie).ok() {
+                    report.updated += 1;
+                }
+            }
+        }
+        match movie.imdb.len() {
+            0 => report.imdb_none += 1,
+            1 => report.imdb_one += 1,
+            _ => report.imdb_many += 1,
+        }
+        if movie.imdb_selected.is_some() {
+            report.imdb_selected += 1;
+        }
+        if movie.imdb_confirmed {
+            report.confirmed += 1;
+        } else {
+            report.unconfirmed += 1;
+        }
+        if !movie.tmdb.is_empty() {
+            report.tmdb += 1;
+        }
+        if !movie.justwatch.is_empty() {
+            report.justwatch += 1;
+        }
+    }
+    report.removed = before.len() - kept.len();
+
+    report
+}
+
+/// Label and value of each line of the report, coverage with its percentage of the movies.
+fn report_rows(report: &Report) -> Vec<(&'static str, String)> {
+    let coverage = |count: usize| match report.movies {
+        0 => count.to_string(),
+        movies => format!("{} ({}%)", count, count * 100 / movies),
+    };
+    vec![
+        ("movies", report.movies.to_string()),
+        ("added", report.added.to_string()),
+        ("updated", report.updated.to_string()),
+        ("removed", report.removed.to_string()),
+        ("imdb: no candidate", coverage(report.imdb_none)),
+        ("imdb: 1 candidate", coverage(report.imdb_one)),
+        ("imdb: many candidates", coverage(report.imdb_many)),
+        ("imdb: selected", coverage(report.imdb_selected)),
+        ("confirmed", coverage(report.confirmed)),
+        ("unconfirmed", coverage(report.unconfirmed)),
+        ("tmdb", coverage(report.tmdb)),
+        ("justwatch", coverage(report.justwatch)),
+        ("unparseable nodes", report.unparseable_nodes.len().to_string()),
+    ]
+}
+
+/// The report as a table for the terminal, followed by the unparseable nodes.
+pub fn format_table(report: &Report) -> String {
+    let rows = report_rows(report);
+    let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    let value_width = rows.iter().map(|(_, value)| value.len()).max().unwrap_or(0);
+    let border = format!("+-{}-+-{}-+\n", "-".repeat(label_width), "-".repeat(value_width));
+
+    let mut table = border.clone();
+    for (label, value) in rows.iter() {
+        table += &format!("| {:<label_width$} | {:>value_width$} |\n", label, value);
+    }
+    table += &border;
+    for full_node_name in report.unparseable_nodes.iter() {
+        table += &format!("# Unparseable: '{}'\n", full_node_name);
+    }
+    table
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// The report as a standalone HTML page.
+pub fn format_html(report: &Report) -> String {
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Notitia report</title>\n</head>\n<body>\n<table>\n");
+    for (label, value) in report_rows(report) {
+        html += &format!("<tr><th>{}</th><td>{}</td></tr>\n", label, value);
+    }
+    html += "</table>\n";
+    if !report.unparseable_nodes.is_empty() {
+        html += "<h2>Unparseable nodes</h2>\n<ul>\n";
+        for full_node_name in report.unparseable_nodes.iter() {
+            html += &format!("<li>{}</li>\n", escape_html(full_node_name));
+        }
+        html += "</ul>\n";
+    }
+    html += "</body>\n</html>\n";
+    html
+}
+
+/// Prints the table and writes the JSON and HTML files of `config`.
+pub fn write_report(config: &ReportConfig, report: &Report) -> Result<(), Error> {
+    if config.table {
+        print!("{}", format_table(report));
+    }
+    if !config.json.is_empty() {
+        let json = serde_json::to_string_pretty(report).map_err(|err| Error::json(&config.json, err))?;
+        fs::write(&config.json, json).map_err(|err| Error::io(&config.json, err))?;
+    }
+    if !config.html.is_empty() {
+        fs::write(&config.html, format_html(report)).map_err(|err| Error::io(&config.html, err))?;
+    }
+
+    Ok(())
+}
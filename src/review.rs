@@ -0,0 +1,190 @@
+use crate::movies::MovieRecord;
+use crate::imdb::Imdb;
+use crate::tmdb::Tmdb;
+use crate::just_watch::Justwatch;
+use std::io::{self, BufRead, Write};
+
+/// What the user answers for the candidates of a source.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Answer {
+    /// Index (from 0) of the candidate picked.
+    Pick(usize),
+    /// None of the candidates is the movie.
+    RejectAll,
+    /// tconst entered by the user, imdb only.
+    Tconst(String),
+    /// Leaves the source unconfirmed.
+    Skip,
+    /// Stops the review, keeping the answers given.
+    Quit,
+}
+
+/// Parses the answer to `prompt`: the number of a candidate (from 1), `r`, `s` (or nothing),
+/// `q` or, when `tconst` is allowed, a tconst. `None` if it is none of them.
+pub fn parse_answer(line: &str, candidates: usize, tconst: bool) -> Option<Answer> {
+    let answer = line.trim().to_ascii_lowercase();
+    match answer.as_str() {
+        "r" => Some(Answer::RejectAll),
+        "s" | "" => Some(Answer::Skip),
+        "q" => Some(Answer::Quit),
+        _ if tconst && answer.len() > 2 && answer.starts_with("tt") && answer[2..].bytes().all(|c| c.is_ascii_digit()) => Some(Answer::Tconst(answer)),
+        _ => match answer.parse::<usize>() {
+            Ok(number) if number >= 1 && number <= candidates => Some(Answer::Pick(number - 1)),
+            _ => None,
+        },
+    }
+}
+
+fn prompt(candidates: usize, tconst: bool) -> String {
+    let pick = match candidates {
+        0 => String::new(),
+        1 => "1 pick, ".to_string(),
+        candidates => format!("1-{} pick, ", candidates),
+    };
+    let tconst = if tconst { "tt... enter a tconst, " } else { "" };
+    format!("{}r reject all, {}s skip, q quit: ", pick, tconst)
+}
+
+fn or_dash<T: ToString>(value: Option<T>) -> String {
+    value.map_or("-".to_string(), |value| value.to_string())
+}
+
+/// One line of an imdb candidate: tconst, type, titles, year, runtime, rating and score.
+pub fn format_imdb(imdb: &Imdb) -> String {
+    let title = if imdb.originalTitle.is_empty() || imdb.originalTitle == imdb.primaryTitle {
+        imdb.primaryTitle.clone()
+    } else if imdb.primaryTitle.is_empty() {
+        imdb.originalTitle.clone()
+    } else {
+        format!("{} ({})", imdb.primaryTitle, imdb.originalTitle)
+    };
+    let rating = imdb.ratings.as_ref().map_or("no rating".to_string(), |rating| format!("{:.1} ({} votes)", rating.averageRating, rating.numVotes));
+    let score = imdb.score.map_or(String::new(), |score| format!("  score {:.2}", score));
+    let text = |text: &str| if text.is_empty() { "-".to_string() } else { text.to_string() };
+    format!("{}  {}  {}  {}  {} min  {}{}", imdb.tconst, text(&imdb.titleType), text(&title), or_dash(imdb.year), or_dash(imdb.runtimeMinutes), rating, score)
+}
+
+/// One line of a tmdb candidate: id, imdb id, year delta, similarity and the beginning of the overview.
+pub fn format_tmdb(tmdb: &Tmdb) -> String {
+    let overview: String = tmdb.overview.as_deref().unwrap_or("").chars().take(60).collect();
+    let similarity = tmdb.similarity.map(|similarity| format!("{:.2}", similarity));
+    format!("{}  imdb {}  year {}  similarity {}  {}", tmdb.id, tmdb.imdb_id, or_dash(tmdb.year_delta.map(|delta| format!("{:+}", delta))), or_dash(similarity), overview)
+}
+
+/// One line of a justwatch candidate: id, title, year, type and imdb id.
+pub fn format_justwatch(justwatch: &Justwatch) -> String {
+    match &justwatch.edge {
+        Some(edge) => {
+            let content = &edge.node.content;
+            format!("{}  {}  {}  {}  imdb {}", justwatch.id, content.title, content.originalReleaseYear, edge.node.objectType, or_dash(content.externalIds.imdbId.as_ref()))
+        },
+        None => justwatch.id.clone(),
+    }
+}
+
+/// Lists the candidates of a source (`marked` with a `*`) and asks until the answer is valid.
+/// The end of `input` is `Quit`.
+fn ask<T, R: BufRead, W: Write>(input: &mut R, output: &mut W, source: &str, candidates: &[T], format: impl Fn(&T) -> String, marked: impl Fn(&T) -> bool, tconst: bool) -> io::Result<Answer> {
+    writeln!(output, "{}:", source)?;
+    if candidates.is_empty() {
+        writeln!(output, "  no candidates")?;
+    }
+    for (i, candidate) in candidates.iter().enumerate() {
+        writeln!(output, "  {}) {}{}", i + 1, format(candidate), if marked(candidate) { " *" } else { "" })?;
+    }
+    loop {
+        write!(output, "{}", prompt(candidates.len(), tconst))?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(Answer::Quit);
+        }
+        match parse_answer(&line, candidates.len(), tconst) {
+            Some(answer) => return Ok(answer),
+            None => writeln!(output, "unknown answer '{}'", line.trim())?,
+        }
+    }
+}
+
+/// Keeps the candidate picked (or none if all are rejected), `true` if the source is confirmed.
+fn keep_answered<T: Clone>(candidates: &mut Vec<T>, answer: &Answer) -> bool {
+    match answer {
+        Answer::Pick(i) => {
+            *candidates = vec![candidates[*i].clone()];
+            true
+        },
+        Answer::RejectAll => {
+            candidates.clear();
+            true
+        },
+        _ => false,
+    }
+}
+
+/// Movies reviewed and sources confirmed by a review.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReviewSummary {
+    pub reviewed: usize,
+    pub confirmed: usize,
+}
+
+/// Asks for the imdb, tmdb and justwatch candidates not confirmed of every movie (except the one
+/// without title and year). The candidate picked is the only one kept, rejecting all of them keeps
+/// none, in both cases the source is confirmed. `lookup` finds the tconsts entered by the user.
+pub fn review_movies<R: BufRead, W: Write>(movies: &mut [MovieRecord], lookup: impl Fn(&str) -> Option<Imdb>, input: &mut R, output: &mut W) -> io::Result<ReviewSummary> {
+    let mut summary = ReviewSummary { ..Default::default() };
+    for movie in movies.iter_mut() {
+        if (movie.title.is_empty() && movie.year.is_empty()) || (movie.imdb_confirmed && movie.tmdb_confirmed && movie.justwatch_confirmed) {
+            continue;
+        }
+        summary.reviewed += 1;
+        writeln!(output, "\n# {} ({})", movie.title, movie.year)?;
+        for node in movie.node.iter() {
+            writeln!(output, "  {}", node.full_node_name)?;
+        }
+
+        while !movie.imdb_confirmed {
+            let imdbs = match ask(input, output, "imdb", &movie.imdb, format_imdb, |imdb| movie.imdb_selected.as_ref() == Some(&imdb.tconst), true)? {
+                Answer::Pick(i) => vec![movie.imdb[i].clone()],
+                Answer::RejectAll => Vec::new(),
+                Answer::Tconst(tconst) => match lookup(&tconst) {
+                    Some(imdb) => vec![imdb],
+                    None => {
+                        writeln!(output, "{} not found", tconst)?;
+                        continue;
+                    },
+                },
+                Answer::Skip => break,
+                Answer::Quit => return Ok(summary),
+            };
+            movie.imdb_selected = imdbs.first().map(|imdb| imdb.tconst.clone());
+            movie.imdb = imdbs;
+            movie.imdb_confirmed = true;
+            summary.confirmed += 1;
+        }
+
+        if !movie.tmdb_confirmed {
+            let answer = ask(input, output, "tmdb", &movie.tmdb, format_tmdb, |_| false, false)?;
+            if answer == Answer::Quit {
+                return Ok(summary);
+            }
+            if keep_answered(&mut movie.tmdb, &answer) {
+                movie.tmdb_confirmed = true;
+                summary.confirmed += 1;
+            }
+        }
+
+        if !movie.justwatch_confirmed {
+            let answer = ask(input, output, "justwatch", &movie.justwatch, format_justwatch, |_| false, false)?;
+            if answer == Answer::Quit {
+                return Ok(summary);
+            }
+            if keep_answered(&mut movie.justwatch, &answer) {
+                movie.justwatch_confirmed = true;
+                summary.confirmed += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,204 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::node_name::parse_node_name;
+
+/// Words that mark one file of a multi-file release (`CD1`, `Part 2`, `disc3`...).
+static PART_HINTS: &[&str] = &["cd", "disc", "disk", "dvd", "part", "pt"];
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ScanConfig {
+    /// Extensions of the media files, any other file is skipped.
+    pub extensions: Vec<String>,
+    /// Names of files and folders to skip, case insensitive, `*` matches any text.
+    pub ignore: Vec<String>,
+}
+impl Default for ScanConfig {
+    fn default() -> ScanConfig {
+        let list = |items: &[&str]| items.iter().map(|item| item.to_string()).collect();
+        ScanConfig {
+            extensions: list(&["mkv", "mp4", "avi", "iso", "m4v", "mov", "wmv", "mpg", "mpeg", "ts", "m2ts", "webm"]),
+            ignore: list(&[
+                ".*",
+                "sample", "samples", "sample-*", "*-sample.*", "*.sample.*",
+                "extras", "featurettes", "behind the scenes", "deleted scenes", "interviews", "trailers", "*-trailer.*",
+                "subs", "subtitles", "*.srt", "*.sub", "*.idx", "*.ass", "*.ssa",
+            ]),
+        }
+    }
+}
+
+/// A node found in the library: a media file, a group of files of the same
+/// release, or a release folder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScannedNode {
+    pub full_node_name: String,
+    /// Bytes of all the media files of the node.
+    pub size: u64,
+    /// Last modification of the media files, seconds since the unix epoch.
+    pub modified: Option<u64>,
+}
+
+/// Whether `name` matches `pattern`, case insensitive, `*` matches any text.
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.to_lowercase(), name.to_lowercase());
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == name;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !name.starts_with(first) {
+        return false;
+    }
+    let mut rest = &name[first.len()..];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+fn is_ignored(name: &str, config: &ScanConfig) -> bool {
+    config.ignore.iter().any(|pattern| matches_pattern(pattern, name))
+}
+
+/// Media files have one of the configured extensions or one of the node formats (`.movie`...).
+fn is_media(name: &str, config: &ScanConfig, formats: &[String]) -> bool {
+    match Path::new(name).extension().map(|extension| extension.to_string_lossy()) {
+        Some(extension) => config.extensions.iter().any(|item| item.eq_ignore_ascii_case(&extension)) || formats.iter().any(|format| *format == extension),
+        None => false,
+    }
+}
+
+/// Folders with a year in their name are a release, everything inside belongs to one node.
+fn is_release(name: &str) -> bool {
+    parse_node_name(name, &[]).year.is_some()
+}
+
+fn is_separator(character: char) -> bool {
+    matches!(character, ' ' | '.' | '_' | '-')
+}
+
+/// Name of the release of a file without its part marker (`Movie (2001) CD2.avi` is `Movie (2001)`),
+/// `None` if the file is not one part of a release.
+pub fn release_name(file_name: &str) -> Option<String> {
+    let stem = match file_name.rfind('.') {
+        Some(index) => &file_name[..index],
+        None => file_name,
+    };
+    let without_number = stem.trim_end_matches(|character: char| character.is_ascii_digit());
+    if without_number.len() == stem.len() {
+        return None;
+    }
+    let without_number = without_number.trim_end_matches(is_separator);
+    PART_HINTS.iter().find_map(|hint| {
+        let index = without_number.len().checked_sub(hint.len())?;
+        if !without_number.is_char_boundary(index) || !without_number[index..].eq_ignore_ascii_case(hint) {
+            return None;
+        }
+        let release = &without_number[..index];
+        match release.chars().last() {
+            Some(character) if is_separator(character) => Some(release.trim_end_matches(is_separator).to_string()),
+            _ => None,
+        }
+    })
+}
+
+fn modified_seconds(modified: io::Result<SystemTime>) -> Option<u64> {
+    modified.ok()?.duration_since(UNIX_EPOCH).ok().map(|duration| duration.as_secs())
+}
+
+fn sorted_entries(path: &Path) -> io::Result<Vec<fs::DirEntry>> {
+    let mut entries = fs::read_dir(path)?.collect::<io::Result<Vec<fs::DirEntry>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    Ok(entries)
+}
+
+/// Size and last modification of the media files inside a release folder.
+fn release_files(path: &Path, config: &ScanConfig, formats: &[String], node: &mut ScannedNode) -> io::Result<usize> {
+    let mut files = 0;
+    for entry in sorted_entries(path)? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if is_ignored(&name, config) {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            files += release_files(&entry.path(), config, formats, node)?;
+        } else if metadata.is_file() && is_media(&name, config, formats) {
+            node.size += metadata.len();
+            node.modified = node.modified.max(modified_seconds(metadata.modified()));
+            files += 1;
+        }
+    }
+    Ok(files)
+}
+
+fn scan_directory(path: &Path, prefix: &str, config: &ScanConfig, formats: &[String], nodes: &mut Vec<ScannedNode>) -> io::Result<()> {
+    // the files of a multi-file release are grouped in the node of its first file
+    let mut releases: Vec<(String, usize)> = Vec::new();
+
+    for entry in sorted_entries(path)? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if is_ignored(&name, config) {
+            continue;
+        }
+        let full_node_name = format!("{}{}", prefix, name);
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            if is_release(&name) {
+                let mut node = ScannedNode { full_node_name: format!("{}/", full_node_name), size: 0, modified: None };
+                if release_files(&entry.path(), config, formats, &mut node)? > 0 {
+                    nodes.push(node);
+                }
+            } else {
+                scan_directory(&entry.path(), &format!("{}/", full_node_name), config, formats, nodes)?;
+            }
+        } else if metadata.is_file() && is_media(&name, config, formats) {
+            let (size, modified) = (metadata.len(), modified_seconds(metadata.modified()));
+            let release = release_name(&name);
+            match release.as_ref().and_then(|release| releases.iter().find(|(item, _)| item == release)) {
+                Some((_, index)) => {
+                    let node = &mut nodes[*index];
+                    node.size += size;
+                    node.modified = node.modified.max(modified);
+                },
+                None => {
+                    if let Some(release) = release {
+                        releases.push((release, nodes.len()));
+                    }
+                    nodes.push(ScannedNode { full_node_name, size, modified });
+                },
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Walks the library under `root` and returns its nodes, named from `root` with `/` separators.
+/// Folders ending with `/` are release folders (with a year in their name), the other folders
+/// are walked, every media file is a node but the parts of a release (`CD1`, `CD2`...) are one node.
+/// `formats` are the node formats also accepted as extensions.
+pub fn scan(root: &str, config: &ScanConfig, formats: &[String]) -> io::Result<Vec<ScannedNode>> {
+    let mut nodes: Vec<ScannedNode> = Vec::new();
+    let prefix = format!("{}/", root.trim_end_matches(['\\', '/']));
+    scan_directory(Path::new(root), &prefix, config, formats, &mut nodes)?;
+
+    Ok(nodes)
+}
+
+/// Nodes of a node list (`raw_list_movie_file`), one full node name per line, without size or modification.
+/// Blank lines and lines beginning with `#` are skipped.
+pub fn read_node_list(path: &str) -> io::Result<Vec<ScannedNode>> {
+    let list = fs::read_to_string(path)?;
+    Ok(list.lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|line| ScannedNode { full_node_name: line.to_string(), size: 0, modified: None })
+        .collect())
+}
@@ -0,0 +1,117 @@
+use crate::imdb::Imdb;
+use crate::utils::{remove_diacritics, title_ready};
+
+static WEIGHT_TITLE_TYPE: f32 = 0.25;
+static WEIGHT_TITLE: f32 = 0.20;
+static WEIGHT_YEAR: f32 = 0.15;
+static WEIGHT_VOTES: f32 = 0.15;
+static WEIGHT_RUNTIME: f32 = 0.15;
+static WEIGHT_AKA_REGION: f32 = 0.10;
+
+/// Shorts are up to 40 minutes (the Academy's definition), movies are longer.
+static SHORT_MAX_RUNTIME: u32 = 40;
+
+/// What we know about the node a candidate has to match.
+pub struct ScoreContext<'a> {
+    pub title: &'a String,
+    pub title_optional: &'a String,
+    pub format: &'a str,
+    /// tconsts found in the akas of the configured region.
+    pub aka_tconsts: &'a [String],
+}
+
+fn same_title(a: &str, b: &str) -> bool {
+    !a.is_empty() && title_ready(&remove_diacritics(a)) == title_ready(&remove_diacritics(b))
+}
+
+/// 1 if the title type is the node format, 0.5 for the related title types.
+fn score_title_type(titleType: &str, format: &str) -> f32 {
+    if titleType == format {
+        return 1.0;
+    }
+    match (format, titleType) {
+        ("movie", "tvMovie") | ("tvSeries", "tvMiniSeries") | ("short", "tvShort") => 0.5,
+        _ => 0.0,
+    }
+}
+
+/// The primary title is the best match, then the original title.
+fn score_title(imdb: &Imdb, context: &ScoreContext) -> f32 {
+    let titles = [context.title, context.title_optional];
+    if titles.iter().any(|title| same_title(title, &imdb.primaryTitle)) {
+        1.0
+    } else if titles.iter().any(|title| same_title(title, &imdb.originalTitle)) {
+        0.8
+    } else {
+        0.0
+    }
+}
+
+/// Years between the candidate and the node (unknown delta means exact year).
+fn year_distance(imdb: &Imdb) -> i16 {
+    imdb.year_delta.map_or(0, |delta| delta.abs())
+}
+
+/// 1 for the year of the node, less the further the year is.
+fn score_year(imdb: &Imdb) -> f32 {
+    1.0 / (1.0 + year_distance(imdb) as f32)
+}
+
+/// Votes relative to the most voted candidate, in a logarithmic scale.
+fn score_votes(imdb: &Imdb, max_votes: u32) -> f32 {
+    let votes = imdb.ratings.as_ref().map_or(0, |rating| rating.numVotes);
+    if max_votes == 0 {
+        return 0.0;
+    }
+    ((votes as f32) + 1.0).ln() / ((max_votes as f32) + 1.0).ln()
+}
+
+/// Whether the runtime is the one expected for the node format, 0.5 when it is unknown.
+fn score_runtime(imdb: &Imdb, format: &str) -> f32 {
+    match (imdb.runtimeMinutes, format) {
+        (None, _) => 0.5,
+        (Some(runtime), "short") => if runtime <= SHORT_MAX_RUNTIME { 1.0 } else { 0.0 },
+        (Some(runtime), "movie") => if runtime > SHORT_MAX_RUNTIME { 1.0 } else { 0.0 },
+        (Some(_), _) => 0.5,
+    }
+}
+
+/// Score of one candidate between 0 and 1.
+pub fn score_imdb(imdb: &Imdb, context: &ScoreContext, max_votes: u32) -> f32 {
+    let aka_region = if context.aka_tconsts.contains(&imdb.tconst) { 1.0 } else { 0.0 };
+
+    WEIGHT_TITLE_TYPE * score_title_type(&imdb.titleType, context.format)
+        + WEIGHT_TITLE * score_title(imdb, context)
+        + WEIGHT_YEAR * score_year(imdb)
+        + WEIGHT_VOTES * score_votes(imdb, max_votes)
+        + WEIGHT_RUNTIME * score_runtime(imdb, context.format)
+        + WEIGHT_AKA_REGION * aka_region
+}
+
+/// Stores the score of every candidate and sorts them, the closest to the year of the node first
+/// and the best scored first among the candidates of the same year.
+pub fn score_imdbs(imdbs: &mut [Imdb], context: &ScoreContext) {
+    let max_votes = imdbs.iter()
+        .map(|imdb| imdb.ratings.as_ref().map_or(0, |rating| rating.numVotes))
+        .max()
+        .unwrap_or(0);
+    for imdb in imdbs.iter_mut() {
+        imdb.score = Some(score_imdb(imdb, context, max_votes));
+    }
+    imdbs.sort_by(|a, b| year_distance(a).cmp(&year_distance(b))
+        .then(b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal)));
+}
+
+/// The first candidate, if its score reaches `threshold` and there is no tie with another candidate
+/// as close to the year. `imdbs` must be sorted by `score_imdbs`.
+pub fn select_imdb(imdbs: &[Imdb], threshold: f32) -> Option<&Imdb> {
+    let best = imdbs.first()?;
+    let best_score = best.score?;
+    if best_score < threshold {
+        return None;
+    }
+    match imdbs.get(1).filter(|second| year_distance(second) == year_distance(best)).and_then(|second| second.score) {
+        Some(second_score) if second_score >= best_score => None,
+        _ => Some(best),
+    }
+}
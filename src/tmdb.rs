@@ -1,15 +1,18 @@
-use crate::utils::{title_ready, get_line_by_index, search_indices};
-use crate::imdb::{Imdb};
-use std::fs;
+use crate::utils::{title_ready, SimilarTitle, year_delta, years_around, get_line_by_index, BatchIndices};
+use crate::imdb::{Imdb, NodeTitle};
+use crate::pool::map_parallel;
+use crate::logging::{progress_stage, progress_inc};
+use crate::dataset::Dataset;
+use crate::error::Error;
+use crate::config::{Config, MatchingConfig};
 use serde::{Deserialize, Serialize};
-
-static DATA_TMDBS: &str = "files/tmdb/movies_metadata.csv";
-static DATA_TMDBS_BIG: &str = "files/tmdb/archive.csv";
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use tracing::{debug, info, info_span, warn};
 
 #[derive(Debug, Deserialize, Clone)]
 #[derive(Serialize)]
 #[allow(non_camel_case_types)]
-#[derive(PartialEq)]
+#[derive(PartialEq, Default)]
 pub struct TitleTmdbsRecord {
     pub adult: String, // (boolean) = False
     pub belongs_to_collection: String, // (json) = "{'id': 1565, 'name': '28 Days/Weeks Later Collection', 'poster_path': '/4AeGz1Jwnq6ky2kfLT95Tm8nEiw.jpg', 'backdrop_path': '/bpn1vynevsKLwAMRikY1L6cb18p.jpg'}"
@@ -36,41 +39,11 @@ pub struct TitleTmdbsRecord {
     pub vote_average: String, // 7.1
     pub vote_count: String, // 1816
 }
-impl Default for TitleTmdbsRecord {
-    fn default() -> TitleTmdbsRecord {
-        TitleTmdbsRecord {
-            adult: String::new(), // (boolean) = False
-            belongs_to_collection: String::new(), // (json) = "{'id': 1565, 'name': '28 Days/Weeks Later Collection', 'poster_path': '/4AeGz1Jwnq6ky2kfLT95Tm8nEiw.jpg', 'backdrop_path': '/bpn1vynevsKLwAMRikY1L6cb18p.jpg'}"
-            budget: String::new(), // 5000000
-            genres: String::new(), // (json) = "[{'id': 27, 'name': 'Horror'}, {'id': 53, 'name': 'Thriller'}, {'id': 878, 'name': 'Science Fiction'}]"
-            homepage: String::new(), //
-            id: String::new(), // 170
-            imdb_id: String::new(), // tt0289043
-            original_language: String::new(), // en
-            original_title: String::new(), // 28 Days Later
-            overview: None, // "Twenty-eight days after a killer virus was accidentally unleashed from a British research facility, ..."
-            popularity: String::new(), // 17.656951
-            poster_path: String::new(), // (no parece funcionar muchos, usa belongs_to_collection) = /xaYdxi1PBEAYvqknvAmMPK5Eff3.jpg
-            production_companies: String::new(), // (json) = "[{'name': 'DNA Films', 'id': 284}, {'name': 'British Film Council', 'id': 10889}]"
-            production_countries: String::new(), // (json) = "[{'iso_3166_1': 'GB', 'name': 'United Kingdom'}]"
-            release_date: String::new(), // 2002-10-31
-            revenue: String::new(), // 82719885
-            runtime: String::new(), // 113.0
-            spoken_languages: String::new(), // (json) = "[{'iso_639_1': 'es', 'name': 'Español'}, {'iso_639_1': 'en', 'name': 'English'}]"
-            status: String::new(), // Released
-            tagline: String::new(), // His fear began when he woke up alone. His terror began when he realised he wasn't.,
-            title: String::new(), // 28 Days Later
-            video: String::new(), // False
-            vote_average: String::new(), // 7.1
-            vote_count: String::new(), // 1816
-        }
-    }
-}
 
 #[derive(Debug, Deserialize, Clone)]
 #[derive(Serialize)]
 #[allow(non_camel_case_types)]
-#[derive(PartialEq)]
+#[derive(PartialEq, Default)]
 pub struct TitleTmdbsBigRecord {
     pub id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -117,38 +90,11 @@ pub struct TitleTmdbsBigRecord {
     recommendations: 436270-829280-76600-56969-312634-1037858-238-551271-22023-736526-899112-468073-632856-1050535-1050957-112627-774752-322484-338958-882598
     */
 }
-impl Default for TitleTmdbsBigRecord {
-    fn default() -> TitleTmdbsBigRecord {
-        TitleTmdbsBigRecord {
-            id: String::new(),
-            imdb_id: None,
-            title: String::new(),
-            genres: String::new(),
-            original_language: String::new(),
-            overview: None,
-            popularity: String::new(),
-            production_companies: String::new(),
-            release_date: String::new(),
-            budget: String::new(),
-            revenue: String::new(),
-            runtime: String::new(),
-            status: String::new(),
-            tagline: String::new(),
-            vote_average: String::new(),
-            vote_count: String::new(),
-            credits: String::new(),
-            keywords: String::new(),
-            poster_path: String::new(),
-            backdrop_path: String::new(),
-            recommendations: String::new(),
-        }
-    }
-}
 
 #[derive(Debug, Deserialize, Clone)]
 #[derive(Serialize)]
 #[allow(non_camel_case_types)]
-#[derive(PartialEq)]
+#[derive(PartialEq, Default)]
 pub struct TmdbRecord {
     pub id: String,
     pub title: String,
@@ -172,37 +118,10 @@ pub struct TmdbRecord {
     pub recommendations: String,
     pub imdb_id: String,
 }
-impl Default for TmdbRecord {
-    fn default() -> TmdbRecord {
-        TmdbRecord {
-            id: String::new(),
-            imdb_id: String::new(),
-            title: String::new(),
-            genres: String::new(),
-            original_language: String::new(),
-            overview: None,
-            popularity: String::new(),
-            production_companies: String::new(),
-            release_date: String::new(),
-            budget: String::new(),
-            revenue: String::new(),
-            runtime: String::new(),
-            status: String::new(),
-            tagline: String::new(),
-            vote_average: String::new(),
-            vote_count: String::new(),
-            credits: String::new(),
-            keywords: String::new(),
-            poster_path: String::new(),
-            backdrop_path: String::new(),
-            recommendations: String::new(),
-        }
-    }
-}
 
 #[derive(PartialEq)]
 #[derive(Debug, Deserialize, Clone)]
-#[derive(Serialize)]
+#[derive(Serialize, Default)]
 pub struct Tmdb {
     pub id: String, // "id" = 170
     pub imdb_id: String, // "imdb_id" = tt0289043
@@ -210,18 +129,12 @@ pub struct Tmdb {
 
     pub poster_path: String, // from belongs_to_collection
     pub backdrop_path: String, // from belongs_to_collection
-}
-impl Default for Tmdb {
-    fn default() -> Tmdb {
-        Tmdb {
-            id: String::new(),
-            imdb_id: String::new(),
-            overview: None,
-            
-            poster_path: String::new(),
-            backdrop_path: String::new(),
-        }
-    }
+    /// Similarity between the node title and the matched title (see `utils::title_similarity`).
+    #[serde(default)]
+    pub similarity: Option<f32>,
+    /// Release year minus the year of the node.
+    #[serde(default)]
+    pub year_delta: Option<i16>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -234,74 +147,141 @@ pub struct BelongsToCollection {
     pub overview: Option<String>, // 'In the year 180, the death of emperor Marcus Aurelius throws the Roman Empire into chaos...'
 }
 
-pub fn search_tmdbs(data_tmdbs: &String, tmdbs: &mut Vec::<Tmdb>, current_title: &String, current_year: &String, tab_at_the_beginning: bool) -> Result<(), Box<dyn std::error::Error>> {
-    let title = title_ready(&current_title);
-    let title_tabs = if tab_at_the_beginning { format!(",{},", title_ready(&current_title)) } else { format!("{},", title_ready(&current_title)) };
-    for i in search_indices(&data_tmdbs, &title_tabs) {
-        let line = get_line_by_index(&data_tmdbs, &i, true);
-        let tmdb = get_tmdb(&line);
-
-        let is_imdb_id = tmdb.imdb_id.to_ascii_lowercase() == title;
-        if is_imdb_id || title_ready(&tmdb.title.to_ascii_lowercase()) == title {
-            if is_imdb_id || tmdb.release_date.get(0..4) == Some(current_year) { // TODO: 1995-12-15 -> 1995
-                if tmdbs.iter().find(|&item| item.id == tmdb.id) == None {
-                    let mut new_tmdb = Tmdb {
-                        id: tmdb.id,
-                        imdb_id: tmdb.imdb_id,
-                        overview: tmdb.overview,
-
-                        poster_path: String::new(),
-                        backdrop_path: String::new()
-                    };
-                    if tmdb.belongs_to_collection != "" {
-                        match serde_json::from_str::<BelongsToCollection>(&tmdb.belongs_to_collection.replace("'", "\"")) {
-                            Ok(belongs_to_collection_json) => {
-                                new_tmdb.poster_path = belongs_to_collection_json.poster_path;
-                                new_tmdb.backdrop_path = belongs_to_collection_json.backdrop_path;
-                            },
-                            Err(err) => {
-                                println!("{:?}", err);
-                            }
-                        };
-                    }
-                    tmdbs.push(new_tmdb);
-                }
+fn release_year(release_date: &str) -> Option<u16> {
+    release_date.get(0..4)?.parse().ok()
+}
+
+fn check_tmdb(data_tmdbs: &[u8], i: usize, tmdbs: &mut Vec::<Tmdb>, similar: &SimilarTitle, current_year: &str, matching: &MatchingConfig) {
+    let title = title_ready(similar.title());
+    let line = get_line_by_index(data_tmdbs, &i, true);
+    let tmdb = get_tmdb(&line);
+
+    let is_imdb_id = tmdb.imdb_id.to_ascii_lowercase() == title;
+    let similarity = if is_imdb_id { 1.0 } else { similar.similarity(&tmdb.title, matching.similarity_threshold) };
+    if similarity >= matching.similarity_threshold {
+        let delta = year_delta(release_year(&tmdb.release_date), current_year, matching.year_tolerance);
+        if (is_imdb_id || delta.is_some()) && !tmdbs.iter().any(|item| item.id == tmdb.id) {
+            let delta = if is_imdb_id { year_delta(release_year(&tmdb.release_date), current_year, u16::MAX) } else { delta };
+            tmdbs.push(new_tmdb(tmdb, Some(similarity), delta));
+        }
+    }
+}
+
+/// Tmdb of a line of the metadata, with the posters of its collection.
+fn new_tmdb(tmdb: TitleTmdbsRecord, similarity: Option<f32>, year_delta: Option<i16>) -> Tmdb {
+    let mut new_tmdb = Tmdb {
+        id: tmdb.id,
+        imdb_id: tmdb.imdb_id,
+        overview: tmdb.overview,
+
+        poster_path: String::new(),
+        backdrop_path: String::new(),
+        similarity,
+        year_delta,
+    };
+    if !tmdb.belongs_to_collection.is_empty() {
+        match serde_json::from_str::<BelongsToCollection>(&tmdb.belongs_to_collection.replace("'", "\"")) {
+            Ok(belongs_to_collection_json) => {
+                new_tmdb.poster_path = belongs_to_collection_json.poster_path;
+                new_tmdb.backdrop_path = belongs_to_collection_json.backdrop_path;
+            },
+            Err(err) => {
+                debug!(id = %new_tmdb.id, "invalid belongs_to_collection: {}", err);
             }
+        };
+    }
+    new_tmdb
+}
+
+fn check_tmdb_big(data_tmdbs: &[u8], i: usize, tmdbs: &mut Vec::<Tmdb>, similar: &SimilarTitle, current_year: &str, matching: &MatchingConfig) {
+    let title = title_ready(similar.title());
+    let line = get_line_by_index(data_tmdbs, &i, true);
+    let tmdb = get_tmdb_big(&line);
+
+    let similarity = if tmdb.id.to_ascii_lowercase() == title { 1.0 } else { similar.similarity(&tmdb.title, matching.similarity_threshold) };
+    if similarity >= matching.similarity_threshold {
+        if let Some(delta) = year_delta(release_year(&tmdb.release_date), current_year, matching.year_tolerance) {
+            if !tmdbs.iter().any(|item| item.id == tmdb.id) {
+                tmdbs.push(Tmdb {
+                    id: tmdb.id,
+                    imdb_id: String::new(),
+                    overview: tmdb.overview,
+                    
+                    poster_path: tmdb.poster_path,
+                    backdrop_path: tmdb.backdrop_path,
+                    similarity: Some(similarity),
+                    year_delta: Some(delta),
+                    });
+            };
         }
     }
+}
 
+fn release_date_patterns(current_year: &str, year_tolerance: u16) -> Vec<String> {
+    years_around(current_year, year_tolerance).iter().map(|year| format!(",{}-", year)).collect()
+}
+
+/// Pattern searched for a title (or an id when `tab_at_the_beginning` is false).
+fn tmdb_pattern(current_title: &str, tab_at_the_beginning: bool) -> String {
+    if tab_at_the_beginning { format!(",{},", title_ready(current_title)) } else { format!("{},", title_ready(current_title)) }
+}
+
+pub fn search_tmdbs(data_tmdbs: &[u8], batch: &BatchIndices, tmdbs: &mut Vec::<Tmdb>, current_title: &str, current_year: &str, tab_at_the_beginning: bool, matching: &MatchingConfig) -> Result<(), Error> {
+    let similar = SimilarTitle::new(current_title);
+    let title_tabs = tmdb_pattern(current_title, tab_at_the_beginning);
+    for i in batch.search_indices(data_tmdbs, &title_tabs)? {
+        check_tmdb(data_tmdbs, i, tmdbs, &similar, current_year, matching);
+    }
     Ok(())
 }
 
-pub fn search_tmdbs_big(data_tmdbs: &String, tmdbs: &mut Vec::<Tmdb>, current_title: &String, current_year: &String, tab_at_the_beginning: bool) -> Result<(), Box<dyn std::error::Error>> {
-    let title = title_ready(&current_title);
-    let title_tabs = if tab_at_the_beginning { format!(",{},", title_ready(&current_title)) } else { format!("{},", title_ready(&current_title)) };
-    for i in search_indices(&data_tmdbs, &title_tabs) {
-        let line = get_line_by_index(&data_tmdbs, &i, true);
-        let tmdb = get_tmdb_big(&line);
-
-        if tmdb.id.to_ascii_lowercase() == title 
-            || title_ready(&tmdb.title.to_ascii_lowercase()) == title {
-            if tmdb.release_date.get(0..4) == Some(current_year) { // TODO: 1995-12-15 -> 1995
-                if tmdbs.iter().find(|&item| item.id == tmdb.id) == None {
-                    tmdbs.push(Tmdb {
-                        id: tmdb.id,
-                        imdb_id: String::new(),
-                        overview: tmdb.overview,
-                        
-                        poster_path: tmdb.poster_path,
-                        backdrop_path: tmdb.backdrop_path,
-                        });
-                };
-            }
-        }
+/// Searches the titles of the years around `current_year` similar to `current_title`, used when `search_tmdbs` finds nothing.
+pub fn search_tmdbs_similar(data_tmdbs: &[u8], batch: &BatchIndices, tmdbs: &mut Vec::<Tmdb>, current_title: &str, current_year: &str, matching: &MatchingConfig) -> Result<(), Error> {
+    let similar = SimilarTitle::new(current_title);
+    for i in batch.line_indices_containing(data_tmdbs, &release_date_patterns(current_year, matching.year_tolerance))? {
+        check_tmdb(data_tmdbs, i, tmdbs, &similar, current_year, matching);
+    }
+    Ok(())
+}
+
+pub fn search_tmdbs_big(data_tmdbs: &[u8], batch: &BatchIndices, tmdbs: &mut Vec::<Tmdb>, current_title: &str, current_year: &str, tab_at_the_beginning: bool, matching: &MatchingConfig) -> Result<(), Error> {
+    let similar = SimilarTitle::new(current_title);
+    let title_tabs = tmdb_pattern(current_title, tab_at_the_beginning);
+    for i in batch.search_indices(data_tmdbs, &title_tabs)? {
+        check_tmdb_big(data_tmdbs, i, tmdbs, &similar, current_year, matching);
     }
+    Ok(())
+}
 
+/// Same as `search_tmdbs_similar` for the archive.
+pub fn search_tmdbs_big_similar(data_tmdbs: &[u8], batch: &BatchIndices, tmdbs: &mut Vec::<Tmdb>, current_title: &str, current_year: &str, matching: &MatchingConfig) -> Result<(), Error> {
+    let similar = SimilarTitle::new(current_title);
+    for i in batch.line_indices_containing(data_tmdbs, &release_date_patterns(current_year, matching.year_tolerance))? {
+        check_tmdb_big(data_tmdbs, i, tmdbs, &similar, current_year, matching);
+    }
     Ok(())
 }
 
-pub fn get_tmdb(line: &String) -> TitleTmdbsRecord {
+// malformed lines of tmdb logged as warnings in a run, the next ones are logged at debug level
+static MALFORMED_LINES_LOGGED: usize = 10;
+static MALFORMED_LINES: AtomicUsize = AtomicUsize::new(0);
 
+/// Logs a tmdb line that can't be read, like `Columns::warn_malformed_line` does for imdb: the first
+/// `MALFORMED_LINES_LOGGED` of the run are warnings and the next ones are logged at debug level.
+fn warn_malformed_line(dataset: &str, line: &str, err: &csv::Error) {
+    let line: String = line.chars().take(80).collect();
+    let count = MALFORMED_LINES.fetch_add(1, AtomicOrdering::Relaxed);
+    if count < MALFORMED_LINES_LOGGED {
+        warn!(dataset, %line, "malformed line skipped: {}", err);
+    } else {
+        if count == MALFORMED_LINES_LOGGED {
+            warn!(dataset, "more malformed lines, the next ones are logged at debug level");
+        }
+        debug!(dataset, %line, "malformed line skipped: {}", err);
+    }
+}
+
+pub fn get_tmdb(line: &str) -> TitleTmdbsRecord {
     let line_and_header = "adult,belongs_to_collection,budget,genres,homepage,id,imdb_id,original_language,original_title,overview,popularity,poster_path,production_companies,production_countries,release_date,revenue,runtime,spoken_languages,status,tagline,title,video,vote_average,vote_count\n".to_string() + line;
 
     let mut rdr = csv::ReaderBuilder::new()
@@ -316,11 +296,8 @@ pub fn get_tmdb(line: &String) -> TitleTmdbsRecord {
     for result in rdr.deserialize() {
         let record: TitleTmdbsRecord = match result {
             Ok(record) => record,
-            Err(_err) => {
-                /*match *err.kind() {
-                    csv::ErrorKind::Deserialize { .. } => { println!("{:?}", err); }
-                    _ => { println!("not a deserialize error"); }
-                }*/
+            Err(err) => {
+                warn_malformed_line("tmdb", line, &err);
                 continue;
             }
         };
@@ -329,7 +306,7 @@ pub fn get_tmdb(line: &String) -> TitleTmdbsRecord {
     record_return
 }
 
-pub fn get_tmdb_big(line: &String) -> TitleTmdbsBigRecord {
+pub fn get_tmdb_big(line: &str) -> TitleTmdbsBigRecord {
     let line_and_header = "id,title,genres,original_language,overview,popularity,production_companies,release_date,budget,revenue,runtime,status,tagline,vote_average,vote_count,credits,keywords,poster_path,backdrop_path,recommendations\n".to_string() + line;
 
     let mut rdr = csv::ReaderBuilder::new()
@@ -344,11 +321,8 @@ pub fn get_tmdb_big(line: &String) -> TitleTmdbsBigRecord {
     for result in rdr.deserialize() {
         let record: TitleTmdbsBigRecord = match result {
             Ok(record) => record,
-            Err(_err) => {
-                /*match *err.kind() {
-                    csv::ErrorKind::Deserialize { .. } => { println!("{:?}", err); }
-                    _ => { println!("not a deserialize error"); }
-                }*/
+            Err(err) => {
+                warn_malformed_line("tmdb archive", line, &err);
                 continue;
             }
         };
@@ -357,73 +331,246 @@ pub fn get_tmdb_big(line: &String) -> TitleTmdbsBigRecord {
     record_return
 }
 
-pub fn search_tmdbs_by_imdb(data_tmdbs: &String, imdbs: &Vec<Imdb>, (title, title_optional, year): (&String, &String, &str)) -> Vec<Tmdb> {
-    let mut tmdbs = Vec::<Tmdb>::new();
-    if imdbs.len() == 0 {
-        println!("search_tmdbs...");
-        search_tmdbs(&data_tmdbs, &mut tmdbs, &title.to_string(), &year.to_string(), true).unwrap();
-        if tmdbs.len() == 0 && title_optional.len() > 0 {
-            println!("search_tmdbs...");
-            search_tmdbs(&data_tmdbs, &mut tmdbs, &title_optional.to_string(), &year.to_string(), true).unwrap();
+/// Searches the tmdbs of the imdb candidates of a node or, without candidates, of its titles.
+fn search_tmdbs_of_node(data_tmdbs: &[u8], batch: &BatchIndices, tmdbs: &mut Vec<Tmdb>, imdbs: &Vec<Imdb>, (title, title_optional, year): (&String, &String, &str), matching: &MatchingConfig) -> Result<(), Error> {
+    if imdbs.is_empty() {
+        debug!(%title, "search_tmdbs");
+        search_tmdbs(data_tmdbs, batch, tmdbs, title, year, true, matching)?;
+        if tmdbs.is_empty() && !title_optional.is_empty() {
+            debug!(title = %title_optional, "search_tmdbs");
+            search_tmdbs(data_tmdbs, batch, tmdbs, title_optional, year, true, matching)?;
         }
-    } else if imdbs.len() == 1 {
-        for imdb in imdbs {
-            println!("search_tmdbs...");
-            search_tmdbs(&data_tmdbs, &mut tmdbs, &imdb.tconst.to_string(), &year.to_string(), true).unwrap();
+        if tmdbs.is_empty() {
+            debug!(%title, "search_tmdbs (similar titles)");
+            search_tmdbs_similar(data_tmdbs, batch, tmdbs, title, year, matching)?;
+            if tmdbs.is_empty() && !title_optional.is_empty() {
+                search_tmdbs_similar(data_tmdbs, batch, tmdbs, title_optional, year, matching)?;
+            }
         }
     } else {
-        // more than one
+        for imdb in imdbs {
+            debug!(tconst = %imdb.tconst, "search_tmdbs");
+            search_tmdbs(data_tmdbs, batch, tmdbs, &imdb.tconst, year, true, matching)?;
+        }
     }
+    Ok(())
+}
+
+fn find_tmdbs_by_imdb(data_tmdbs: &[u8], batch: &BatchIndices, imdbs: &Vec<Imdb>, (title, title_optional, year): (&String, &String, &str), matching: &MatchingConfig) -> Vec<Tmdb> {
+    let _movie = info_span!("movie", %title, %year).entered();
+    let mut tmdbs = Vec::<Tmdb>::new();
+    if let Err(err) = search_tmdbs_of_node(data_tmdbs, batch, &mut tmdbs, imdbs, (title, title_optional, year), matching) {
+        warn!("{}", err);
+    }
+    tmdbs.sort_by_key(|tmdb| tmdb.year_delta.map_or(0, |delta| delta.abs()));
     tmdbs
 }
 
-pub fn get_tmdbs(data_tmdbs: &String, data_tmdbs_big: &String, imdbs: &Vec<Imdb>, (title, title_optional, year): (&String, &String, &String)) -> Vec<Tmdb> {
-    let mut tmdbs = search_tmdbs_by_imdb(data_tmdbs, &imdbs, (&title, &title_optional, &year));
+pub fn search_tmdbs_by_imdb(data_tmdbs: &[u8], imdbs: &Vec<Imdb>, (title, title_optional, year): (&String, &String, &str), matching: &MatchingConfig) -> Vec<Tmdb> {
+    find_tmdbs_by_imdb(data_tmdbs, &BatchIndices::default(), imdbs, (title, title_optional, year), matching)
+}
 
-    println!("search_tmdbs_big...");
-    let mut tmdbs_big = Vec::<Tmdb>::new();
-    if tmdbs.len() > 0 {
-        for tmdb in &tmdbs {
-            search_tmdbs_big(&data_tmdbs_big, &mut tmdbs_big, &tmdb.id.clone().to_string(), &year, false).unwrap();
+/// Searches the archive for the ids of `tmdbs` or, when `tmdbs` is empty, for the titles of the node.
+fn search_tmdbs_big_of_node(data_tmdbs_big: &[u8], batch: &BatchIndices, tmdbs: &[Tmdb], tmdbs_big: &mut Vec<Tmdb>, (title, title_optional, year): (&String, &String, &String), matching: &MatchingConfig) -> Result<(), Error> {
+    if !tmdbs.is_empty() {
+        for tmdb in tmdbs {
+            search_tmdbs_big(data_tmdbs_big, batch, tmdbs_big, &tmdb.id, year, false, matching)?;
         }
-        for tmdb_big in tmdbs_big.iter_mut() {
-            for tmdb in tmdbs.iter_mut() {
-                if tmdb.id == tmdb_big.id {
-                    tmdb.poster_path = tmdb_big.poster_path.to_string();
-                    tmdb.backdrop_path = tmdb_big.backdrop_path.to_string();
-                    tmdb_big.id = "0".to_string();
-                    break;
-                }
+    } else {
+        search_tmdbs_big(data_tmdbs_big, batch, tmdbs_big, title, year, true, matching)?;
+        if !title_optional.is_empty() {
+            search_tmdbs_big(data_tmdbs_big, batch, tmdbs_big, title_optional, year, true, matching)?;
+        }
+        if tmdbs_big.is_empty() {
+            debug!(%title, "search_tmdbs_big (similar titles)");
+            search_tmdbs_big_similar(data_tmdbs_big, batch, tmdbs_big, title, year, matching)?;
+            if !title_optional.is_empty() {
+                search_tmdbs_big_similar(data_tmdbs_big, batch, tmdbs_big, title_optional, year, matching)?;
             }
         }
-    } else {
-        search_tmdbs_big(&data_tmdbs_big, &mut tmdbs_big, &title.to_string(), &year.to_string(), true).unwrap();
-        if title_optional.len() > 0 {
-            search_tmdbs_big(&data_tmdbs_big, &mut tmdbs_big, &title_optional.to_string(), &year.to_string(), true).unwrap();
+    }
+    Ok(())
+}
+
+/// Adds the posters of the archive to `tmdbs`, or the titles of the archive when `tmdbs` is empty.
+fn add_tmdbs_big(data_tmdbs_big: &[u8], batch: &BatchIndices, tmdbs: &[Tmdb], (title, title_optional, year): (&String, &String, &String), matching: &MatchingConfig) -> Vec<Tmdb> {
+    let _movie = info_span!("movie", %title, %year).entered();
+    let mut tmdbs = tmdbs.to_vec();
+
+    debug!(tmdbs = tmdbs.len(), "search_tmdbs_big");
+    let mut tmdbs_big = Vec::<Tmdb>::new();
+    if let Err(err) = search_tmdbs_big_of_node(data_tmdbs_big, batch, &tmdbs, &mut tmdbs_big, (title, title_optional, year), matching) {
+        warn!("{}", err);
+    }
+    for tmdb_big in tmdbs_big.iter_mut() {
+        for tmdb in tmdbs.iter_mut() {
+            if tmdb.id == tmdb_big.id {
+                tmdb.poster_path = tmdb_big.poster_path.to_string();
+                tmdb.backdrop_path = tmdb_big.backdrop_path.to_string();
+                tmdb_big.id = "0".to_string();
+                break;
+            }
         }
     }
-    while tmdbs_big.len() > 0 {
-        let tmdb = tmdbs_big.pop().unwrap();
+    while let Some(tmdb) = tmdbs_big.pop() {
         if tmdb.id != "0" {
             tmdbs.push(tmdb);
         }
     }
+    tmdbs.sort_by_key(|tmdb| tmdb.year_delta.map_or(0, |delta| delta.abs()));
 
     tmdbs
 }
 
-pub fn load_tmdbs() -> Result<String, Box<dyn std::error::Error>> {
-    println!("Loading data for tmdb...");
-    let path_tmdbs = DATA_TMDBS;
-    let data_tmdbs = fs::read_to_string(path_tmdbs).expect("Something went wrong reading the file");
+pub fn get_tmdbs(data_tmdbs: &[u8], data_tmdbs_big: &[u8], imdbs: &Vec<Imdb>, (title, title_optional, year): (&String, &String, &String), matching: &MatchingConfig) -> Vec<Tmdb> {
+    let tmdbs = search_tmdbs_by_imdb(data_tmdbs, imdbs, (title, title_optional, year), matching);
+    add_tmdbs_big(data_tmdbs_big, &BatchIndices::default(), &tmdbs, (title, title_optional, year), matching)
+}
+
+fn find_tmdb_by_id(data_tmdbs: &[u8], data_tmdbs_big: &[u8], batch: &BatchIndices, batch_big: &BatchIndices, id: &str, current_year: &str) -> Result<Option<Tmdb>, Error> {
+    let id_pattern = id.to_string();
+    let tmdb = batch.search_indices(data_tmdbs, &tmdb_pattern(&id_pattern, true))?.into_iter()
+        .map(|i| get_tmdb(&get_line_by_index(data_tmdbs, &i, true)))
+        .find(|tmdb| tmdb.id == id);
+    let tmdb_big = batch_big.search_indices(data_tmdbs_big, &tmdb_pattern(&id_pattern, false))?.into_iter()
+        .map(|i| get_tmdb_big(&get_line_by_index(data_tmdbs_big, &i, true)))
+        .find(|tmdb_big| tmdb_big.id == id);
+    let delta = |release_date: &str| year_delta(release_year(release_date), current_year, u16::MAX);
+
+    Ok(match (tmdb, tmdb_big) {
+        (Some(tmdb), tmdb_big) => {
+            let year_delta = delta(&tmdb.release_date);
+            let mut tmdb = new_tmdb(tmdb, None, year_delta);
+            if let Some(tmdb_big) = tmdb_big {
+                tmdb.poster_path = tmdb_big.poster_path;
+                tmdb.backdrop_path = tmdb_big.backdrop_path;
+            }
+            Some(tmdb)
+        },
+        (None, Some(tmdb_big)) => Some(Tmdb {
+            id: tmdb_big.id,
+            imdb_id: String::new(),
+            overview: tmdb_big.overview,
+
+            poster_path: tmdb_big.poster_path,
+            backdrop_path: tmdb_big.backdrop_path,
+            similarity: None,
+            year_delta: delta(&tmdb_big.release_date),
+        }),
+        (None, None) => None,
+    })
+}
+
+/// The tmdb `id` whatever its title and release date, for an id given by the user: from the
+/// metadata with the posters of the archive, or only from the archive.
+pub fn get_tmdb_by_id(data_tmdbs: &[u8], data_tmdbs_big: &[u8], id: &str, current_year: &str) -> Result<Option<Tmdb>, Error> {
+    find_tmdb_by_id(data_tmdbs, data_tmdbs_big, &BatchIndices::default(), &BatchIndices::default(), id, current_year)
+}
+
+/// Tmdbs of nodes with ids in their names, (tmdb id, tconst, year) each: the tmdb id (see
+/// `get_tmdb_by_id`) or else the tmdbs whose `imdb_id` is the tconst, with the posters of the
+/// archive. Their titles are not searched, each dataset is searched once for all the nodes.
+pub fn get_tmdbs_by_id_batch(data_tmdbs: &[u8], data_tmdbs_big: &[u8], nodes: &[(Option<String>, Option<String>, String)]) -> Vec<Vec<Tmdb>> {
+    debug!(nodes = nodes.len(), "search_tmdbs by id batch");
+    let mut batch = BatchIndices::default();
+    if let Err(err) = batch.add_titles(data_tmdbs, nodes.iter()
+        .filter_map(|(tmdb_id, tconst, _)| if tmdb_id.is_none() { tconst.as_ref() } else { None })
+        .map(|tconst| tmdb_pattern(tconst, true))
+        .collect()) {
+        warn!("{}", err);
+    }
+    let ids: Vec<Vec<String>> = nodes.iter().map(|(tmdb_id, tconst, _)| match (tmdb_id, tconst) {
+        (Some(tmdb_id), _) => vec![tmdb_id.clone()],
+        (None, Some(tconst)) => batch.search_indices(data_tmdbs, &tmdb_pattern(tconst, true)).unwrap_or_else(|err| {
+            warn!(%tconst, "{}", err);
+            Vec::new()
+        }).into_iter()
+            .map(|i| get_tmdb(&get_line_by_index(data_tmdbs, &i, true)))
+            .filter(|tmdb| tmdb.imdb_id.eq_ignore_ascii_case(tconst))
+            .map(|tmdb| tmdb.id)
+            .collect(),
+        (None, None) => Vec::new(),
+    }).collect();
+
+    let mut batch = BatchIndices::default();
+    if let Err(err) = batch.add_titles(data_tmdbs, ids.iter().flatten().map(|id| tmdb_pattern(id, true)).collect()) {
+        warn!("{}", err);
+    }
+    let mut batch_big = BatchIndices::default();
+    if let Err(err) = batch_big.add_titles(data_tmdbs_big, ids.iter().flatten().map(|id| tmdb_pattern(id, false)).collect()) {
+        warn!("{}", err);
+    }
+    ids.iter().zip(nodes).map(|(ids, (_, _, year))| {
+        ids.iter().filter_map(|id| find_tmdb_by_id(data_tmdbs, data_tmdbs_big, &batch, &batch_big, id, year).unwrap_or_else(|err| {
+            warn!(tmdb_id = %id, "{}", err);
+            None
+        })).collect()
+    }).collect()
+}
+
+/// Titles of a node searched by `find_tmdbs_by_imdb` and `add_tmdbs_big` when nothing better is known.
+fn node_patterns((title, title_optional, _): &NodeTitle) -> Vec<String> {
+    let mut patterns = vec![tmdb_pattern(title, true)];
+    if !title_optional.is_empty() {
+        patterns.push(tmdb_pattern(title_optional, true));
+    }
+    patterns
+}
+
+/// Same as `get_tmdbs` for every node (its imdb candidates and its titles), in the order of `nodes`.
+/// Each dataset is searched in one pass for the ids and titles of all the nodes (and in another
+/// one for the release dates of the nodes whose titles are nowhere), then the nodes are checked on
+/// `config.threads` workers, matched as `config.matching` says.
+pub fn get_tmdbs_batch(data_tmdbs: &[u8], data_tmdbs_big: &[u8], nodes: &[(Vec<Imdb>, NodeTitle)], config: &Config) -> Vec<Vec<Tmdb>> {
+    let matching = &config.matching;
+    progress_stage("tmdb", nodes.len());
+    debug!(nodes = nodes.len(), "search_tmdbs batch");
+    let mut batch = BatchIndices::default();
+    if let Err(err) = batch.add_titles(data_tmdbs, nodes.iter().flat_map(|(imdbs, node)| match imdbs.len() {
+        0 => node_patterns(node),
+        _ => imdbs.iter().map(|imdb| tmdb_pattern(&imdb.tconst, true)).collect(),
+    }).collect()) {
+        warn!("{}", err);
+    }
+    let years = nodes.iter()
+        .filter(|(imdbs, node)| imdbs.is_empty() && node_patterns(node).iter().any(|title| batch.is_missing(title)))
+        .flat_map(|(_, (_, _, year))| release_date_patterns(year, matching.year_tolerance))
+        .collect();
+    if let Err(err) = batch.add_lines(data_tmdbs, years) {
+        warn!("{}", err);
+    }
+    let tmdbs = map_parallel(nodes, config.threads, |(imdbs, (title, title_optional, year))| find_tmdbs_by_imdb(data_tmdbs, &batch, imdbs, (title, title_optional, year), matching), progress_inc);
+
+    progress_stage("tmdb archive", nodes.len());
+    debug!(nodes = nodes.len(), "search_tmdbs_big batch");
+    let nodes: Vec<(Vec<Tmdb>, &NodeTitle)> = tmdbs.into_iter().zip(nodes.iter().map(|(_, node)| node)).collect();
+    let mut batch = BatchIndices::default();
+    if let Err(err) = batch.add_titles(data_tmdbs_big, nodes.iter().flat_map(|(tmdbs, node)| match tmdbs.len() {
+        0 => node_patterns(node),
+        _ => tmdbs.iter().map(|tmdb| tmdb_pattern(&tmdb.id, false)).collect(),
+    }).collect()) {
+        warn!("{}", err);
+    }
+    let years = nodes.iter()
+        .filter(|(tmdbs, node)| tmdbs.is_empty() && node_patterns(node).iter().any(|title| batch.is_missing(title)))
+        .flat_map(|(_, (_, _, year))| release_date_patterns(year, matching.year_tolerance))
+        .collect();
+    if let Err(err) = batch.add_lines(data_tmdbs_big, years) {
+        warn!("{}", err);
+    }
+    map_parallel(&nodes, config.threads, |(tmdbs, (title, title_optional, year))| add_tmdbs_big(data_tmdbs_big, &batch, tmdbs, (title, title_optional, year), matching), progress_inc)
+}
+
+pub fn load_tmdbs(path_tmdbs: &str, extract_dir: &str) -> Result<Dataset, Error> {
+    info!(path = path_tmdbs, "loading tmdb");
+    let data_tmdbs = Dataset::open_extracting_in(path_tmdbs, extract_dir)?;
 
     Ok(data_tmdbs)
 }
 
-pub fn load_tmdbs_big() -> Result<String, Box<dyn std::error::Error>> {
-    println!("Loading data for tmdb...");
-    let path_tmdbs_big = DATA_TMDBS_BIG;
-    let data_tmdbs_big = fs::read_to_string(path_tmdbs_big).expect("Something went wrong reading the file");
+pub fn load_tmdbs_big(path_tmdbs_big: &str, extract_dir: &str) -> Result<Dataset, Error> {
+    info!(path = path_tmdbs_big, "loading tmdb archive");
+    let data_tmdbs_big = Dataset::open_extracting_in(path_tmdbs_big, extract_dir)?;
 
     Ok(data_tmdbs_big)
 }
@@ -1,9 +1,18 @@
+use aho_corasick::{AhoCorasick, Anchored, Input, MatchKind};
+use aho_corasick::automaton::{Automaton, StateID};
+use aho_corasick::dfa::DFA;
+use aho_corasick::nfa::{contiguous, noncontiguous};
+use crate::error::Error;
+use std::collections::HashMap;
+use memchr::{memchr, memchr2, memchr3, memchr2_iter, memrchr, memrchr2};
+use tracing::debug;
+
 /// https://docs.rs/diacritics
 /// Used for removing diacritics from a string.
 /// # Examples
 /// ```
 /// let string = "TÅRÖÄÆØ";
-/// let new_string = diacritics::remove_diacritics(string);
+/// let new_string = notitia::utils::remove_diacritics(string);
 /// assert_eq!("TAROAAO", new_string);
 /// ```
 pub fn remove_diacritics(string: &str) -> String {
@@ -48,62 +57,504 @@ fn find_char_match(c: char) -> String {
     }
 }
 
-pub fn title_ready(current_title: &String) -> String {
-    let mut title = current_title.clone().to_ascii_lowercase();
-    title.retain(|c| !r#":.,'?;'"#.contains(c));
+/// Punctuation ignored when comparing titles: `title_ready` removes it from the titles (and so from
+/// the keys of the indexes) and `TitleSearcher` skips it in the data. `,` and `"` were added to
+/// the first ones (`:.'?;`) with the version 4 of the index, so older indexes are built again.
+pub static IGNORED_CHARS: &[u8] = b":.,'?;\"";
+
+pub fn title_ready(current_title: &str) -> String {
+    let mut title = current_title.to_ascii_lowercase();
+    title.retain(|c| !(c.is_ascii() && IGNORED_CHARS.contains(&(c as u8))));
     title
 }
 
-pub fn search_indices(data: &String, current_title: &String) -> Vec<usize> {
-    let title = title_ready(&current_title);
+/// Articles ignored at the beginning of a title ("The Matrix" ~ "Matrix", "La haine" ~ "Haine").
+static LEADING_ARTICLES: &[&str] = &["the", "a", "an", "el", "la", "los", "las", "le", "les", "l", "il", "lo", "der", "die", "das"];
+
+pub static DEFAULT_SIMILARITY_THRESHOLD: f32 = 0.85;
+
+/// Value of a roman numeral made of i, v and x (up to 39), only in its canonical form.
+fn roman_to_arabic(token: &str) -> Option<u32> {
+    if token.is_empty() || !token.chars().all(|c| c == 'i' || c == 'v' || c == 'x') {
+        return None;
+    }
+    let value_of = |c: char| match c { 'i' => 1, 'v' => 5, _ => 10 };
+    let chars: Vec<char> = token.chars().collect();
+    let mut value = 0;
+    for (i, c) in chars.iter().enumerate() {
+        match chars.get(i + 1) {
+            Some(next) if value_of(*c) < value_of(*next) => value -= value_of(*c),
+            _ => value += value_of(*c),
+        }
+    }
+    let units = ["", "i", "ii", "iii", "iv", "v", "vi", "vii", "viii", "ix"];
+    let canonical = "x".repeat((value / 10) as usize) + units[(value % 10) as usize];
+    if value > 0 && value < 40 && canonical == token { Some(value) } else { None }
+}
+
+/// Title ready for fuzzy comparisons: without diacritics, lowercase, punctuation as spaces,
+/// "&" as "and", roman numerals as arabic, without a leading article and with single spaces.
+pub fn normalize_title(title: &str) -> String {
+    let title = remove_diacritics(title).to_lowercase().replace('&', " and ");
+    let title: String = title.chars().map(|c| if c.is_alphanumeric() { c } else { ' ' }).collect();
+    let mut tokens: Vec<String> = title.split_whitespace()
+        .map(|token| match roman_to_arabic(token) {
+            Some(value) => value.to_string(),
+            None => token.to_string(),
+        })
+        .collect();
+    if tokens.len() > 1 && LEADING_ARTICLES.contains(&tokens[0].as_str()) {
+        tokens.remove(0);
+    }
+    tokens.join(" ")
+}
+
+/// Damerau-Levenshtein distance (optimal string alignment) between two strings, in chars.
+pub fn damerau_levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// Similarity between two titles after `normalize_title`, from 0 (different) to 1 (same title).
+pub fn title_similarity(a: &str, b: &str) -> f32 {
+    SimilarTitle::new(a).similarity(b, 0.0)
+}
+
+fn trigrams(chars: &[char]) -> Vec<[char; 3]> {
+    let mut trigrams: Vec<[char; 3]> = chars.windows(3).map(|window| [window[0], window[1], window[2]]).collect();
+    trigrams.sort_unstable();
+    trigrams
+}
+
+/// Trigrams in both sorted lists, counting the repeated ones.
+fn common_trigrams(a: &[[char; 3]], b: &[[char; 3]]) -> usize {
+    let (mut i, mut j, mut common) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                common += 1;
+                i += 1;
+                j += 1;
+            },
+        }
+    }
+    common
+}
+
+/// A title normalized once (see `normalize_title`) to be compared with many others. Only the
+/// titles that can reach the threshold by their length and the trigrams they share get their
+/// Damerau-Levenshtein distance computed, the rest are 0.
+pub struct SimilarTitle {
+    title: String,
+    normalized: String,
+    chars: Vec<char>,
+    trigrams: Vec<[char; 3]>,
+}
+impl SimilarTitle {
+    pub fn new(title: &str) -> SimilarTitle {
+        let normalized = normalize_title(title);
+        let chars: Vec<char> = normalized.chars().collect();
+        let trigrams = trigrams(&chars);
+        SimilarTitle { title: title.to_string(), normalized, chars, trigrams }
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn normalized(&self) -> &str {
+        &self.normalized
+    }
+
+    /// `title_similarity` with `title`, 0 if it is below `threshold`.
+    pub fn similarity(&self, title: &str, threshold: f32) -> f32 {
+        self.similarity_normalized(&normalize_title(title), threshold)
+    }
+
+    /// Same as `similarity` for a title already normalized.
+    pub fn similarity_normalized(&self, normalized: &str, threshold: f32) -> f32 {
+        let chars: Vec<char> = normalized.chars().collect();
+        let len = self.chars.len().max(chars.len());
+        if len == 0 {
+            return 1.0;
+        }
+        let similarity = |distance: usize| 1.0 - distance as f32 / len as f32;
+        // the distance is at least the difference of lengths
+        if similarity(self.chars.len().abs_diff(chars.len())) < threshold {
+            return 0.0;
+        }
+        // every edit changes at most 4 trigrams (a transposition), so with at most `edits` edits
+        // the titles share at least that many less trigrams than the longest one has
+        let mut edits = len;
+        while edits > 0 && similarity(edits) < threshold {
+            edits -= 1;
+        }
+        let trigrams_needed = self.chars.len().max(chars.len()).saturating_sub(2).saturating_sub(4 * edits);
+        if trigrams_needed > 0 && common_trigrams(&self.trigrams, &trigrams(&chars)) < trigrams_needed {
+            return 0.0;
+        }
+        if self.normalized == normalized {
+            return 1.0;
+        }
+        match similarity(damerau_levenshtein(&self.normalized, normalized)) {
+            similarity if similarity < threshold => 0.0,
+            similarity => similarity,
+        }
+    }
+}
 
-    let special_chrs:  &[_] = &[b':', b'.', b'\'', b'?', b';']; // b',', 
+/// Difference between `year` and `current_year` if it is within `year_tolerance` years.
+pub fn year_delta(year: Option<u16>, current_year: &str, year_tolerance: u16) -> Option<i16> {
+    let year = year? as i32;
+    let current_year: i32 = current_year.trim().parse().ok()?;
+    let delta = year - current_year;
+    if delta.abs() <= year_tolerance as i32 { Some(delta as i16) } else { None }
+}
 
-    let title_a: &[u8] = title.as_bytes();
-    let data_a: &[u8] = data.as_bytes();
+/// `current_year` and the years around it within `year_tolerance`, `current_year` first.
+pub fn years_around(current_year: &str, year_tolerance: u16) -> Vec<String> {
+    let current_year: i32 = match current_year.trim().parse() {
+        Ok(year) => year,
+        Err(_) => return vec![current_year.to_string()],
+    };
+    let mut years = vec![current_year.to_string()];
+    for delta in 1..=year_tolerance as i32 {
+        years.push((current_year - delta).to_string());
+        years.push((current_year + delta).to_string());
+    }
+    years
+}
 
+/// Beginning of every line (the header excluded) containing any of `patterns`,
+/// used to get the candidates for fuzzy matching when the exact search finds nothing.
+pub fn line_indices_containing(data: &[u8], patterns: &[String]) -> Result<Vec<usize>, Error> {
     let mut indices: Vec<usize> = vec!();
+    let mut line_begin = memchr(b'\n', data).map_or(data.len(), |header_end| header_end + 1);
+    if patterns.iter().any(|pattern| pattern.is_empty()) {
+        while line_begin < data.len() {
+            indices.push(line_begin);
+            line_begin = memchr(b'\n', &data[line_begin..]).map_or(data.len(), |end| line_begin + end + 1);
+        }
+        return Ok(indices);
+    }
+    let searcher = AhoCorasick::new(patterns).map_err(|err| Error::Search(err.to_string()))?;
+    while line_begin < data.len() {
+        let found = match searcher.find(Input::new(data).span(line_begin..data.len())) {
+            Some(found) => found.start(),
+            None => break,
+        };
+        // the rest of the line is not searched
+        indices.push(memrchr(b'\n', &data[..found]).map_or(0, |eol| eol + 1));
+        line_begin = memchr(b'\n', &data[found..]).map_or(data.len(), |end| found + end + 1);
+    }
+    Ok(indices)
+}
+
+/// Decodes the utf-8 char beginning at `index`, returns the char and its length in bytes.
+fn char_at(data: &[u8], index: usize) -> Option<(char, usize)> {
+    let len = match data[index] {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => return None,
+    };
+    let bytes = data.get(index..index + len)?;
+    std::str::from_utf8(bytes).ok()?.chars().next().map(|c| (c, len))
+}
 
-    let begin_data = 0;
-    let end_data = data_a.len();
-    let begin = 0;
-    let end = title_a.len();
-    let mut j = begin_data;
-    let mut last_eol: usize = 0;
-    while j < end_data {
-        let mut current_index: bool = true;
-        if data_a[j] == b'\n' || data_a[j] == b'\r' {
-            last_eol = j + 1;
-        }
-        let mut jj = j;
-        let mut i = begin;
-        while i < end {
-            if special_chrs.contains(&data_a[jj + i]) { // ignore certain chrs
-                jj = jj + 1;
-            } else if data_a[jj + i] != title_a[i] // compare chrs
-                && data_a[jj + i] < (255 - (b'a' - b'A')) && data_a[jj + i] + (b'a' - b'A') != title_a[i] { // lower case
-                current_index = false;
-                break;
-            } else {
-                i = i + 1;
+/// Beginning of the line of the byte at `index` (lines end with `\n` or `\r`).
+fn line_begin(data: &[u8], index: usize) -> usize {
+    memrchr2(b'\n', b'\r', &data[..index]).map_or(0, |eol| eol + 1)
+}
+
+/// Automaton of a `TitleSearcher`: a DFA, the fastest, unless the titles are too many for it.
+/// The contiguous NFA is then used and, for even more titles, the noncontiguous one.
+enum TitleAutomaton {
+    Dfa(DFA),
+    Contiguous(contiguous::NFA),
+    Noncontiguous(noncontiguous::NFA),
+}
+impl TitleAutomaton {
+    fn new(patterns: &[&str]) -> Result<TitleAutomaton, Error> {
+        let err = match DFA::builder().match_kind(MatchKind::Standard).build(patterns) {
+            Ok(dfa) => return Ok(TitleAutomaton::Dfa(dfa)),
+            Err(err) => err,
+        };
+        debug!(titles = patterns.len(), "too many titles for a DFA ({}), searching with a contiguous NFA", err);
+        let err = match contiguous::NFA::builder().match_kind(MatchKind::Standard).build(patterns) {
+            Ok(nfa) => return Ok(TitleAutomaton::Contiguous(nfa)),
+            Err(err) => err,
+        };
+        debug!(titles = patterns.len(), "too many titles for a contiguous NFA ({}), searching with a noncontiguous NFA", err);
+        noncontiguous::NFA::builder().match_kind(MatchKind::Standard).build(patterns)
+            .map(TitleAutomaton::Noncontiguous)
+            .map_err(|err| Error::Search(err.to_string()))
+    }
+}
+
+/// Searches a batch of titles in one pass over the data, comparing them as `search_indices` does:
+/// the titles are prepared with `title_ready`, upper case letters of the data match lower case
+/// letters, `IGNORED_CHARS` of the data are skipped (unless a pattern goes on or begins with them,
+/// like the `,` around the titles of a csv file) and, with `fold_diacritics`, letters with
+/// diacritics match the letters without them.
+pub struct TitleSearcher {
+    automaton: TitleAutomaton,
+    start: StateID,
+    titles: usize,
+    /// Title of each pattern of the automaton, the titles that are not empty.
+    pattern_titles: Vec<usize>,
+    /// Titles that are empty, they are found in every line.
+    empty: Vec<usize>,
+    /// The only bytes of the data that leave the start state, when they are up to 3.
+    start_bytes: Vec<u8>,
+    fold_diacritics: bool,
+}
+impl TitleSearcher {
+    /// Fails only when the titles are too many even for the slowest automaton.
+    pub fn new(titles: &[String], fold_diacritics: bool) -> Result<TitleSearcher, Error> {
+        let titles_ready: Vec<String> = titles.iter().map(String::as_str).map(title_ready).collect();
+        let (empty, pattern_titles): (Vec<usize>, Vec<usize>) = (0..titles.len()).partition(|&i| titles_ready[i].is_empty());
+        let patterns: Vec<&str> = pattern_titles.iter().map(|&i| titles_ready[i].as_str()).collect();
+        let automaton = TitleAutomaton::new(&patterns)?;
+        let (start, start_bytes) = match &automaton {
+            TitleAutomaton::Dfa(dfa) => TitleSearcher::start(dfa, fold_diacritics)?,
+            TitleAutomaton::Contiguous(nfa) => TitleSearcher::start(nfa, fold_diacritics)?,
+            TitleAutomaton::Noncontiguous(nfa) => TitleSearcher::start(nfa, fold_diacritics)?,
+        };
+
+        Ok(TitleSearcher { automaton, start, titles: titles.len(), pattern_titles, empty, start_bytes, fold_diacritics })
+    }
+
+    /// Start state of `automaton` and the only bytes of the data that leave it (see `start_bytes`).
+    fn start<A: Automaton>(automaton: &A, fold_diacritics: bool) -> Result<(StateID, Vec<u8>), Error> {
+        let start = automaton.start_state(Anchored::No).map_err(|err| Error::Search(err.to_string()))?;
+        let start_bytes: Vec<u8> = if fold_diacritics {
+            Vec::new() // any letter with diacritics can leave the start state
+        } else {
+            (0..=255u8).filter(|&byte| automaton.next_state(Anchored::No, start, byte.to_ascii_lowercase()) != start).collect()
+        };
+        let start_bytes = if start_bytes.len() <= 3 { start_bytes } else { Vec::new() };
+        Ok((start, start_bytes))
+    }
+
+    /// Next byte of the data that can leave the start state, from `index`.
+    fn skip_to_candidate(&self, data: &[u8], index: usize) -> usize {
+        let found = match self.start_bytes[..] {
+            [a] => memchr(a, &data[index..]),
+            [a, b] => memchr2(a, b, &data[index..]),
+            [a, b, c] => memchr3(a, b, c, &data[index..]),
+            _ => Some(0),
+        };
+        found.map_or(data.len(), |found| index + found)
+    }
+
+    fn report<A: Automaton>(&self, automaton: &A, data: &[u8], index: usize, state: StateID, indices: &mut [Vec<usize>]) {
+        let begin = line_begin(data, index);
+        for i in 0..automaton.match_len(state) {
+            let found = &mut indices[self.pattern_titles[automaton.match_pattern(state, i).as_usize()]];
+            if found.last() != Some(&begin) {
+                found.push(begin);
+            }
+        }
+    }
+
+    /// Beginning of every line where each title is found, in the order of the titles.
+    pub fn search(&self, data: &[u8]) -> Vec<Vec<usize>> {
+        match &self.automaton {
+            TitleAutomaton::Dfa(dfa) => self.search_with(dfa, data),
+            TitleAutomaton::Contiguous(nfa) => self.search_with(nfa, data),
+            TitleAutomaton::Noncontiguous(nfa) => self.search_with(nfa, data),
+        }
+    }
+
+    fn search_with<A: Automaton>(&self, automaton: &A, data: &[u8]) -> Vec<Vec<usize>> {
+        let mut indices: Vec<Vec<usize>> = vec![Vec::new(); self.titles];
+        let mut state = self.start;
+        let mut j = 0;
+        while j < data.len() {
+            if state == self.start && !self.start_bytes.is_empty() {
+                j = self.skip_to_candidate(data, j);
+                if j >= data.len() {
+                    break;
+                }
+            }
+            let chr = data[j];
+            if IGNORED_CHARS.contains(&chr) && automaton.next_state(Anchored::No, state, chr) == self.start {
+                j += 1;
+                continue;
+            }
+            if self.fold_diacritics && chr >= 0x80 {
+                if let Some((c, len)) = char_at(data, j) {
+                    for byte in find_char_match(c).to_ascii_lowercase().bytes() {
+                        state = automaton.next_state(Anchored::No, state, byte);
+                        if automaton.is_match(state) {
+                            self.report(automaton, data, j, state, &mut indices);
+                        }
+                    }
+                    j += len;
+                    continue;
+                }
+            }
+            state = automaton.next_state(Anchored::No, state, chr.to_ascii_lowercase());
+            if automaton.is_match(state) {
+                self.report(automaton, data, j, state, &mut indices);
             }
+            j += 1;
         }
-        if current_index {
-            indices.push(last_eol);
+
+        if !self.empty.is_empty() {
+            let mut lines: Vec<usize> = vec![0];
+            lines.extend(memchr2_iter(b'\n', b'\r', data).map(|eol| eol + 1).filter(|&begin| begin < data.len()));
+            for &i in self.empty.iter() {
+                indices[i] = lines.clone();
+            }
         }
-        j = j + 1;
+        indices
     }
+}
+
+/// Beginning of every line where each of `titles` is found, in one pass over `data` (see `TitleSearcher`).
+pub fn search_indices_batch(data: &[u8], titles: &[String]) -> Result<Vec<Vec<usize>>, Error> {
+    Ok(TitleSearcher::new(titles, false)?.search(data))
+}
+
+/// Beginning of every line where `current_title` is found, see `TitleSearcher`.
+pub fn search_indices(data: &[u8], current_title: &String) -> Result<Vec<usize>, Error> {
+    Ok(TitleSearcher::new(std::slice::from_ref(current_title), false)?.search(data).remove(0))
+}
 
-    indices
+/// Same as `search_indices` but diacritics in data are ignored,
+/// `current_title` must not have diacritics (see `remove_diacritics`).
+pub fn search_indices_without_diacritics(data: &[u8], current_title: &String) -> Result<Vec<usize>, Error> {
+    Ok(TitleSearcher::new(std::slice::from_ref(current_title), true)?.search(data).remove(0))
 }
 
-pub fn get_line_by_index(data: &String, index: &usize, calculate_line_begins: bool) -> String {
-    let mut index_l: usize = *index;
-    if calculate_line_begins {
-        index_l = data[..*index].rfind("\n").unwrap();
+/// Lines of a dataset found in one pass for the patterns of a batch of nodes, so every node does
+/// not search the whole dataset again. Patterns that are not in the batch are searched on their own.
+#[derive(Debug, Default)]
+pub struct BatchIndices {
+    titles: HashMap<String, Vec<usize>>,
+    titles_without_diacritics: HashMap<String, Vec<usize>>,
+    lines: HashMap<String, Vec<usize>>,
+}
+impl BatchIndices {
+    fn new_patterns(found: &HashMap<String, Vec<usize>>, patterns: Vec<String>) -> Vec<String> {
+        let mut patterns: Vec<String> = patterns.into_iter().filter(|pattern| !found.contains_key(pattern)).collect();
+        patterns.sort();
+        patterns.dedup();
+        patterns
+    }
+
+    /// Searches `titles` as `search_indices` does, in one pass. If it fails, the titles are left
+    /// out of the batch and searched on their own.
+    pub fn add_titles(&mut self, data: &[u8], titles: Vec<String>) -> Result<(), Error> {
+        let titles = BatchIndices::new_patterns(&self.titles, titles);
+        if !titles.is_empty() {
+            self.titles.extend(titles.iter().cloned().zip(search_indices_batch(data, &titles)?));
+        }
+        Ok(())
+    }
+
+    /// Searches `titles` as `search_indices_without_diacritics` does, in one pass.
+    pub fn add_titles_without_diacritics(&mut self, data: &[u8], titles: Vec<String>) -> Result<(), Error> {
+        let titles = BatchIndices::new_patterns(&self.titles_without_diacritics, titles);
+        if !titles.is_empty() {
+            self.titles_without_diacritics.extend(titles.iter().cloned().zip(TitleSearcher::new(&titles, true)?.search(data)));
+        }
+        Ok(())
+    }
+
+    /// Searches the lines containing each of `patterns` as `line_indices_containing` does, in one pass.
+    pub fn add_lines(&mut self, data: &[u8], patterns: Vec<String>) -> Result<(), Error> {
+        let mut patterns = BatchIndices::new_patterns(&self.lines, patterns);
+        // an empty pattern is in every line, it is better searched on its own
+        patterns.retain(|pattern| !pattern.is_empty());
+        if patterns.is_empty() {
+            return Ok(());
+        }
+        let mut indices: Vec<Vec<usize>> = vec![Vec::new(); patterns.len()];
+        let header_end = memchr(b'\n', data).map_or(data.len(), |header_end| header_end + 1);
+        let searcher = AhoCorasick::new(&patterns).map_err(|err| Error::Search(err.to_string()))?;
+        for found in searcher.find_overlapping_iter(Input::new(data).span(header_end..data.len())) {
+            let begin = memrchr(b'\n', &data[..found.start()]).map_or(0, |eol| eol + 1);
+            let lines = &mut indices[found.pattern().as_usize()];
+            if lines.last() != Some(&begin) {
+                lines.push(begin);
+            }
+        }
+        self.lines.extend(patterns.into_iter().zip(indices));
+        Ok(())
+    }
+
+    /// Whether `title` was searched in the batch and found nowhere.
+    pub fn is_missing(&self, title: &String) -> bool {
+        self.titles.get(title).or(self.titles_without_diacritics.get(title)).is_some_and(|indices| indices.is_empty())
     }
-    let index_r: Option<usize> = data[*index..].find("\n");
-    let begin = index_l + 1;
-    let end = if index_r != None { index_r.unwrap() + *index } else { data.len() };
-    (&data[begin..end]).to_string()
+
+    /// Same as `search_indices`, from the batch when `current_title` is in it.
+    pub fn search_indices(&self, data: &[u8], current_title: &String) -> Result<Vec<usize>, Error> {
+        match self.titles.get(current_title) {
+            Some(indices) => Ok(indices.clone()),
+            None => search_indices(data, current_title),
+        }
+    }
+
+    /// Same as `search_indices_without_diacritics`, from the batch when `current_title` is in it.
+    pub fn search_indices_without_diacritics(&self, data: &[u8], current_title: &String) -> Result<Vec<usize>, Error> {
+        match self.titles_without_diacritics.get(current_title) {
+            Some(indices) => Ok(indices.clone()),
+            None => search_indices_without_diacritics(data, current_title),
+        }
+    }
+
+    /// Same as `line_indices_containing`, from the batch when all the `patterns` are in it.
+    pub fn line_indices_containing(&self, data: &[u8], patterns: &[String]) -> Result<Vec<usize>, Error> {
+        if !patterns.iter().all(|pattern| self.lines.contains_key(pattern)) {
+            return line_indices_containing(data, patterns);
+        }
+        let mut indices: Vec<usize> = patterns.iter().flat_map(|pattern| self.lines[pattern].iter().copied()).collect();
+        indices.sort_unstable();
+        indices.dedup();
+        Ok(indices)
+    }
+}
+
+pub fn get_line_by_index(data: &[u8], index: &usize, calculate_line_begins: bool) -> String {
+    let begin = if calculate_line_begins {
+        memrchr(b'\n', &data[..*index]).map_or(0, |eol| eol + 1)
+    } else {
+        *index + 1
+    };
+    let end = memchr(b'\n', &data[*index..]).map_or(data.len(), |eol| eol + *index);
+    String::from_utf8_lossy(&data[begin..end]).to_string()
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it to `path`,
+/// so readers never see a partially written file.
+pub fn write_atomically(path: &str, contents: &[u8]) -> std::io::Result<()> {
+    let temporary = format!("{}.tmp", path);
+    std::fs::write(&temporary, contents)?;
+    std::fs::rename(&temporary, path)
 }
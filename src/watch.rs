@@ -0,0 +1,123 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use serde::{Deserialize, Serialize};
+use crate::scan::ScannedNode;
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct WatchConfig {
+    /// Milliseconds without changes before the nodes are checked again.
+    pub debounce_ms: u64,
+}
+impl Default for WatchConfig {
+    fn default() -> WatchConfig {
+        WatchConfig {
+            debounce_ms: 2000,
+        }
+    }
+}
+
+/// Nodes that differ between two scans, by full node name.
+/// A renamed node is removed with its old name and added with the new one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeChanges {
+    pub added: Vec<ScannedNode>,
+    pub changed: Vec<ScannedNode>,
+    pub removed: Vec<String>,
+}
+impl NodeChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Compares the nodes already known with the current ones. A node has changed when its size or
+/// modification differs, nodes without modification (from a node list) never change.
+pub fn diff_nodes(previous: &[ScannedNode], current: &[ScannedNode]) -> NodeChanges {
+    let mut changes = NodeChanges { added: Vec::new(), changed: Vec::new(), removed: Vec::new() };
+    let previous_nodes: HashMap<&str, &ScannedNode> = previous.iter().map(|node| (node.full_node_name.as_str(), node)).collect();
+    let current_names: HashSet<&str> = current.iter().map(|node| node.full_node_name.as_str()).collect();
+    for node in current {
+        match previous_nodes.get(node.full_node_name.as_str()) {
+            None => changes.added.push(node.clone()),
+            Some(item) if node.modified.is_some() && (item.size != node.size || item.modified != node.modified) => changes.changed.push(node.clone()),
+            Some(_) => {},
+        }
+    }
+    for node in previous {
+        if !current_names.contains(node.full_node_name.as_str()) {
+            changes.removed.push(node.full_node_name.clone());
+        }
+    }
+    changes
+}
+
+/// Filesystem notifications for a library root (recursive) or a node list file.
+pub struct Watcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    /// Only the events of this file count, when watching a node list.
+    file: Option<PathBuf>,
+}
+impl Watcher {
+    pub fn new(path: &Path) -> notify::Result<Watcher> {
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        // a node list is watched through its folder, editors replace the file when saving
+        let file = if path.is_dir() {
+            watcher.watch(path, RecursiveMode::Recursive)?;
+            None
+        } else {
+            let folder = match path.parent() {
+                Some(folder) if !folder.as_os_str().is_empty() => folder,
+                _ => Path::new("."),
+            };
+            watcher.watch(folder, RecursiveMode::NonRecursive)?;
+            path.file_name().map(PathBuf::from)
+        };
+
+        Ok(Watcher { _watcher: watcher, events, file })
+    }
+
+    fn is_change(&self, event: &notify::Result<Event>) -> bool {
+        let event = match event {
+            Ok(event) => event,
+            // lost events, check everything again
+            Err(_) => return true,
+        };
+        if matches!(event.kind, EventKind::Access(_)) {
+            return false;
+        }
+        match &self.file {
+            Some(file) => event.paths.iter().any(|path| path.file_name() == Some(file.as_os_str())),
+            None => true,
+        }
+    }
+
+    /// Blocks until something changes and then nothing else changes for `quiet`.
+    /// Returns `false` if nothing changed before `timeout` (forever if `None`).
+    pub fn wait(&self, quiet: Duration, timeout: Option<Duration>) -> bool {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let event = match deadline {
+                Some(deadline) => match self.events.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(event) => event,
+                    Err(_) => return false,
+                },
+                None => match self.events.recv() {
+                    Ok(event) => event,
+                    Err(_) => return false,
+                },
+            };
+            if self.is_change(&event) {
+                break;
+            }
+        }
+        // debounce, a copy or an extraction is a burst of events
+        while self.events.recv_timeout(quiet).is_ok() {}
+        true
+    }
+}
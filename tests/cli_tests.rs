@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod cli_tests {
+    use std::fs;
+    use std::process::Command;
+
+    #[test]
+    fn corrupt_working_file_aborts_without_replacing_it() {
+        let folder = tempfile::tempdir().unwrap();
+        let working_file = folder.path().join("movies.json");
+        fs::write(&working_file, "[{\"title\": \"Blade Runner\",").unwrap();
+        fs::write(folder.path().join("movies.txt"), "Blade Runner (1982).movie\n").unwrap();
+
+        let output = Command::new(env!("CARGO_BIN_EXE_NotitiaRust"))
+            .current_dir(folder.path())
+            .args(["--working-file", "movies.json", "--output-file", "movies.json", "--raw-list-movie-file", "movies.txt"])
+            .output()
+            .unwrap();
+
+        assert_eq!(Some(1), output.status.code());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("invalid JSON in 'movies.json'"));
+        assert_eq!("[{\"title\": \"Blade Runner\",", fs::read_to_string(&working_file).unwrap());
+        // no backup either, only the working file and the node list
+        assert_eq!(2, fs::read_dir(folder.path()).unwrap().count());
+    }
+
+    #[test]
+    fn empty_working_file_is_no_movies() {
+        let folder = tempfile::tempdir().unwrap();
+        fs::write(folder.path().join("movies.json"), " \n").unwrap();
+        fs::write(folder.path().join("movies.txt"), "Blade Runner (1982).movie\n").unwrap();
+        let files = |path: &str| format!("{}/tests/files/{}", env!("CARGO_MANIFEST_DIR"), path);
+
+        let output = Command::new(env!("CARGO_BIN_EXE_NotitiaRust"))
+            .current_dir(folder.path())
+            .args(["--working-file", "movies.json", "--output-file", "result.json", "--raw-list-movie-file", "movies.txt", "--quiet"])
+            .args(["--imdb-basics", &files("imdb/title.basics.reduced.es_test.tsv"), "--imdb-akas", &files("imdb/title.akas.reduced.es_test.tsv"), "--imdb-ratings", &files("imdb/title.ratings_test.tsv")])
+            .args(["--tmdb-movies-metadata", &files("tmdb/movies_metadata_test.csv"), "--tmdb-archive", &files("tmdb/archive_test.csv"), "--justwatch-files", &files("justwatch/test.json")])
+            .output()
+            .unwrap();
+
+        assert_eq!(Some(0), output.status.code(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert!(fs::read_to_string(folder.path().join("result.json")).unwrap().contains("\"title\":\"Blade Runner\""));
+    }
+
+    #[test]
+    fn unknown_command_exits_with_error() {
+        let output = Command::new(env!("CARGO_BIN_EXE_NotitiaRust")).arg("unknown").output().unwrap();
+
+        assert_eq!(Some(1), output.status.code());
+        assert_eq!("error: unknown command 'unknown'\n", String::from_utf8_lossy(&output.stderr));
+    }
+}
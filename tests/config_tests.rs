@@ -0,0 +1,112 @@
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+#[cfg(test)]
+mod config_tests {
+    use notitia::config::{Config, ConfigError, apply_option, load_config, load_config_file};
+    use notitia::Error;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn load_config_without_args_uses_defaults() {
+        let (config, positional) = load_config(&args(&[])).unwrap();
+
+        assert_eq!(Config { ..Default::default() }, config);
+        assert_eq!(0, positional.len());
+    }
+
+    #[test]
+    fn load_config_file_partial_keeps_defaults() {
+        let config = load_config_file("tests/files/config/test.json").unwrap();
+
+        assert_eq!("library/movies.json", config.working_file);
+        assert!(!config.updating);
+        assert_eq!(vec!["movie".to_string()], config.filter_node_format);
+        assert_eq!("FR", config.imdb.language);
+        assert_eq!("files/imdb/title.basics.reduced.fr.tsv", config.imdb.basics);
+        assert_eq!("files/imdb/title.akas.reduced.es.tsv", config.imdb.akas);
+        assert_eq!("files/tmdb/archive.csv", config.tmdb.archive);
+    }
+
+    #[test]
+    fn load_config_args_override_file() {
+        let (config, positional) = load_config(&args(&[
+            "--config", "tests/files/config/test.json",
+            "--updating",
+            "--imdb-language=EN",
+            "--filter-node-format", "movie,short",
+            "--justwatch-files", "a.json,b.json",
+            "--threads", "4",
+        ])).unwrap();
+
+        assert!(config.updating);
+        assert_eq!("EN", config.imdb.language);
+        assert_eq!(vec!["movie".to_string(), "short".to_string()], config.filter_node_format);
+        assert_eq!(vec!["a.json".to_string(), "b.json".to_string()], config.justwatch.files);
+        assert_eq!(4, config.threads);
+        assert_eq!("library/movies.txt", config.raw_list_movie_file);
+        assert_eq!(0, positional.len());
+    }
+
+    #[test]
+    fn load_config_positional_args() {
+        let (config, positional) = load_config(&args(&["index", "--no-updating", "--prune-missing"])).unwrap();
+
+        assert!(!config.updating);
+        assert!(config.prune_missing);
+        assert_eq!(vec!["index".to_string()], positional);
+    }
+
+    #[test]
+    fn load_config_log_options() {
+        let (config, _) = load_config(&args(&["--log-level", "debug", "--log-format=json", "--no-progress"])).unwrap();
+
+        assert_eq!("debug", config.log.level);
+        assert_eq!("json", config.log.format);
+        assert!(!config.log.progress);
+    }
+
+    #[test]
+    fn load_config_quiet() {
+        let (config, _) = load_config(&args(&["--quiet"])).unwrap();
+
+        assert_eq!("warn", config.log.level);
+        assert!(!config.log.progress);
+    }
+
+    #[test]
+    fn load_config_unknown_option() {
+        assert!(load_config(&args(&["--not-an-option", "value"])).is_err());
+    }
+
+    #[test]
+    fn apply_option_unknown_option() {
+        let mut config = Config { ..Default::default() };
+
+        assert_eq!(Err(ConfigError::UnknownOption { option: "--not-an-option".to_string() }), apply_option(&mut config, "--not-an-option", "value"));
+    }
+
+    #[test]
+    fn apply_option_invalid_value() {
+        let mut config = Config { ..Default::default() };
+
+        assert_eq!(Err(ConfigError::InvalidValue { option: "--threads".to_string(), value: "abc".to_string() }), apply_option(&mut config, "--threads", "abc"));
+        assert_eq!(0, config.threads);
+    }
+
+    #[test]
+    fn load_config_invalid_value() {
+        let result = load_config(&args(&["--year-tolerance", "x"]));
+
+        assert!(matches!(result, Err(Error::Usage(message)) if message == "invalid value 'x' for '--year-tolerance'"));
+    }
+
+    #[test]
+    fn load_config_missing_value() {
+        assert!(load_config(&args(&["--output-file"])).is_err());
+    }
+}
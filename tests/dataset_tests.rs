@@ -0,0 +1,92 @@
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+#[cfg(test)]
+mod dataset_tests {
+    use std::fs;
+    use std::io::Write;
+    use flate2::{Compression, write::GzEncoder};
+    use notitia::dataset::{Dataset, extracted_path};
+    use notitia::error::Error;
+
+    #[test]
+    fn open_dataset() {
+        let data = Dataset::open("tests/files/imdb/title.ratings_test.tsv").unwrap();
+        let expected = fs::read("tests/files/imdb/title.ratings_test.tsv").expect("Something went wrong reading the file");
+
+        assert_eq!("tests/files/imdb/title.ratings_test.tsv", data.path);
+        assert_eq!(&expected[..], &data[..]);
+    }
+
+    #[test]
+    fn open_dataset_gzip_extracted_once() {
+        let expected = fs::read("tests/files/imdb/title.ratings_test.tsv").unwrap();
+        let folder = tempfile::tempdir().unwrap();
+        let path_gz = folder.path().join("title.ratings.tsv.gz").to_string_lossy().to_string();
+        let mut encoder = GzEncoder::new(fs::File::create(&path_gz).unwrap(), Compression::default());
+        encoder.write_all(&expected).unwrap();
+        encoder.finish().unwrap();
+
+        let data = Dataset::open(&path_gz).unwrap();
+        assert_eq!(path_gz, data.path);
+        assert_eq!(&expected[..], &data[..]);
+        assert_eq!(expected, fs::read(extracted_path(&path_gz, "")).unwrap());
+
+        // the extracted file is used while the gzip file doesn't change
+        let modified = fs::metadata(extracted_path(&path_gz, "")).unwrap().modified().unwrap();
+        let data = Dataset::open(&path_gz).unwrap();
+        assert_eq!(&expected[..], &data[..]);
+        assert_eq!(modified, fs::metadata(extracted_path(&path_gz, "")).unwrap().modified().unwrap());
+    }
+
+    #[test]
+    fn open_dataset_gzip_extracted_in_extract_dir() {
+        let expected = fs::read("tests/files/imdb/title.ratings_test.tsv").unwrap();
+        let folder = tempfile::tempdir().unwrap();
+        let path_gz = folder.path().join("title.ratings.tsv.gz").to_string_lossy().to_string();
+        let extract_dir = folder.path().join("extracted").to_string_lossy().to_string();
+        let mut encoder = GzEncoder::new(fs::File::create(&path_gz).unwrap(), Compression::default());
+        encoder.write_all(&expected).unwrap();
+        encoder.finish().unwrap();
+
+        let data = Dataset::open_extracting_in(&path_gz, &extract_dir).unwrap();
+
+        assert_eq!(&expected[..], &data[..]);
+        assert_eq!(folder.path().join("extracted").join("title.ratings.tsv.gz.extracted").to_string_lossy(), extracted_path(&path_gz, &extract_dir));
+        assert_eq!(expected, fs::read(extracted_path(&path_gz, &extract_dir)).unwrap());
+        assert!(!std::path::Path::new(&extracted_path(&path_gz, "")).exists());
+    }
+
+    #[test]
+    fn open_dataset_gzip_corrupt_removes_temporary_file() {
+        let folder = tempfile::tempdir().unwrap();
+        let path_gz = folder.path().join("title.ratings.tsv.gz").to_string_lossy().to_string();
+        fs::write(&path_gz, "not a gzip file").unwrap();
+
+        assert!(Dataset::open(&path_gz).is_err());
+        assert!(!std::path::Path::new(&extracted_path(&path_gz, "")).exists());
+        assert!(!std::path::Path::new(&format!("{}.tmp", extracted_path(&path_gz, ""))).exists());
+    }
+
+    #[test]
+    fn open_dataset_not_found() {
+        assert!(Dataset::open("tests/files/imdb/not_found.tsv").is_err());
+    }
+
+    #[test]
+    fn dataset_from_bytes() {
+        let data = Dataset::from_bytes("memory", "tconst\tnumVotes\n".as_bytes().to_vec());
+
+        assert_eq!(16, data.len());
+        assert_eq!(b"tconst", &data[..6]);
+    }
+
+    #[test]
+    fn open_dataset_not_found_is_missing_dataset() {
+        let error = Dataset::open("tests/files/imdb/not_found.tsv").err().unwrap();
+
+        assert!(matches!(&error, Error::MissingDataset { path, .. } if path == "tests/files/imdb/not_found.tsv"));
+        assert!(error.to_string().starts_with("can't open dataset 'tests/files/imdb/not_found.tsv': "));
+    }
+}
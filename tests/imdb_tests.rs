@@ -2,22 +2,38 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
-#[path = "../src/utils.rs"] mod utils;
-#[path = "../src/imdb.rs"] mod imdb;
-
 #[cfg(test)]
 mod imdb_tests {
     use std::fs;
-    use crate::imdb::{Imdb, get_basics, get_akas, get_ratings, update_imdbs_with_akas};
+    use std::io::prelude::*;
+    use flate2::{Compression, write::GzEncoder};
+    use notitia::dataset::{Dataset, extracted_path};
+    use notitia::utils::DEFAULT_SIMILARITY_THRESHOLD;
+    use notitia::error::{Error, MalformedLine};
+    use notitia::config::{Config, ImdbConfig, MatchingConfig};
+    use notitia::imdb::{Imdb, NodeTitle, Rating, Genre, Columns, get_basic, get_basics, get_basics_batch, get_akas, get_akas_batch, get_ratings, get_ratings_batch, update_imdbs_with_akas, update_imdbs_with_akas_batch, reduce_basics, reduce_akas, rank_imdbs_by_votes, load_basics, get_imdb_by_tconst, get_basics_by_tconst_batch, ImdbDatasets};
+
+    fn matching(year_tolerance: u16) -> MatchingConfig {
+        MatchingConfig { similarity_threshold: DEFAULT_SIMILARITY_THRESHOLD, year_tolerance }
+    }
+
+    fn gzip_to_temp(path: &str, name: &str) -> String {
+        let data = fs::read(path).expect("Something went wrong reading the file");
+        let path_gz = std::env::temp_dir().join(name).to_string_lossy().to_string();
+        let mut encoder = GzEncoder::new(fs::File::create(&path_gz).unwrap(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        encoder.finish().unwrap();
+        path_gz
+    }
 
     #[test]
     fn search_basics_found() {
-        let data = fs::read_to_string("tests/files/imdb/title.basics.reduced.es_test.tsv").expect("Something went wrong reading the file");
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
         let title = "Blade Runner";
         let title_optional = "not found";
         let year = "1982";
         
-        let imdbs = get_basics(&data, (&title.to_string(), &title_optional.to_string(), &year.to_string()));
+        let imdbs = get_basics(&data, None, (&title.to_string(), &title_optional.to_string(), year), &matching(0));
         
         assert_eq!(imdbs.len(), 1);
         assert_eq!("tt0083658", imdbs[0].tconst);
@@ -25,12 +41,12 @@ mod imdb_tests {
 
     #[test]
     fn search_basics_optional_found() {
-        let data = fs::read_to_string("tests/files/imdb/title.basics.reduced.es_test.tsv").expect("Something went wrong reading the file");
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
         let title = "not found";
         let title_optional = "Blade Runner";
         let year = "1982";
         
-        let imdbs = get_basics(&data, (&title.to_string(), &title_optional.to_string(), &year.to_string()));
+        let imdbs = get_basics(&data, None, (&title.to_string(), &title_optional.to_string(), year), &matching(0));
         
         assert_eq!(imdbs.len(), 1);
         assert_eq!("tt0083658", imdbs[0].tconst);
@@ -38,33 +54,33 @@ mod imdb_tests {
 
     #[test]
     fn search_basics_not_found_wrong_year() {
-        let data = fs::read_to_string("tests/files/imdb/title.basics.reduced.es_test.tsv").expect("Something went wrong reading the file");
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
         let title = "Blade Runner";
         let title_optional = "not found";
         let year = "1882";
         
-        let imdbs = get_basics(&data, (&title.to_string(), &title_optional.to_string(), &year.to_string()));
+        let imdbs = get_basics(&data, None, (&title.to_string(), &title_optional.to_string(), year), &matching(0));
         
         assert_eq!(imdbs.len(), 0);
     }
 
     #[test]
     fn search_basics_not_found() {
-        let data = fs::read_to_string("tests/files/imdb/title.basics.reduced.es_test.tsv").expect("Something went wrong reading the file");
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
         let title = "not found";
         let title_optional = "not found";
         let year = "2025";
         
-        let imdbs = get_basics(&data, (&title.to_string(), &title_optional.to_string(), &year.to_string()));
+        let imdbs = get_basics(&data, None, (&title.to_string(), &title_optional.to_string(), year), &matching(0));
         
         assert_eq!(imdbs.len(), 0);
     }
     
     #[test]
     fn get_akas_found() {
-        let data = fs::read_to_string("tests/files/imdb/title.akas.reduced.es_test.tsv").expect("Something went wrong reading the file");
+        let data = Dataset::open("tests/files/imdb/title.akas.reduced.es_test.tsv").unwrap();
         
-        let imdbs = get_akas(&data, (&"Blade Runner".to_string(), &"not found".to_string()));
+        let imdbs = get_akas(&data, (&"Blade Runner".to_string(), &"not found".to_string()), "ES", DEFAULT_SIMILARITY_THRESHOLD);
         
         assert_eq!(imdbs.len(), 1);
         assert_eq!("tt0083658", imdbs[0].tconst);
@@ -72,75 +88,89 @@ mod imdb_tests {
 
     #[test]
     fn get_akas_optional_found() {
-        let data = fs::read_to_string("tests/files/imdb/title.akas.reduced.es_test.tsv").expect("Something went wrong reading the file");
+        let data = Dataset::open("tests/files/imdb/title.akas.reduced.es_test.tsv").unwrap();
         
-        let imdbs = get_akas(&data, (&"not found".to_string(), &"Blade Runner".to_string()));
+        let imdbs = get_akas(&data, (&"not found".to_string(), &"Blade Runner".to_string()), "ES", DEFAULT_SIMILARITY_THRESHOLD);
         
         assert_eq!(imdbs.len(), 1);
         assert_eq!("tt0083658", imdbs[0].tconst);
     }
 
+    #[test]
+    fn get_akas_without_diacritics_found() {
+        let data = Dataset::open("tests/files/imdb/title.akas.reduced.es_test.tsv").unwrap();
+        
+        let imdbs = get_akas(&data, (&"El senor de los anillos: La comunidad del anillo".to_string(), &"not found".to_string()), "ES", DEFAULT_SIMILARITY_THRESHOLD);
+        
+        assert_eq!(imdbs.len(), 1);
+        assert_eq!("tt0120737", imdbs[0].tconst);
+    }
+
     #[test]
     fn get_akas_not_found() {
-        let data = fs::read_to_string("tests/files/imdb/title.akas.reduced.es_test.tsv").expect("Something went wrong reading the file");
+        let data = Dataset::open("tests/files/imdb/title.akas.reduced.es_test.tsv").unwrap();
         
-        let imdbs = get_akas(&data, (&"not found".to_string(), &"not found".to_string()));
+        let imdbs = get_akas(&data, (&"not found".to_string(), &"not found".to_string()), "ES", DEFAULT_SIMILARITY_THRESHOLD);
         
         assert_eq!(imdbs.len(), 0);
     }
 
     #[test]
     fn get_ratings_found() {
-        let data = fs::read_to_string("tests/files/imdb/title.ratings_test.tsv").expect("Something went wrong reading the file");
+        let data = Dataset::open("tests/files/imdb/title.ratings_test.tsv").unwrap();
         
-        let rating = get_ratings(&data, &"tt1856101".to_string());
+        let rating = get_ratings(&data, None, &"tt1856101".to_string());
         
         assert_eq!(rating.tconst, "tt1856101");
-        assert_eq!(rating.averageRating, "8.0");
-        assert_eq!(rating.numVotes, "578708");
+        assert_eq!(rating.averageRating, 8.0);
+        assert_eq!(rating.numVotes, 578708);
     }
 
     #[test]
     fn get_ratings_found_similar_to_other_tconst() {
-        let data = fs::read_to_string("tests/files/imdb/title.ratings_test.tsv").expect("Something went wrong reading the file");
+        let data = Dataset::open("tests/files/imdb/title.ratings_test.tsv").unwrap();
         
-        let rating = get_ratings(&data, &"tt18561016".to_string());
+        let rating = get_ratings(&data, None, &"tt18561016".to_string());
         
         assert_eq!(rating.tconst, "tt18561016");
-        assert_eq!(rating.averageRating, "5.7");
-        assert_eq!(rating.numVotes, "19");
+        assert_eq!(rating.averageRating, 5.7);
+        assert_eq!(rating.numVotes, 19);
     }
 
     #[test]
     fn get_ratings_not_found() {
-        let data = fs::read_to_string("tests/files/imdb/title.ratings_test.tsv").expect("Something went wrong reading the file");
+        let data = Dataset::open("tests/files/imdb/title.ratings_test.tsv").unwrap();
         
-        let rating = get_ratings(&data, &"not found".to_string());
+        let rating = get_ratings(&data, None, &"not found".to_string());
         
         assert_eq!(rating.tconst, "");
-        assert_eq!(rating.averageRating, "");
-        assert_eq!(rating.numVotes, "");
+        assert_eq!(rating.averageRating, 0.0);
+        assert_eq!(rating.numVotes, 0);
     }
 
     #[test]
     fn update_imdbs_with_akas_found_and_added() {
-        let data = fs::read_to_string("tests/files/imdb/title.basics.reduced.es_test.tsv").expect("Something went wrong reading the file");
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
         let mut imdbs = Vec::<Imdb>::new();
-        let mut imdbs_akas = Vec::<Imdb>::new();
-        imdbs_akas.push(Imdb {
-            tconst: "tt0083658".to_string(),
-            titleType: String::new(),
+        let imdbs_akas = vec![
+            Imdb {
+                tconst: "tt0083658".to_string(),
+                titleType: String::new(),
             
-            primaryTitle: String::new(),
-            originalTitle: String::new(),
-            year: String::new(),
-            runtimeMinutes: String::new(),
-            genres: String::new(),
-            ratings: None,
-        });
+                primaryTitle: String::new(),
+                originalTitle: String::new(),
+                year: None,
+                runtimeMinutes: None,
+                genres: Vec::new(),
+                ratings: None,
+                score: None,
+                similarity: None,
+                year_delta: None,
+            },
+        ];
         let year = "1982";
 
-        update_imdbs_with_akas(&mut imdbs, &imdbs_akas, &year, &data);
+        update_imdbs_with_akas(&mut imdbs, &imdbs_akas, year, &data, None, 0);
 
         assert_eq!(imdbs.len(), 1);
         assert_eq!("tt0083658", imdbs[0].tconst);
@@ -148,36 +178,419 @@ mod imdb_tests {
 
     #[test]
     fn update_imdbs_with_akas_not_found() {
-        let data = fs::read_to_string("tests/files/imdb/title.basics.reduced.es_test.tsv").expect("Something went wrong reading the file");
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
         let mut imdbs = Vec::<Imdb>::new();
-        let mut imdbs_akas = Vec::<Imdb>::new();
-        imdbs_akas.push(Imdb {
-            tconst: "tt18561016".to_string(),
-            titleType: String::new(),
+        let imdbs_akas = vec![
+            Imdb {
+                tconst: "tt18561016".to_string(),
+                titleType: String::new(),
             
-            primaryTitle: String::new(),
-            originalTitle: String::new(),
-            year: String::new(),
-            runtimeMinutes: String::new(),
-            genres: String::new(),
-            ratings: None,
-        });
+                primaryTitle: String::new(),
+                originalTitle: String::new(),
+                year: None,
+                runtimeMinutes: None,
+                genres: Vec::new(),
+                ratings: None,
+                score: None,
+                similarity: None,
+                year_delta: None,
+            },
+        ];
         let year = "1922";
 
-        update_imdbs_with_akas(&mut imdbs, &imdbs_akas, &year, &data);
+        update_imdbs_with_akas(&mut imdbs, &imdbs_akas, year, &data, None, 0);
 
         assert_eq!(imdbs.len(), 0);
     }
 
     #[test]
     fn update_imdbs_with_akas_empty() {
-        let data = fs::read_to_string("tests/files/imdb/title.basics.reduced.es_test.tsv").expect("Something went wrong reading the file");
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
         let mut imdbs = Vec::<Imdb>::new();
         let imdbs_akas = Vec::<Imdb>::new();
         let year = "1982";
 
-        update_imdbs_with_akas(&mut imdbs, &imdbs_akas, &year, &data);
+        update_imdbs_with_akas(&mut imdbs, &imdbs_akas, year, &data, None, 0);
 
         assert_eq!(imdbs.len(), 0);
     }
+
+    #[test]
+    fn search_basics_gzip_found() {
+        let path_gz = gzip_to_temp("tests/files/imdb/title.basics.reduced.es_test.tsv", "notitia_search_basics_gzip_found.tsv.gz");
+        let data = Dataset::open(&path_gz).unwrap();
+        fs::remove_file(&path_gz).unwrap();
+        fs::remove_file(extracted_path(&path_gz, "")).unwrap();
+
+        let imdbs = get_basics(&data, None, (&"Blade Runner".to_string(), &String::new(), "1982"), &matching(0));
+
+        assert_eq!(imdbs.len(), 1);
+        assert_eq!("tt0083658", imdbs[0].tconst);
+    }
+
+    #[test]
+    fn reduce_basics_and_akas_gzip() {
+        let basics_gz = gzip_to_temp("tests/files/imdb/title.basics.reduced.es_test.tsv", "notitia_reduce_basics.tsv.gz");
+        let akas_gz = gzip_to_temp("tests/files/imdb/title.akas.reduced.es_test.tsv", "notitia_reduce_akas.tsv.gz");
+        let basics = std::env::temp_dir().join("notitia_reduce_basics.tsv").to_string_lossy().to_string();
+        let akas = std::env::temp_dir().join("notitia_reduce_akas.tsv").to_string_lossy().to_string();
+
+        let tconsts = reduce_basics(&basics_gz, &basics).unwrap();
+        reduce_akas(&akas_gz, &akas, "ES", &tconsts).unwrap();
+        let data = fs::read_to_string(&basics).unwrap();
+        let data_akas = fs::read_to_string(&akas).unwrap();
+        for path in [&basics_gz, &akas_gz, &basics, &akas] {
+            fs::remove_file(path).unwrap();
+        }
+
+        assert!(tconsts.contains("tt0083658"));
+        assert!(data.starts_with("tconst\ttitleType"));
+        assert!(data.contains("tt0083658\tmovie\tBlade Runner"));
+        assert!(data_akas.starts_with("titleId\tordering"));
+        assert!(data_akas.contains("tt0083658\t16\tBlade Runner\tES"));
+        assert!(!data_akas.contains("\tEN\t"));
+    }
+
+    #[test]
+    fn reduce_basics_skips_other_title_types() {
+        let input = std::env::temp_dir().join("notitia_reduce_basics_types_input.tsv").to_string_lossy().to_string();
+        let output = std::env::temp_dir().join("notitia_reduce_basics_types_output.tsv").to_string_lossy().to_string();
+        fs::write(&input, "tconst\ttitleType\tprimaryTitle\ntt1\tvideoGame\tGame\ntt2\ttvMovie\tMovie\n").unwrap();
+
+        let tconsts = reduce_basics(&input, &output).unwrap();
+        let data = fs::read_to_string(&output).unwrap();
+        fs::remove_file(&input).unwrap();
+        fs::remove_file(&output).unwrap();
+
+        assert_eq!(1, tconsts.len());
+        assert!(tconsts.contains("tt2"));
+        assert_eq!("tconst\ttitleType\tprimaryTitle\ntt2\ttvMovie\tMovie\n", data);
+    }
+
+    #[test]
+    fn search_basics_typed_values() {
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
+
+        let imdbs = get_basics(&data, None, (&"Blade Runner".to_string(), &String::new(), "1982"), &matching(0));
+
+        assert_eq!(Some(1982), imdbs[0].year);
+        assert_eq!(Some(117), imdbs[0].runtimeMinutes);
+        assert_eq!(vec![Genre::Action, Genre::Drama, Genre::SciFi], imdbs[0].genres);
+    }
+
+    #[test]
+    fn imdb_from_previous_working_file() {
+        let json = r#"{"tconst":"tt0083658","titleType":"movie","primaryTitle":"Blade Runner","originalTitle":"Blade Runner","year":"1982","runtimeMinutes":"\\N","genres":"Action,Drama,Sci-Fi","ratings":"8.1"}"#;
+
+        let imdb: Imdb = serde_json::from_str(json).unwrap();
+
+        assert_eq!(Some(1982), imdb.year);
+        assert_eq!(None, imdb.runtimeMinutes);
+        assert_eq!(vec![Genre::Action, Genre::Drama, Genre::SciFi], imdb.genres);
+        assert_eq!(Some(Rating { averageRating: 8.1, numVotes: 0 }), imdb.ratings);
+    }
+
+    #[test]
+    fn imdb_ratings_from_number_and_object() {
+        let imdb: Imdb = serde_json::from_str(r#"{"tconst":"tt1","titleType":"movie","primaryTitle":"Movie","originalTitle":"Movie","ratings":7.5}"#).unwrap();
+        assert_eq!(Some(Rating { averageRating: 7.5, numVotes: 0 }), imdb.ratings);
+
+        let imdb: Imdb = serde_json::from_str(r#"{"tconst":"tt1","titleType":"movie","primaryTitle":"Movie","originalTitle":"Movie","ratings":{"averageRating":7.5,"numVotes":1200}}"#).unwrap();
+        assert_eq!(Some(Rating { averageRating: 7.5, numVotes: 1200 }), imdb.ratings);
+
+        let imdb: Imdb = serde_json::from_str(r#"{"tconst":"tt1","titleType":"movie","primaryTitle":"Movie","originalTitle":"Movie","ratings":null}"#).unwrap();
+        assert_eq!(None, imdb.ratings);
+    }
+
+    #[test]
+    fn rank_imdbs_by_votes_sorts_and_filters() {
+        let imdb = |tconst: &str, numVotes: u32| Imdb {
+            tconst: tconst.to_string(),
+            ratings: Some(Rating { averageRating: 7.0, numVotes }),
+            ..Default::default()
+        };
+        let mut imdbs = vec![imdb("tt1", 10), imdb("tt2", 5000), imdb("tt3", 300)];
+
+        rank_imdbs_by_votes(&mut imdbs, 100);
+
+        let tconsts: Vec<&str> = imdbs.iter().map(|imdb| imdb.tconst.as_str()).collect();
+        assert_eq!(vec!["tt2", "tt3"], tconsts);
+
+        let mut imdbs = vec![imdb("tt1", 10), imdb("tt2", 20)];
+        rank_imdbs_by_votes(&mut imdbs, 100);
+        assert_eq!(2, imdbs.len());
+        assert_eq!("tt2", imdbs[0].tconst);
+    }
+
+    #[test]
+    fn imdb_serialize_and_deserialize() {
+        let imdb = Imdb {
+            tconst: "tt0083658".to_string(),
+            year: Some(1982),
+            runtimeMinutes: Some(117),
+            genres: vec![Genre::SciFi, Genre::Other("Unknown".to_string())],
+            ratings: Some(Rating { averageRating: 8.1, numVotes: 675000 }),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&imdb).unwrap();
+        let imdb_deserialized: Imdb = serde_json::from_str(&json).unwrap();
+
+        assert!(json.contains(r#""year":1982"#));
+        assert!(json.contains(r#""genres":["Sci-Fi","Unknown"]"#));
+        assert_eq!(imdb, imdb_deserialized);
+    }
+
+    #[test]
+    fn search_basics_reordered_and_new_columns() {
+        let data = Dataset::from_bytes("basics.tsv", "tconst\tnewColumn\tstartYear\toriginalTitle\tprimaryTitle\ttitleType\n\
+            tt0083658\tnew\t1982\tBlade Runner\tBlade Runner\tmovie\n".as_bytes().to_vec());
+
+        let imdbs = get_basics(&data, None, (&"Blade Runner".to_string(), &String::new(), "1982"), &matching(0));
+
+        assert_eq!(imdbs.len(), 1);
+        assert_eq!("tt0083658", imdbs[0].tconst);
+        assert_eq!("movie", imdbs[0].titleType);
+        assert_eq!(None, imdbs[0].runtimeMinutes);
+    }
+
+    #[test]
+    fn search_basics_malformed_lines_are_skipped() {
+        let data = Dataset::from_bytes("basics.tsv", "tconst\ttitleType\tprimaryTitle\toriginalTitle\tstartYear\n\
+            tt1\tmovie\tBlade Runner\n\
+            tt2\tmovie\tBlade Runner\tBlade Runner\tyear\n\
+            tt0083658\tmovie\tBlade Runner\tBlade Runner\t1982\n".as_bytes().to_vec());
+
+        let imdbs = get_basics(&data, None, (&"Blade Runner".to_string(), &String::new(), "1982"), &matching(0));
+
+        assert_eq!(imdbs.len(), 1);
+        assert_eq!("tt0083658", imdbs[0].tconst);
+    }
+
+    #[test]
+    fn search_basics_malformed_lines_are_counted() {
+        let malformed: String = (0..20).map(|i| format!("tt{}\tmovie\tBlade Runner\tBlade Runner\tyear\n", i)).collect();
+        let data = Dataset::from_bytes("basics.tsv", format!("tconst\ttitleType\tprimaryTitle\toriginalTitle\tstartYear\n{}\
+            tt0083658\tmovie\tBlade Runner\tBlade Runner\t1982\n", malformed).into_bytes());
+
+        let imdbs = get_basics(&data, None, (&"Blade Runner".to_string(), &String::new(), "1982"), &matching(0));
+
+        assert_eq!(imdbs.len(), 1);
+        assert_eq!(20, data.malformed_lines());
+    }
+
+    #[test]
+    fn get_basic_malformed_line() {
+        let data = "tconst\ttitleType\tprimaryTitle\toriginalTitle\tstartYear\ntt1\tmovie\tBlade Runner\n".as_bytes();
+        let columns = Columns::from_header("basics.tsv", "tconst\ttitleType\tprimaryTitle\toriginalTitle\tstartYear", &[]).unwrap();
+
+        let message = get_basic("tt1\tmovie\tBlade Runner", &columns).unwrap_err();
+        let error = columns.malformed_line(data, data.iter().position(|&c| c == b'\n').unwrap() + 1, message);
+
+        assert_eq!("basics.tsv:2: missing column 'originalTitle', expected 5 columns and found 3", error.to_string());
+    }
+
+    #[test]
+    fn columns_missing_required_column() {
+        let error = Columns::from_header("basics.tsv", "tconst\ttitleType", &["tconst", "startYear"]).unwrap_err();
+
+        assert_eq!(MalformedLine {
+            file: "basics.tsv".to_string(),
+            line: 1,
+            message: "missing column 'startYear' in header".to_string(),
+        }, error);
+    }
+
+    #[test]
+    fn search_basics_missing_column_in_header_not_found() {
+        let data = Dataset::from_bytes("basics.tsv", "tconst\tprimaryTitle\ntt0083658\tBlade Runner\n".as_bytes().to_vec());
+
+        let imdbs = get_basics(&data, None, (&"Blade Runner".to_string(), &String::new(), "1982"), &matching(0));
+
+        assert_eq!(imdbs.len(), 0);
+    }
+
+    #[test]
+    fn search_basics_similar_titles() {
+        let data = Dataset::from_bytes("basics.tsv", "tconst\ttitleType\tprimaryTitle\toriginalTitle\tstartYear\n\
+            tt0211915\tmovie\tAmélie\tLe fabuleux destin d'Amélie Poulain\t2001\n\
+            tt0071562\tmovie\tThe Godfather Part II\tThe Godfather Part II\t1974\n".as_bytes().to_vec());
+
+        let imdbs = get_basics(&data, None, (&"Amelie".to_string(), &String::new(), "2001"), &matching(0));
+        assert_eq!(1, imdbs.len());
+        assert_eq!("tt0211915", imdbs[0].tconst);
+        assert_eq!(Some(1.0), imdbs[0].similarity);
+
+        let imdbs = get_basics(&data, None, (&"Godfather Part 2".to_string(), &String::new(), "1974"), &matching(0));
+        assert_eq!(1, imdbs.len());
+        assert_eq!("tt0071562", imdbs[0].tconst);
+
+        let imdbs = get_basics(&data, None, (&"Godfather Part 2".to_string(), &String::new(), "1974"), &MatchingConfig { similarity_threshold: 1.01, year_tolerance: 0 });
+        assert_eq!(0, imdbs.len());
+    }
+
+    #[test]
+    fn search_basics_exact_title_similarity() {
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
+
+        let imdbs = get_basics(&data, None, (&"Blade Runner".to_string(), &String::new(), "1982"), &matching(0));
+
+        assert_eq!(Some(1.0), imdbs[0].similarity);
+    }
+
+    #[test]
+    fn search_basics_year_tolerance() {
+        let data = Dataset::from_bytes("basics.tsv", "tconst\ttitleType\tprimaryTitle\toriginalTitle\tstartYear\n\
+            tt1\tmovie\tThe Movie\tThe Movie\t2019\n\
+            tt2\tmovie\tThe Movie\tThe Movie\t2020\n\
+            tt3\tmovie\tThe Movie\tThe Movie\t2022\n".as_bytes().to_vec());
+
+        let imdbs = get_basics(&data, None, (&"The Movie".to_string(), &String::new(), "2020"), &matching(0));
+        assert_eq!(1, imdbs.len());
+        assert_eq!(Some(0), imdbs[0].year_delta);
+
+        let imdbs = get_basics(&data, None, (&"The Movie".to_string(), &String::new(), "2020"), &matching(1));
+        let tconsts: Vec<&str> = imdbs.iter().map(|imdb| imdb.tconst.as_str()).collect();
+        assert_eq!(vec!["tt2", "tt1"], tconsts);
+        assert_eq!(Some(-1), imdbs[1].year_delta);
+
+        let imdbs = get_basics(&data, None, (&"The Movie".to_string(), &String::new(), "2021"), &matching(1));
+        assert_eq!(2, imdbs.len());
+        assert_eq!(Some(-1), imdbs[0].year_delta);
+        assert_eq!(Some(1), imdbs[1].year_delta);
+    }
+
+    #[test]
+    fn rank_imdbs_by_votes_exact_year_first() {
+        let imdb = |tconst: &str, year_delta: i16, numVotes: u32| Imdb {
+            tconst: tconst.to_string(),
+            ratings: Some(Rating { averageRating: 7.0, numVotes }),
+            year_delta: Some(year_delta),
+            ..Default::default()
+        };
+        let mut imdbs = vec![imdb("tt1", 1, 5000), imdb("tt2", 0, 10)];
+
+        rank_imdbs_by_votes(&mut imdbs, 0);
+
+        assert_eq!("tt2", imdbs[0].tconst);
+    }
+
+    fn batch_nodes() -> Vec<NodeTitle> {
+        let node = |title: &str, title_optional: &str, year: &str| (title.to_string(), title_optional.to_string(), year.to_string());
+        vec![
+            node("Blade Runner", "not found", "1982"),
+            node("not found", "Blade Runner", "1982"),
+            node("Blade Runner", "", "1882"),
+            node("not found", "not found", "2025"),
+            node("El senor de los anillos: La comunidad del anillo", "", "2001"),
+            node("Blade Runner", "not found", "1982"),
+        ]
+    }
+
+    #[test]
+    fn get_basics_batch_same_as_get_basics() {
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
+        let nodes = batch_nodes();
+
+        let imdbs = get_basics_batch(&data, None, &nodes, &Config { threads: 2, ..Default::default() });
+
+        assert_eq!(nodes.len(), imdbs.len());
+        for ((title, title_optional, year), imdbs) in nodes.iter().zip(&imdbs) {
+            assert_eq!(&get_basics(&data, None, (title, title_optional, year), &matching(1)), imdbs);
+        }
+        assert_eq!("tt0083658", imdbs[1][0].tconst);
+    }
+
+    #[test]
+    fn get_akas_batch_same_as_get_akas() {
+        let data = Dataset::open("tests/files/imdb/title.akas.reduced.es_test.tsv").unwrap();
+        let nodes = batch_nodes();
+
+        let imdbs = get_akas_batch(&data, &nodes, &Config { imdb: ImdbConfig { language: "ES".to_string(), ..Default::default() }, threads: 2, ..Default::default() });
+
+        assert_eq!(nodes.len(), imdbs.len());
+        for ((title, title_optional, _), imdbs) in nodes.iter().zip(&imdbs) {
+            assert_eq!(&get_akas(&data, (title, title_optional), "ES", DEFAULT_SIMILARITY_THRESHOLD), imdbs);
+        }
+        assert_eq!("tt0120737", imdbs[4][0].tconst);
+    }
+
+    #[test]
+    fn get_ratings_batch_found() {
+        let data = Dataset::open("tests/files/imdb/title.ratings_test.tsv").unwrap();
+        let tconsts = vec!["tt1856101".to_string(), "tt18561016".to_string(), "not found".to_string()];
+
+        let ratings = get_ratings_batch(&data, None, &tconsts, &Config { threads: 2, ..Default::default() });
+
+        assert_eq!(3, ratings.len());
+        assert_eq!(578708, ratings["tt1856101"].numVotes);
+        assert_eq!(19, ratings["tt18561016"].numVotes);
+        assert_eq!("", ratings["not found"].tconst);
+    }
+
+    #[test]
+    fn update_imdbs_with_akas_batch_found_and_added() {
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
+        let aka = Imdb { tconst: "tt0083658".to_string(), ..Default::default() };
+        let nodes = vec![
+            ("Blade Runner".to_string(), String::new(), "1982".to_string()),
+            ("Blade Runner".to_string(), String::new(), "1882".to_string()),
+            ("not found".to_string(), String::new(), "1982".to_string()),
+        ];
+        let mut imdbs = vec![Vec::<Imdb>::new(); 3];
+
+        update_imdbs_with_akas_batch(&mut imdbs, &[vec![aka.clone()], vec![aka], Vec::new()], &nodes, &data, None, 0);
+
+        assert_eq!(1, imdbs[0].len());
+        assert_eq!("tt0083658", imdbs[0][0].tconst);
+        assert_eq!(0, imdbs[1].len());
+        assert_eq!(0, imdbs[2].len());
+    }
+
+    #[test]
+    fn load_basics_missing_column_in_header() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("basics.tsv").to_string_lossy().to_string();
+        fs::write(&path, "tconst\tprimaryTitle\ntt0083658\tBlade Runner\n").unwrap();
+
+        let error = load_basics(&path, "").err().unwrap();
+
+        assert!(matches!(&error, Error::MalformedLine(MalformedLine { line: 1, .. })));
+    }
+
+    #[test]
+    fn get_imdb_by_tconst_found() {
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
+        let data_ratings = Dataset::open("tests/files/imdb/title.ratings_test.tsv").unwrap();
+        let imdb = get_imdb_by_tconst(&ImdbDatasets { basics: &data, index_basics: None, ratings: &data_ratings, index_ratings: None }, "TT1856101").unwrap().unwrap();
+
+        assert_eq!("tt1856101", imdb.tconst);
+        assert_eq!("Blade Runner 2049", imdb.primaryTitle);
+        assert_eq!(Some(2017), imdb.year);
+        assert_eq!(Some(Rating { averageRating: 8.0, numVotes: 578708 }), imdb.ratings);
+        assert_eq!(None, imdb.year_delta);
+    }
+
+    #[test]
+    fn get_imdb_by_tconst_not_found() {
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
+        let data_ratings = Dataset::open("tests/files/imdb/title.ratings_test.tsv").unwrap();
+
+        assert_eq!(None, get_imdb_by_tconst(&ImdbDatasets { basics: &data, index_basics: None, ratings: &data_ratings, index_ratings: None }, "tt185610").unwrap());
+    }
+
+    #[test]
+    fn get_basics_by_tconst_batch_same_as_get_imdb_by_tconst() {
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
+        let data_ratings = Dataset::open("tests/files/imdb/title.ratings_test.tsv").unwrap();
+        let tconsts = vec!["tt0083658".to_string(), "tt185610".to_string(), "TT1856101".to_string()];
+
+        let imdbs = get_basics_by_tconst_batch(&data, None, &tconsts, &Config { threads: 2, ..Default::default() }).unwrap();
+
+        assert_eq!(tconsts.len(), imdbs.len());
+        for (tconst, imdb) in tconsts.iter().zip(imdbs) {
+            let expected = get_imdb_by_tconst(&ImdbDatasets { basics: &data, index_basics: None, ratings: &data_ratings, index_ratings: None }, tconst).unwrap().map(|imdb| Imdb { ratings: None, ..imdb });
+            assert_eq!(expected, imdb);
+        }
+    }
 }
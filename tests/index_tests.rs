@@ -0,0 +1,208 @@
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+#[cfg(test)]
+mod index_tests {
+    use std::fs;
+    use notitia::dataset::Dataset;
+    use notitia::utils::{DEFAULT_SIMILARITY_THRESHOLD, title_similarity, search_indices};
+    use notitia::index::{index_path, build_index, build_index_in_chunks, load_index};
+    use notitia::error::Error;
+    use notitia::config::MatchingConfig;
+    use notitia::imdb::{Imdb, get_basics, get_ratings, update_imdbs_with_akas, build_basics_index, build_ratings_index};
+
+    fn matching(year_tolerance: u16) -> MatchingConfig {
+        MatchingConfig { similarity_threshold: DEFAULT_SIMILARITY_THRESHOLD, year_tolerance }
+    }
+
+    fn index_file(folder: &tempfile::TempDir) -> String {
+        folder.path().join("dataset.tsv.idx").to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn build_basics_index_titles_and_ids() {
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
+
+        let folder = tempfile::tempdir().unwrap();
+        let index = build_basics_index(&data, &index_file(&folder)).unwrap();
+
+        assert_eq!(data.len(), index.data_len);
+        assert_eq!(1, index.title_indices("Blade Runner").len());
+        assert_eq!(1, index.title_indices("the lord of the rings the fellowship of the ring").len());
+        assert_eq!(1, index.id_indices(&"tt0083658".to_string()).len());
+        assert_eq!(0, index.id_indices(&"tconst".to_string()).len());
+    }
+
+    #[test]
+    fn search_basics_with_index_found() {
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
+        let folder = tempfile::tempdir().unwrap();
+        let index = build_basics_index(&data, &index_file(&folder)).unwrap();
+
+        let imdbs = get_basics(&data, Some(&index), (&"Blade Runner".to_string(), &"not found".to_string(), "1982"), &matching(0));
+
+        assert_eq!(imdbs.len(), 1);
+        assert_eq!("tt0083658", imdbs[0].tconst);
+    }
+
+    #[test]
+    fn title_with_comma_found_with_and_without_index() {
+        let data = Dataset::from_bytes("title.basics.tsv", b"tconst\ttitleType\tprimaryTitle\toriginalTitle\tisAdult\tstartYear\tendYear\truntimeMinutes\tgenres\n\
+            tt0190332\tmovie\tCrouching Tiger, Hidden Dragon\tWo hu cang long\t0\t2000\t\\N\t120\tAction,Adventure,Drama\n\
+            tt0097165\tmovie\t\"Dead Poets Society\"\tDead Poets Society\t0\t1989\t\\N\t128\tComedy,Drama\n".to_vec());
+        let folder = tempfile::tempdir().unwrap();
+        let index = build_basics_index(&data, &index_file(&folder)).unwrap();
+
+        for title in ["Crouching Tiger, Hidden Dragon", "Crouching Tiger Hidden Dragon", "\"Dead Poets Society\""] {
+            let with_index = index.title_indices(title);
+            let without_index = search_indices(&data, &format!("\t{}\t", title)).unwrap();
+
+            assert_eq!(with_index.len(), 1, "{}", title);
+            assert_eq!(with_index, without_index, "{}", title);
+        }
+    }
+
+    #[test]
+    fn search_basics_with_index_not_found_wrong_year() {
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
+        let folder = tempfile::tempdir().unwrap();
+        let index = build_basics_index(&data, &index_file(&folder)).unwrap();
+
+        let imdbs = get_basics(&data, Some(&index), (&"Blade Runner".to_string(), &"not found".to_string(), "1882"), &matching(0));
+
+        assert_eq!(imdbs.len(), 0);
+    }
+
+    #[test]
+    fn update_imdbs_with_akas_with_index_found_and_added() {
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
+        let folder = tempfile::tempdir().unwrap();
+        let index = build_basics_index(&data, &index_file(&folder)).unwrap();
+        let mut imdbs = Vec::<Imdb>::new();
+        let imdbs_akas = vec![Imdb { tconst: "tt0083658".to_string(), ..Default::default() }];
+
+        update_imdbs_with_akas(&mut imdbs, &imdbs_akas, "1982", &data, Some(&index), 0);
+
+        assert_eq!(imdbs.len(), 1);
+        assert_eq!("tt0083658", imdbs[0].tconst);
+    }
+
+    #[test]
+    fn get_ratings_with_index_found_similar_to_other_tconst() {
+        let data = Dataset::open("tests/files/imdb/title.ratings_test.tsv").unwrap();
+        let folder = tempfile::tempdir().unwrap();
+        let index = build_ratings_index(&data, &index_file(&folder)).unwrap();
+
+        let rating = get_ratings(&data, Some(&index), &"tt18561016".to_string());
+
+        assert_eq!(rating.tconst, "tt18561016");
+        assert_eq!(rating.averageRating, 5.7);
+        assert_eq!(rating.numVotes, 19);
+    }
+
+    #[test]
+    fn build_and_load_index() {
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
+        let folder = tempfile::tempdir().unwrap();
+        let path = index_path(&folder.path().join("title.basics.tsv").to_string_lossy());
+        let index = build_basics_index(&data, &path).unwrap();
+
+        let loaded = load_index(&path, &data).unwrap();
+        let outdated = load_index(&path, &data[..data.len() - 1]).unwrap();
+
+        assert_eq!(Some(index), loaded);
+        assert_eq!(None, outdated);
+    }
+
+    #[test]
+    fn load_index_dataset_replaced_with_same_length() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("ratings.tsv.idx").to_string_lossy().to_string();
+        let data = "tconst\taverageRating\tnumVotes\ntt0000001\t5.7\t1960\n";
+        let replaced = "tconst\taverageRating\tnumVotes\ntt0000002\t5.7\t1960\n";
+        build_index(&path, data.as_bytes(), 0, &[]).unwrap();
+
+        assert!(load_index(&path, data.as_bytes()).unwrap().is_some());
+        assert_eq!(None, load_index(&path, replaced.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn loaded_index_found() {
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("basics.tsv.idx").to_string_lossy().to_string();
+        build_basics_index(&data, &path).unwrap();
+
+        let index = load_index(&path, &data).unwrap().unwrap();
+
+        assert_eq!(1, index.title_indices("Blade Runner").len());
+        assert_eq!(1, index.id_indices(&"tt0083658".to_string()).len());
+        assert_eq!(0, index.id_indices(&"tt0083659".to_string()).len());
+        assert_eq!(1, index.similar_title_indices("Blade Runer 2049", DEFAULT_SIMILARITY_THRESHOLD).len());
+    }
+
+    #[test]
+    fn build_index_in_chunks_same_as_in_one_chunk() {
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
+        let folder = tempfile::tempdir().unwrap();
+        let in_one_chunk = build_index(&folder.path().join("one.idx").to_string_lossy(), &data, 0, &[2, 3]).unwrap();
+
+        // 9 lines with 2 titles each, in chunks of 4 records
+        let index = build_index_in_chunks(&index_file(&folder), &data, 0, &[2, 3], 4).unwrap();
+
+        assert_eq!(in_one_chunk, index);
+        assert_eq!(1, index.title_indices("Blade Runner").len());
+        assert_eq!(1, index.id_indices(&"tt0083658".to_string()).len());
+        assert_eq!(1, index.similar_title_indices("Blade Runer 2049", DEFAULT_SIMILARITY_THRESHOLD).len());
+        // the temporary files of the chunks are removed
+        assert_eq!(2, fs::read_dir(folder.path()).unwrap().count());
+    }
+
+    #[test]
+    fn load_index_previous_version() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("basics.tsv.idx").to_string_lossy().to_string();
+        fs::write(&path, "notitia-index\t10\nt\tblade runner\t1,2\n").unwrap();
+
+        assert_eq!(None, load_index(&path, &[b' '; 10]).unwrap());
+    }
+
+    #[test]
+    fn similar_title_indices_same_as_title_similarity() {
+        let data = Dataset::open("tests/files/imdb/title.basics.reduced.es_test.tsv").unwrap();
+        let folder = tempfile::tempdir().unwrap();
+        let index = build_basics_index(&data, &index_file(&folder)).unwrap();
+        let title = "Blade Runer 2049".to_string();
+
+        let mut expected: Vec<usize> = index.titles()
+            .filter(|(key, _)| title_similarity(key, &title) >= DEFAULT_SIMILARITY_THRESHOLD)
+            .flat_map(|(_, indices)| indices)
+            .collect();
+        expected.sort();
+        expected.dedup();
+
+        assert_eq!(1, expected.len());
+        assert_eq!(expected, index.similar_title_indices(&title, DEFAULT_SIMILARITY_THRESHOLD));
+    }
+
+    #[test]
+    fn load_index_not_found() {
+        assert_eq!(None, load_index("tests/files/imdb/not_found.idx", &[]).unwrap());
+    }
+
+    #[test]
+    fn load_index_corrupt() {
+        let data = "tconst\taverageRating\tnumVotes\ntt0000001\t5.7\t1960\n".as_bytes();
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("ratings.tsv.idx").to_string_lossy().to_string();
+        build_index(&path, data, 0, &[]).unwrap();
+        let saved = fs::read(&path).unwrap();
+        let header_end = saved.iter().position(|&c| c == b'\n').unwrap() + 1;
+        fs::write(&path, &saved[..header_end + 4]).unwrap();
+
+        let error = load_index(&path, data).unwrap_err();
+
+        assert!(matches!(&error, Error::Io { .. }));
+    }
+}
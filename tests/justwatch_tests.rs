@@ -2,14 +2,11 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
-#[path = "../src/utils.rs"] mod utils;
-#[path = "../src/imdb.rs"] mod imdb;
-#[path = "../src/just_watch.rs"] mod justwatch;
-
 #[cfg(test)]
 mod justwatch_tests {
-    use crate::imdb::{Imdb};
-    use crate::justwatch::{load_justwatch_file, load_justwatch_files, get_justwatchs};
+    use notitia::imdb::{Imdb};
+    use notitia::error::Error;
+    use notitia::just_watch::{load_justwatch_file, load_justwatch_files, get_justwatchs, get_justwatchs_by_tconst, get_justwatch_by_id};
 
     #[test]
     fn get_justwatchs_title_found() {
@@ -17,19 +14,40 @@ mod justwatch_tests {
         let imdbs = Vec::<Imdb>::new();
         let title = "Nightmare Alley";
         
-        let justwatchs = get_justwatchs(&justwatch_edges, &imdbs, &title.to_string());
+        let justwatchs = get_justwatchs(&justwatch_edges, &imdbs, title);
         
         assert_eq!(justwatchs.len(), 1);
         assert_eq!("tm855362", justwatchs[0].id);
     }
 
+    #[test]
+    fn get_justwatch_by_id_found() {
+        let justwatch_edges = load_justwatch_file("tests/files/justwatch/test.json").unwrap();
+
+        let justwatch = get_justwatch_by_id(&justwatch_edges, "tm855362").unwrap();
+
+        assert_eq!("Nightmare Alley", justwatch.edge.as_ref().unwrap().node.content.title);
+        assert!(justwatch.edge.unwrap().node.content.credits.is_empty());
+        assert!(get_justwatch_by_id(&justwatch_edges, "tm0").is_none());
+    }
+
+    #[test]
+    fn get_justwatchs_by_tconst_found() {
+        let justwatch_edges = load_justwatch_file("tests/files/justwatch/test.json").unwrap();
+
+        let justwatchs = get_justwatchs_by_tconst(&justwatch_edges, "TT7740496");
+
+        assert_eq!(vec!["tm855362"], justwatchs.iter().map(|justwatch| justwatch.id.as_str()).collect::<Vec<&str>>());
+        assert!(get_justwatchs_by_tconst(&justwatch_edges, "tt0000000").is_empty());
+    }
+
     #[test]
     fn get_justwatchs_title_not_found() {
         let justwatch_edges = load_justwatch_file("tests/files/justwatch/test.json").unwrap();
         let imdbs = Vec::<Imdb>::new();
         let title = "not found";
         
-        let justwatchs = get_justwatchs(&justwatch_edges, &imdbs, &title.to_string());
+        let justwatchs = get_justwatchs(&justwatch_edges, &imdbs, title);
         
         assert_eq!(justwatchs.len(), 0);
     }
@@ -37,21 +55,25 @@ mod justwatch_tests {
     #[test]
     fn get_justwatchs_imdb_id_found() {
         let justwatch_edges = load_justwatch_file("tests/files/justwatch/test.json").unwrap();
-        let mut imdbs = Vec::<Imdb>::new();        
-        imdbs.push(Imdb {
-            tconst: "tt7740496".to_string(),
-            titleType: String::new(),
+        let imdbs = vec![
+            Imdb {
+                tconst: "tt7740496".to_string(),
+                titleType: String::new(),
             
-            primaryTitle: String::new(),
-            originalTitle: String::new(),
-            year: String::new(),
-            runtimeMinutes: String::new(),
-            genres: String::new(),
-            ratings: None,
-        });
+                primaryTitle: String::new(),
+                originalTitle: String::new(),
+                year: None,
+                runtimeMinutes: None,
+                genres: Vec::new(),
+                ratings: None,
+                score: None,
+                similarity: None,
+                year_delta: None,
+            },
+        ];
         let title = "not found";
         
-        let justwatchs = get_justwatchs(&justwatch_edges, &imdbs, &title.to_string());
+        let justwatchs = get_justwatchs(&justwatch_edges, &imdbs, title);
         
         assert_eq!(justwatchs.len(), 1);
         assert_eq!("tm855362", justwatchs[0].id);
@@ -60,32 +82,39 @@ mod justwatch_tests {
     #[test]
     fn get_justwatchs_several_imdb_ids_found() {
         let justwatch_edges = load_justwatch_file("tests/files/justwatch/test.json").unwrap();
-        let mut imdbs = Vec::<Imdb>::new();        
-        imdbs.push(Imdb {
-            tconst: "tt1950186".to_string(),
-            titleType: String::new(),
+        let imdbs = vec![
+            Imdb {
+                tconst: "tt1950186".to_string(),
+                titleType: String::new(),
             
-            primaryTitle: String::new(),
-            originalTitle: String::new(),
-            year: String::new(),
-            runtimeMinutes: String::new(),
-            genres: String::new(),
-            ratings: None,
-        });     
-        imdbs.push(Imdb {
-            tconst: "tt7740496".to_string(),
-            titleType: String::new(),
+                primaryTitle: String::new(),
+                originalTitle: String::new(),
+                year: None,
+                runtimeMinutes: None,
+                genres: Vec::new(),
+                ratings: None,
+                score: None,
+                similarity: None,
+                year_delta: None,
+            },
+            Imdb {
+                tconst: "tt7740496".to_string(),
+                titleType: String::new(),
             
-            primaryTitle: String::new(),
-            originalTitle: String::new(),
-            year: String::new(),
-            runtimeMinutes: String::new(),
-            genres: String::new(),
-            ratings: None,
-        });
+                primaryTitle: String::new(),
+                originalTitle: String::new(),
+                year: None,
+                runtimeMinutes: None,
+                genres: Vec::new(),
+                ratings: None,
+                score: None,
+                similarity: None,
+                year_delta: None,
+            },
+        ];
         let title = "not found";
         
-        let justwatchs = get_justwatchs(&justwatch_edges, &imdbs, &title.to_string());
+        let justwatchs = get_justwatchs(&justwatch_edges, &imdbs, title);
         
         assert_eq!(justwatchs.len(), 2);
         assert_eq!("tm855362", justwatchs[0].id);
@@ -95,21 +124,25 @@ mod justwatch_tests {
     #[test]
     fn get_justwatchs_imdb_id_not_found() {
         let justwatch_edges = load_justwatch_file("tests/files/justwatch/test.json").unwrap();
-        let mut imdbs = Vec::<Imdb>::new();
-        imdbs.push(Imdb {
-            tconst: "not found".to_string(),
-            titleType: String::new(),
+        let imdbs = vec![
+            Imdb {
+                tconst: "not found".to_string(),
+                titleType: String::new(),
             
-            primaryTitle: String::new(),
-            originalTitle: String::new(),
-            year: String::new(),
-            runtimeMinutes: String::new(),
-            genres: String::new(),
-            ratings: None,
-        });
+                primaryTitle: String::new(),
+                originalTitle: String::new(),
+                year: None,
+                runtimeMinutes: None,
+                genres: Vec::new(),
+                ratings: None,
+                score: None,
+                similarity: None,
+                year_delta: None,
+            },
+        ];
         let title = "not found";
         
-        let justwatchs = get_justwatchs(&justwatch_edges, &imdbs, &title.to_string());
+        let justwatchs = get_justwatchs(&justwatch_edges, &imdbs, title);
         
         assert_eq!(justwatchs.len(), 0);
     }
@@ -120,7 +153,7 @@ mod justwatch_tests {
         let imdbs = Vec::<Imdb>::new();
         let title = "Nightmare Alley";
         
-        let justwatchs = get_justwatchs(&justwatch_edges, &imdbs, &title.to_string());
+        let justwatchs = get_justwatchs(&justwatch_edges, &imdbs, title);
         
         assert_eq!(justwatchs.len(), 1);
         assert_eq!("tm855362", justwatchs[0].id);
@@ -132,9 +165,19 @@ mod justwatch_tests {
         let imdbs = Vec::<Imdb>::new();
         let title = "Derry Girls";
         
-        let justwatchs = get_justwatchs(&justwatch_edges, &imdbs, &title.to_string());
+        let justwatchs = get_justwatchs(&justwatch_edges, &imdbs, title);
         
         assert_eq!(justwatchs.len(), 1);
         assert_eq!("ts77795", justwatchs[0].id);
     }
+
+    #[test]
+    fn load_justwatch_file_invalid_json() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("justwatch.json").to_string_lossy().to_string();
+        std::fs::write(&path, "[{\"node\": ").unwrap();
+
+        assert!(matches!(load_justwatch_file(&path), Err(Error::Json { .. })));
+        assert!(matches!(load_justwatch_file("tests/files/justwatch/not_found.json"), Err(Error::MissingDataset { .. })));
+    }
 }
@@ -0,0 +1,21 @@
+#![allow(non_snake_case)]
+
+#[cfg(test)]
+mod logging_tests {
+    use notitia::error::Error;
+    use notitia::logging::{LogConfig, init};
+
+    #[test]
+    fn init_unknown_level() {
+        let config = LogConfig { level: "verbose".to_string(), ..Default::default() };
+
+        assert!(matches!(init(&config), Err(Error::Usage(message)) if message == "unknown log level 'verbose'"));
+    }
+
+    #[test]
+    fn init_unknown_format() {
+        let config = LogConfig { format: "xml".to_string(), ..Default::default() };
+
+        assert!(matches!(init(&config), Err(Error::Usage(message)) if message == "unknown log format 'xml'"));
+    }
+}
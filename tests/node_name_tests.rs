@@ -0,0 +1,285 @@
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+#[cfg(test)]
+mod node_name_tests {
+    use std::fs;
+    use serde::Deserialize;
+    use notitia::config::Config;
+    use notitia::node_name::{ParsedNodeName, NodeExtra, FormatRule, node_format, format_extensions, search_title, deserialize_node_extra};
+
+    fn parse_node_name(node_name: &str) -> ParsedNodeName {
+        let config = Config { ..Default::default() };
+        notitia::node_name::parse_node_name(node_name, &format_extensions(&config.format_rules, &config.filter_node_format))
+    }
+
+    fn parsed(titles: &[&str], year: Option<u16>, extra: &str, tags: &[&str], format: &str) -> ParsedNodeName {
+        ParsedNodeName {
+            titles: titles.iter().map(|title| title.to_string()).collect(),
+            year,
+            extra: extra.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            format: format.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_node_name_movies_txt() {
+        let expected = vec![
+            parsed(&[], None, "", &[], ""),
+            parsed(&["Blade Runner 2049"], Some(2017), "", &[], "movie"),
+            ParsedNodeName { languages: vec!["French".to_string()], ..parsed(&["Amelie"], Some(2001), "[French]", &["French"], "movie") },
+            parsed(&["The Lord of the Rings: The Fellowship of the Ring", "El Senor de los Anillos - La Comunidad del Anillo"], Some(2001), "", &[], "movie"),
+            parsed(&["World of Warcraft Reckoning"], Some(2019), "[Original name has a colon]", &["Original name has a colon"], "short"),
+            parsed(&["Interstellar"], Some(2014), "[Different tags] [Tag 2]", &["Different tags", "Tag 2"], "movie"),
+            parsed(&["The Batman"], Some(2004), "", &[], "tvSeries"),
+            parsed(&["The Batman"], Some(2022), "[Same name but different year]", &["Same name but different year"], "movie"),
+            parsed(&["The Lego Batman Movie"], Some(2017), "", &[], "movie"),
+            parsed(&["Los lunes al sol"], Some(2002), "[Spanish movie, original title]", &["Spanish movie, original title"], "movie"),
+            parsed(&["Entrevista con el vampiro"], Some(1994), "[Just Spanish title when original title is in English]", &["Just Spanish title when original title is in English"], "movie"),
+            parsed(&["28 Dias Después", "28 Days Later"], Some(2002), "[Spanish title with accents]", &["Spanish title with accents"], "movie"),
+            parsed(&["The Philadelphia Story"], Some(1940), "", &[], "movie"),
+            parsed(&["Piper"], Some(2016), "", &[], "short"),
+            parsed(&["Piratas del caribe - La maldicion de la perla negra", "Pirates of the Caribbean The Curse of the Black Pearl"], Some(2003), "", &[], "movie"),
+            parsed(&["Paperman"], Some(2012), "[Using \"double quotes\" in node name]", &["Using \"double quotes\" in node name"], "short"),
+            parsed(&[], None, "", &[], ""),
+            parsed(&["Casablanca"], Some(1940), "[Wrong year]", &["Wrong year"], "movie"),
+            parsed(&[], None, "", &[], ""),
+            parsed(&["Pink Floyd: The Wall"], Some(1982), "[This will be ignored as it is \"music\"]", &["This will be ignored as it is \"music\""], "music"),
+            parsed(&["Saludos Amigos"], None, "", &["Year not recognized", "1942"], "short"),
+        ];
+        let movies = fs::read_to_string("result/movies.txt").expect("Something went wrong reading the file");
+
+        let parsed_names: Vec<ParsedNodeName> = movies.lines()
+            .map(|line| line.rsplit(['\\', '/']).next().unwrap())
+            .map(|node_name| if node_name.starts_with('#') { ParsedNodeName { ..Default::default() } } else { parse_node_name(node_name) })
+            .collect();
+
+        assert_eq!(expected, parsed_names);
+    }
+
+    #[test]
+    fn parse_node_name_parentheses_in_title() {
+        let parsed = parse_node_name("Birdman (or The Unexpected Virtue of Ignorance) (2014).movie");
+
+        assert_eq!("Birdman (or The Unexpected Virtue of Ignorance)", parsed.title());
+        assert_eq!(Some(2014), parsed.year);
+    }
+
+    #[test]
+    fn parse_node_name_dots_in_title() {
+        let parsed = parse_node_name("Mr. Smith Goes to Washington (1939).movie");
+
+        // as previous versions stored it, the searches use single spaces
+        assert_eq!("Mr  Smith Goes to Washington", parsed.title());
+        assert_eq!("Mr Smith Goes to Washington", search_title(&parsed.title()));
+        assert_eq!("1939", parsed.year_text());
+        assert_eq!("movie", parsed.format);
+    }
+
+    #[test]
+    fn parse_node_name_titles_keep_their_positions() {
+        let parsed = parse_node_name(" ~ 28 Days Later (2002).movie");
+        assert_eq!("", parsed.title());
+        assert_eq!("28 Days Later", parsed.title_optional());
+
+        let parsed = parse_node_name("28 Dias Después ~  (2002).movie");
+        assert_eq!("28 Dias Después", parsed.title());
+        assert_eq!("", parsed.title_optional());
+    }
+
+    #[test]
+    fn parse_node_name_year_range() {
+        let parsed = parse_node_name("The Sopranos (1999-2007).tvSeries");
+        assert_eq!(Some(1999), parsed.year);
+        assert_eq!(Some(2007), parsed.year_end);
+
+        let parsed = parse_node_name("The Simpsons (1989-).tvSeries");
+        assert_eq!(Some(1989), parsed.year);
+        assert_eq!(None, parsed.year_end);
+    }
+
+    #[test]
+    fn parse_node_name_missing_year() {
+        let parsed = parse_node_name("Saludos Amigos.short");
+
+        assert_eq!("Saludos Amigos", parsed.title());
+        assert_eq!("", parsed.year_text());
+        assert_eq!("short", parsed.format);
+    }
+
+    #[test]
+    fn parse_node_name_scene_style() {
+        let parsed = parse_node_name("Blade.Runner.2049.2017.1080p.BluRay.x264.mkv");
+
+        assert_eq!("Blade Runner 2049", parsed.title());
+        assert_eq!(Some(2017), parsed.year);
+        assert_eq!("1080p BluRay x264", parsed.extra);
+        assert_eq!(vec!["1080p", "bluray", "x264"], parsed.quality);
+        assert_eq!(Some("1080p".to_string()), parsed.resolution);
+        assert_eq!(Some("x264".to_string()), parsed.codec);
+        assert_eq!("mkv", parsed.format);
+    }
+
+    #[test]
+    fn parse_node_name_scene_style_year_in_title_without_year() {
+        let parsed = parse_node_name("Blade.Runner.2049.1080p.mkv");
+
+        assert_eq!("Blade Runner 2049", parsed.title());
+        assert_eq!(None, parsed.year);
+        assert_eq!("1080p", parsed.extra);
+        assert_eq!("mkv", parsed.format);
+    }
+
+    #[test]
+    fn parse_node_name_scene_style_year_before_edition_or_release_hints() {
+        let parsed = parse_node_name("Blade.Runner.1982.Final.Cut.mkv");
+        assert_eq!("Blade Runner", parsed.title());
+        assert_eq!(Some(1982), parsed.year);
+        assert_eq!(Some("Final Cut".to_string()), parsed.edition);
+
+        let parsed = parse_node_name("Amelie.2001.FRENCH.PROPER.mkv");
+        assert_eq!("Amelie", parsed.title());
+        assert_eq!(Some(2001), parsed.year);
+
+        let parsed = parse_node_name("Blade.Runner.2049.Final.Cut.2017.mkv");
+        assert_eq!("Blade Runner 2049 Final Cut", parsed.title());
+        assert_eq!(Some(2017), parsed.year);
+    }
+
+    #[test]
+    fn parse_node_name_scene_style_without_extension() {
+        let parsed = parse_node_name("Mr.Robot");
+
+        assert_eq!("Mr Robot", parsed.title());
+        assert_eq!("", parsed.format);
+        assert_eq!("movie", node_format(&parsed, "Series/Mr.Robot/", &[FormatRule { directory: String::new(), extensions: vec!["".to_string()], format: "movie".to_string() }], &[]));
+    }
+
+    #[test]
+    fn parse_node_name_scene_style_without_year() {
+        let parsed = parse_node_name("Amelie.720p.mkv");
+
+        assert_eq!("Amelie", parsed.title());
+        assert_eq!(None, parsed.year);
+        assert_eq!(vec!["720p"], parsed.quality);
+    }
+
+    #[test]
+    fn parse_node_name_edition_and_quality() {
+        let parsed = parse_node_name("Blade Runner (1982) [Final Cut] [2160p HDR].movie");
+
+        assert_eq!(Some("Final Cut".to_string()), parsed.edition);
+        assert_eq!(vec!["2160p", "hdr"], parsed.quality);
+        assert_eq!(vec!["Final Cut", "2160p HDR"], parsed.tags);
+    }
+
+    #[test]
+    fn parse_node_name_without_format() {
+        let parsed = parse_node_name("Blade Runner (1982)");
+
+        assert_eq!("Blade Runner", parsed.title());
+        assert_eq!("", parsed.format);
+    }
+
+    #[test]
+    fn parse_node_name_ids() {
+        let parsed = parse_node_name("Blade Runner (1982) [tt0083658].movie");
+        assert_eq!(Some("tt0083658".to_string()), parsed.imdb_id);
+        assert_eq!(vec!["tt0083658"], parsed.tags);
+
+        let parsed = parse_node_name("Blade Runner {imdb-tt0083658} (1982) {tmdb-78} {edition-Final Cut}.mkv");
+        assert_eq!("Blade Runner", parsed.title());
+        assert_eq!(Some(1982), parsed.year);
+        assert_eq!(Some("tt0083658".to_string()), parsed.imdb_id);
+        assert_eq!(Some("78".to_string()), parsed.tmdb_id);
+        assert_eq!("{edition-Final Cut}", parsed.extra);
+
+        let parsed = parse_node_name("Blade.Runner.1982.1080p.[imdbid-TT0083658].mkv");
+        assert_eq!("Blade Runner", parsed.title());
+        assert_eq!(Some("tt0083658".to_string()), parsed.imdb_id);
+    }
+
+    #[test]
+    fn parse_node_name_without_ids() {
+        let parsed = parse_node_name("Blade Runner (1982) [tt] {imdb-0083658} [tmdb-].movie");
+
+        assert_eq!(None, parsed.imdb_id);
+        assert_eq!(None, parsed.tmdb_id);
+    }
+
+    fn rules() -> Vec<FormatRule> {
+        vec![
+            FormatRule { directory: "Series/".to_string(), extensions: Vec::new(), format: "tvSeries".to_string() },
+            FormatRule { directory: String::new(), extensions: vec!["mkv".to_string(), "".to_string()], format: "movie".to_string() },
+        ]
+    }
+
+    #[test]
+    fn node_format_rules() {
+        let formats = vec!["movie".to_string(), "tvSeries".to_string(), "short".to_string()];
+        let format = |full_node_name: &str| {
+            let node_name = full_node_name.trim_end_matches('/').rsplit('/').next().unwrap();
+            node_format(&parse_node_name(node_name), full_node_name, &rules(), &formats)
+        };
+
+        assert_eq!("short", format("Movies/Piper (2016).short"));
+        assert_eq!("movie", format("Movies/Blade.Runner.2049.2017.1080p.MKV"));
+        assert_eq!("movie", format("Movies/Blade Runner (1982)/"));
+        assert_eq!("tvSeries", format("Media/Series/The Sopranos (1999-2007)/"));
+        assert_eq!("tvSeries", format("series/The.Sopranos.1999.mkv"));
+        assert_eq!("music", format("Music/Pink Floyd: The Wall (1982).music"));
+    }
+
+    #[test]
+    fn parse_node_name_scene_folder_without_extension() {
+        let parsed = parse_node_name("Blade.Runner.2049.2017.1080p.BluRay");
+
+        assert_eq!("", parsed.format);
+        assert_eq!("Blade Runner 2049", parsed.title());
+    }
+
+    #[test]
+    fn node_extra_structured_fields() {
+        let parsed = parse_node_name("Blade Runner (1982) [Director's Cut] [Spanish, English] [720p x265].mkv");
+
+        let extra = NodeExtra::from(&parsed);
+
+        assert_eq!("[Director's Cut] [Spanish, English] [720p x265]", extra.text);
+        assert_eq!(Some("720p".to_string()), extra.resolution);
+        assert_eq!(Some("x265".to_string()), extra.codec);
+        assert_eq!(Some("Director's Cut".to_string()), extra.edition);
+        assert_eq!(vec!["Spanish", "English"], extra.languages);
+    }
+
+    #[test]
+    fn node_extra_tag_with_language_word_is_not_a_language() {
+        let extra = NodeExtra::from(&parse_node_name("Los lunes al sol (2002) [Spanish movie, original title].movie"));
+
+        assert!(extra.languages.is_empty());
+    }
+
+    #[derive(Deserialize)]
+    struct Node {
+        #[serde(default, deserialize_with = "deserialize_node_extra")]
+        extra: NodeExtra,
+    }
+
+    #[test]
+    fn node_extra_from_previous_working_file() {
+        let node: Node = serde_json::from_str(r#"{"extra":" [French] [Extended]"}"#).unwrap();
+
+        assert_eq!("[French] [Extended]", node.extra.text);
+        assert_eq!(vec!["French", "Extended"], node.extra.tags);
+        assert_eq!(vec!["French"], node.extra.languages);
+        assert_eq!(Some("Extended".to_string()), node.extra.edition);
+
+        let node: Node = serde_json::from_str(r#"{"extra":{"text":"[1080p]","resolution":"1080p"}}"#).unwrap();
+        assert_eq!(Some("1080p".to_string()), node.extra.resolution);
+
+        let node: Node = serde_json::from_str(r#"{}"#).unwrap();
+        assert_eq!(NodeExtra { ..Default::default() }, node.extra);
+    }
+}
@@ -0,0 +1,49 @@
+#![allow(non_snake_case)]
+
+#[cfg(test)]
+mod overrides_tests {
+    use std::fs;
+    use notitia::error::Error;
+    use notitia::overrides::{Override, load_overrides, find_override};
+
+    fn overrides() -> Vec<Override> {
+        vec![
+            Override { title: Some("Blade Runner".to_string()), year: Some("1982".to_string()), tconst: Some("tt0083658".to_string()), ..Default::default() },
+            Override { node_name: Some("Blade Runner (1982) [Final Cut].movie".to_string()), tmdb_id: Some("78".to_string()), ..Default::default() },
+        ]
+    }
+
+    #[test]
+    fn find_override_by_node_name_first() {
+        let overrides = overrides();
+
+        assert_eq!(Some(&overrides[1]), find_override(&overrides, "Blade Runner (1982) [Final Cut].movie", "Blade Runner", "1982"));
+    }
+
+    #[test]
+    fn find_override_by_title_and_year() {
+        let overrides = overrides();
+
+        assert_eq!(Some(&overrides[0]), find_override(&overrides, "Blade Runner (1982).mkv", "blade runner", "1982"));
+        assert_eq!(None, find_override(&overrides, "Blade Runner (2049).mkv", "Blade Runner", "2049"));
+    }
+
+    #[test]
+    fn load_overrides_file() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("overrides.json").to_string_lossy().to_string();
+        fs::write(&path, r#"[{"title": "Blade Runner", "year": "1982", "tconst": "tt0083658"}]"#).unwrap();
+
+        assert_eq!(vec![overrides().remove(0)], load_overrides(&path).unwrap());
+        assert!(load_overrides("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn load_overrides_invalid_json() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("overrides.json").to_string_lossy().to_string();
+        fs::write(&path, r#"[{"title": "#).unwrap();
+
+        assert!(matches!(load_overrides(&path), Err(Error::Json { .. })));
+    }
+}
@@ -0,0 +1,169 @@
+#![allow(non_snake_case)]
+
+#[cfg(test)]
+mod pipeline_tests {
+    use std::fs;
+    use notitia::config::{Config, ImdbConfig, TmdbConfig, JustwatchConfig};
+    use notitia::scan::ScannedNode;
+    use notitia::movies::known_nodes;
+    use notitia::watch::diff_nodes;
+    use notitia::{Error, Pipeline};
+
+    fn test_config() -> Config {
+        Config {
+            imdb: ImdbConfig {
+                basics: "tests/files/imdb/title.basics.reduced.es_test.tsv".to_string(),
+                akas: "tests/files/imdb/title.akas.reduced.es_test.tsv".to_string(),
+                ratings: "tests/files/imdb/title.ratings_test.tsv".to_string(),
+                ..Default::default()
+            },
+            tmdb: TmdbConfig {
+                movies_metadata: "tests/files/tmdb/movies_metadata_test.csv".to_string(),
+                archive: "tests/files/tmdb/archive_test.csv".to_string(),
+            },
+            justwatch: JustwatchConfig { files: vec!["tests/files/justwatch/test.json".to_string()] },
+            updating: false,
+            ..Default::default()
+        }
+    }
+
+    fn node(full_node_name: &str) -> ScannedNode {
+        ScannedNode { full_node_name: full_node_name.to_string(), size: 0, modified: None }
+    }
+
+    #[test]
+    fn add_nodes_found() {
+        let mut pipeline = Pipeline::load(test_config()).unwrap();
+        pipeline.add_nodes(&[node("Movies\\Blade Runner (1982).movie"), node("Movies\\Blade Runner 2049 (2017).movie")]);
+
+        assert_eq!(2, pipeline.movies.len());
+        assert_eq!(Some("tt0083658".to_string()), pipeline.movies[0].imdb_selected);
+        assert_eq!(Some("tt1856101".to_string()), pipeline.movies[1].imdb_selected);
+        assert_eq!("Blade Runner (1982).movie", pipeline.movies[0].node[0].node_name);
+    }
+
+    #[test]
+    fn add_nodes_twice_updates_the_movie() {
+        let mut pipeline = Pipeline::load(test_config()).unwrap();
+        let nodes = [node("Movies\\Blade Runner (1982).movie")];
+        pipeline.add_nodes(&nodes);
+        pipeline.add_nodes(&nodes);
+
+        assert_eq!(1, pipeline.movies.len());
+        assert_eq!(1, pipeline.movies[0].node.len());
+    }
+
+    #[test]
+    fn add_nodes_dotted_title_keeps_the_movie() {
+        let mut pipeline = Pipeline::load(test_config()).unwrap();
+        let nodes = [node("Movies\\Blade. Runner (1982).movie")];
+        pipeline.add_nodes(&nodes);
+        // stored as previous versions did, but searched with single spaces
+        assert_eq!("Blade  Runner", pipeline.movies[0].title);
+        assert_eq!(Some("tt0083658".to_string()), pipeline.movies[0].imdb_selected);
+
+        pipeline.movies[0].imdb_confirmed = true;
+        pipeline.add_nodes(&nodes);
+
+        assert_eq!(1, pipeline.movies.len());
+        assert!(pipeline.movies[0].imdb_confirmed);
+    }
+
+    #[test]
+    fn filter_nodes_ignored_are_not_changes() {
+        let mut pipeline = Pipeline::load(test_config()).unwrap();
+        let nodes = [node("Movies\\Blade Runner (1982).movie"), node("Music\\Vangelis - Blade Runner (1994).music")];
+        pipeline.add_nodes(&nodes);
+
+        assert_eq!(vec![nodes[0].clone()], pipeline.filter_nodes(&nodes));
+        assert!(diff_nodes(&known_nodes(&pipeline.movies), &pipeline.filter_nodes(&nodes)).is_empty());
+    }
+
+    #[test]
+    fn reconcile_missing_node() {
+        let mut pipeline = Pipeline::load(Config { prune_missing: true, ..test_config() }).unwrap();
+        pipeline.add_nodes(&[node("Movies\\Blade Runner (1982).movie")]);
+        let reconciliation = pipeline.reconcile(&[]);
+
+        assert_eq!(vec!["Movies\\Blade Runner (1982).movie".to_string()], reconciliation.missing);
+        assert!(pipeline.movies.is_empty());
+    }
+
+    #[test]
+    fn add_nodes_with_override() {
+        let folder = tempfile::tempdir().unwrap();
+        let overrides_file = folder.path().join("overrides.json").to_string_lossy().to_string();
+        fs::write(&overrides_file, r#"[{"node_name": "Bladerunner (1982).movie", "tconst": "tt0083658", "tmdb_id": "11862", "justwatch_id": "tm0"}]"#).unwrap();
+        let mut pipeline = Pipeline::load(Config { overrides_file, ..test_config() }).unwrap();
+        pipeline.add_nodes(&[node("Movies\\Bladerunner (1982).movie")]);
+
+        let movie = &pipeline.movies[0];
+        assert_eq!(vec!["tt0083658".to_string()], movie.imdb.iter().map(|imdb| imdb.tconst.clone()).collect::<Vec<String>>());
+        assert_eq!(Some("tt0083658".to_string()), movie.imdb_selected);
+        assert!(movie.imdb_confirmed);
+        assert_eq!("11862", movie.tmdb[0].id);
+        assert!(movie.tmdb_confirmed);
+        // a justwatch id that doesn't exist is searched
+        assert!(!movie.justwatch_confirmed);
+    }
+
+    #[test]
+    fn add_nodes_same_movie_in_one_batch() {
+        let folder = tempfile::tempdir().unwrap();
+        let overrides_file = folder.path().join("overrides.json").to_string_lossy().to_string();
+        fs::write(&overrides_file, r#"[{"node_name": "Blade Runner (1982).movie", "tconst": "tt1856101"}]"#).unwrap();
+        let mut pipeline = Pipeline::load(Config { overrides_file, ..test_config() }).unwrap();
+        pipeline.add_nodes(&[node("Movies\\Blade Runner (1982).movie"), node("Movies\\Blade Runner (1982) [Final Cut].movie")]);
+
+        // the second node is added to the movie of the first one, its confirmed imdb is not searched again
+        assert_eq!(1, pipeline.movies.len());
+        let movie = &pipeline.movies[0];
+        assert_eq!(2, movie.node.len());
+        assert!(movie.imdb_confirmed);
+        assert_eq!(vec!["tt1856101".to_string()], movie.imdb.iter().map(|imdb| imdb.tconst.clone()).collect::<Vec<String>>());
+    }
+
+    #[test]
+    fn add_nodes_same_movie_next_node_with_its_own_override() {
+        let folder = tempfile::tempdir().unwrap();
+        let overrides_file = folder.path().join("overrides.json").to_string_lossy().to_string();
+        fs::write(&overrides_file, r#"[{"node_name": "Blade Runner (1982) [Final Cut].movie", "tconst": "tt1856101"}]"#).unwrap();
+        let mut pipeline = Pipeline::load(Config { overrides_file, ..test_config() }).unwrap();
+        pipeline.add_nodes(&[node("Movies\\Blade Runner (1982).movie"), node("Movies\\Blade Runner (1982) [Final Cut].movie")]);
+
+        // the first node is searched, the override of the second one is applied after it
+        assert_eq!(1, pipeline.movies.len());
+        let movie = &pipeline.movies[0];
+        assert_eq!(2, movie.node.len());
+        assert!(movie.imdb_confirmed);
+        assert_eq!(Some("tt1856101".to_string()), movie.imdb_selected);
+    }
+
+    #[test]
+    fn add_nodes_with_ids_in_the_name() {
+        let mut pipeline = Pipeline::load(test_config()).unwrap();
+        // the title is not searched: Nightmare Alley would be found in justwatch
+        pipeline.add_nodes(&[node("Movies\\Nightmare Alley (2021) [tt0083658] {tmdb-11862}.movie"), node("Movies\\Blade Runner 2049 (2017) [tt0000000].movie")]);
+
+        let movie = &pipeline.movies[0];
+        assert_eq!(vec!["tt0083658".to_string()], movie.imdb.iter().map(|imdb| imdb.tconst.clone()).collect::<Vec<String>>());
+        assert_eq!(Some("tt0083658".to_string()), movie.imdb_selected);
+        assert!(movie.imdb[0].ratings.is_some());
+        assert!(!movie.imdb_confirmed);
+        assert!(movie.justwatch.is_empty());
+        assert_eq!("11862", movie.tmdb[0].id);
+        // a tconst that isn't in imdb is searched by title
+        assert_eq!(Some("tt1856101".to_string()), pipeline.movies[1].imdb_selected);
+    }
+
+    #[test]
+    fn load_missing_dataset() {
+        let config = Config {
+            imdb: ImdbConfig { basics: "tests/files/imdb/not_found.tsv".to_string(), ..test_config().imdb },
+            ..test_config()
+        };
+        let error = Pipeline::load(config).err().unwrap();
+
+        assert!(matches!(error, Error::MissingDataset { .. }));
+    }
+}
@@ -0,0 +1,47 @@
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+#[cfg(test)]
+mod pool_tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use notitia::pool::{map_parallel, worker_count};
+
+    #[test]
+    fn map_parallel_keeps_order() {
+        let items: Vec<u64> = (0..50).collect();
+
+        let results = map_parallel(&items, 4, |item| {
+            // later items finish first
+            std::thread::sleep(Duration::from_millis(50 - item));
+            item * 2
+        }, || {});
+
+        assert_eq!(items.iter().map(|item| item * 2).collect::<Vec<u64>>(), results);
+    }
+
+    #[test]
+    fn map_parallel_progress() {
+        let items = vec!["Up (2009).movie", "Heat (1995).movie", "Piper (2016).short"];
+        let progress = AtomicUsize::new(0);
+
+        let results = map_parallel(&items, 2, |item| item.len(), || { progress.fetch_add(1, Ordering::SeqCst); });
+
+        assert_eq!(vec![15, 17, 18], results);
+        assert_eq!(3, progress.into_inner());
+    }
+
+    #[test]
+    fn map_parallel_empty() {
+        let items: Vec<u32> = Vec::new();
+
+        assert!(map_parallel(&items, 0, |item| *item, || {}).is_empty());
+    }
+
+    #[test]
+    fn worker_count_per_core() {
+        assert!(worker_count(0) >= 1);
+        assert_eq!(3, worker_count(3));
+    }
+}
@@ -0,0 +1,85 @@
+#![allow(non_snake_case)]
+
+#[cfg(test)]
+mod report_tests {
+    use notitia::imdb::Imdb;
+    use notitia::tmdb::Tmdb;
+    use notitia::{MovieRecord, NodeRecord};
+    use notitia::report::{Report, build_report, format_table, format_html};
+
+    fn movie(title: &str, year: &str, tconsts: &[&str]) -> MovieRecord {
+        MovieRecord {
+            title: title.to_string(),
+            year: year.to_string(),
+            imdb: tconsts.iter().map(|tconst| Imdb { tconst: tconst.to_string(), ..Default::default() }).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn untitled(full_node_names: &[&str]) -> MovieRecord {
+        MovieRecord {
+            node: full_node_names.iter().map(|name| NodeRecord { full_node_name: name.to_string(), ..Default::default() }).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn build_report_counts() {
+        let mut blade_runner = movie("Blade Runner", "1982", &["tt0083658"]);
+        blade_runner.imdb_confirmed = true;
+        let before = vec![
+            blade_runner.clone(),
+            movie("Amelie", "2001", &[]),
+            movie("Piper", "2016", &[]),
+        ];
+        let mut amelie = movie("Amelie", "2001", &["tt0211915"]);
+        amelie.imdb_selected = Some("tt0211915".to_string());
+        amelie.tmdb = vec![Tmdb { ..Default::default() }];
+        let after = vec![
+            blade_runner,
+            amelie,
+            movie("The Batman", "2022", &["tt1877830", "tt0398417"]),
+            untitled(&["Movies\\Untitled.movie"]),
+        ];
+        let report = build_report(&before, &after);
+
+        assert_eq!(Report {
+            movies: 3,
+            added: 1,
+            updated: 1,
+            removed: 1,
+            imdb_none: 0,
+            imdb_one: 2,
+            imdb_many: 1,
+            imdb_selected: 1,
+            confirmed: 1,
+            unconfirmed: 2,
+            tmdb: 1,
+            justwatch: 0,
+            unparseable_nodes: vec!["Movies\\Untitled.movie".to_string()],
+        }, report);
+    }
+
+    #[test]
+    fn build_report_without_movies() {
+        assert_eq!(Report { ..Default::default() }, build_report(&[], &[]));
+    }
+
+    #[test]
+    fn format_table_rows() {
+        let report = Report { movies: 4, imdb_one: 3, imdb_none: 1, unparseable_nodes: vec!["a.movie".to_string()], ..Default::default() };
+        let table = format_table(&report);
+
+        assert!(table.starts_with("+-----------------------+---------+\n"));
+        assert!(table.contains("| imdb: 1 candidate     | 3 (75%) |\n"));
+        assert!(table.contains("| unparseable nodes     |       1 |\n"));
+        assert!(table.ends_with("# Unparseable: 'a.movie'\n"));
+    }
+
+    #[test]
+    fn format_html_escapes_node_names() {
+        let report = Report { unparseable_nodes: vec!["<Untitled> & co.movie".to_string()], ..Default::default() };
+
+        assert!(format_html(&report).contains("<li>&lt;Untitled&gt; &amp; co.movie</li>"));
+    }
+}
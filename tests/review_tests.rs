@@ -0,0 +1,125 @@
+#![allow(non_snake_case)]
+
+#[cfg(test)]
+mod review_tests {
+    use std::io::Cursor;
+    use notitia::imdb::{Imdb, Rating};
+    use notitia::tmdb::Tmdb;
+    use notitia::MovieRecord;
+    use notitia::review::{Answer, ReviewSummary, parse_answer, format_imdb, review_movies};
+
+    fn imdb(tconst: &str) -> Imdb {
+        Imdb { tconst: tconst.to_string(), ..Default::default() }
+    }
+
+    fn movie(title: &str, year: &str, tconsts: &[&str]) -> MovieRecord {
+        MovieRecord {
+            title: title.to_string(),
+            year: year.to_string(),
+            imdb: tconsts.iter().map(|tconst| imdb(tconst)).collect(),
+            imdb_selected: tconsts.first().map(|tconst| tconst.to_string()),
+            tmdb: vec![Tmdb { id: "78".to_string(), ..Default::default() }, Tmdb { id: "79".to_string(), ..Default::default() }],
+            ..Default::default()
+        }
+    }
+
+    fn review(movies: &mut [MovieRecord], answers: &str) -> (ReviewSummary, String) {
+        let mut output = Vec::new();
+        let lookup = |tconst: &str| if tconst == "tt0083658" { Some(imdb(tconst)) } else { None };
+        let summary = review_movies(movies, lookup, &mut Cursor::new(answers), &mut output).unwrap();
+        (summary, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn parse_answer_valid() {
+        assert_eq!(Some(Answer::Pick(0)), parse_answer("1\n", 2, true));
+        assert_eq!(Some(Answer::Pick(1)), parse_answer(" 2 ", 2, false));
+        assert_eq!(Some(Answer::RejectAll), parse_answer("R", 2, true));
+        assert_eq!(Some(Answer::Skip), parse_answer("\n", 2, true));
+        assert_eq!(Some(Answer::Quit), parse_answer("q", 0, false));
+        assert_eq!(Some(Answer::Tconst("tt0083658".to_string())), parse_answer("TT0083658", 0, true));
+    }
+
+    #[test]
+    fn parse_answer_invalid() {
+        assert_eq!(None, parse_answer("3", 2, true));
+        assert_eq!(None, parse_answer("0", 2, true));
+        assert_eq!(None, parse_answer("tt0083658", 2, false));
+        assert_eq!(None, parse_answer("tt", 2, true));
+        assert_eq!(None, parse_answer("blade runner", 2, true));
+    }
+
+    #[test]
+    fn format_imdb_line() {
+        let blade_runner = Imdb {
+            titleType: "movie".to_string(),
+            primaryTitle: "Blade Runner".to_string(),
+            originalTitle: "Blade Runner".to_string(),
+            year: Some(1982),
+            runtimeMinutes: Some(117),
+            ratings: Some(Rating { averageRating: 8.1, numVotes: 773387 }),
+            score: Some(0.9),
+            ..imdb("tt0083658")
+        };
+
+        assert_eq!("tt0083658  movie  Blade Runner  1982  117 min  8.1 (773387 votes)  score 0.90", format_imdb(&blade_runner));
+        assert_eq!("tt0083658  -  -  -  - min  no rating", format_imdb(&imdb("tt0083658")));
+    }
+
+    #[test]
+    fn review_movies_pick_and_reject() {
+        let mut movies = vec![movie("The Batman", "2022", &["tt1877830", "tt0398417"])];
+        let (summary, output) = review(&mut movies, "x\n2\n1\nr\n");
+
+        assert_eq!(ReviewSummary { reviewed: 1, confirmed: 3 }, summary);
+        assert!(output.contains("unknown answer 'x'"));
+        assert!(output.contains("  1) tt1877830  "));
+        assert_eq!(vec![imdb("tt0398417")], movies[0].imdb);
+        assert_eq!(Some("tt0398417".to_string()), movies[0].imdb_selected);
+        assert!(movies[0].imdb_confirmed);
+        assert_eq!(vec!["78".to_string()], movies[0].tmdb.iter().map(|tmdb| tmdb.id.clone()).collect::<Vec<String>>());
+        assert!(movies[0].tmdb_confirmed);
+        assert!(movies[0].justwatch.is_empty());
+        assert!(movies[0].justwatch_confirmed);
+    }
+
+    #[test]
+    fn review_movies_tconst_entered() {
+        let mut movies = vec![movie("Blade Runner", "1982", &[])];
+        let (summary, output) = review(&mut movies, "tt9999999\ntt0083658\ns\ns\n");
+
+        assert_eq!(ReviewSummary { reviewed: 1, confirmed: 1 }, summary);
+        assert!(output.contains("tt9999999 not found"));
+        assert_eq!(vec![imdb("tt0083658")], movies[0].imdb);
+        assert_eq!(Some("tt0083658".to_string()), movies[0].imdb_selected);
+        assert!(movies[0].imdb_confirmed);
+        assert_eq!(2, movies[0].tmdb.len());
+        assert!(!movies[0].tmdb_confirmed);
+        assert!(!movies[0].justwatch_confirmed);
+    }
+
+    #[test]
+    fn review_movies_skips_confirmed_and_untitled() {
+        let mut confirmed = movie("Blade Runner", "1982", &["tt0083658"]);
+        confirmed.imdb_confirmed = true;
+        confirmed.tmdb_confirmed = true;
+        confirmed.justwatch_confirmed = true;
+        let mut movies = vec![MovieRecord { ..Default::default() }, confirmed, movie("The Batman", "2022", &["tt1877830"])];
+        let (summary, output) = review(&mut movies, "q\n");
+
+        assert_eq!(ReviewSummary { reviewed: 1, confirmed: 0 }, summary);
+        assert!(output.starts_with("\n# The Batman (2022)\n"));
+        assert!(!movies[2].imdb_confirmed);
+    }
+
+    #[test]
+    fn review_movies_end_of_input_quits() {
+        let mut movies = vec![movie("The Batman", "2022", &["tt1877830"]), movie("Piper", "2016", &[])];
+        let (summary, _) = review(&mut movies, "1\n");
+
+        assert_eq!(ReviewSummary { reviewed: 1, confirmed: 1 }, summary);
+        assert!(movies[0].imdb_confirmed);
+        assert!(!movies[0].tmdb_confirmed);
+        assert!(!movies[1].imdb_confirmed);
+    }
+}
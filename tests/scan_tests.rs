@@ -0,0 +1,124 @@
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+#[cfg(test)]
+mod scan_tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use notitia::scan::{ScanConfig, ScannedNode, scan, matches_pattern, release_name, read_node_list};
+
+    fn library(name: &str, files: &[(&str, usize)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("notitia-scan-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (file, size) in files {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, vec![0u8; *size]).unwrap();
+        }
+        root
+    }
+
+    fn scan_library(root: &PathBuf) -> Vec<(String, u64)> {
+        let formats = vec!["movie".to_string(), "tvSeries".to_string(), "short".to_string()];
+        let root_name = root.to_string_lossy().to_string();
+        let nodes: Vec<ScannedNode> = scan(&root_name, &ScanConfig { ..Default::default() }, &formats).unwrap();
+        fs::remove_dir_all(root).unwrap();
+        nodes.iter()
+            .map(|node| (node.full_node_name[root_name.len() + 1..].to_string(), node.size))
+            .collect()
+    }
+
+    #[test]
+    fn scan_media_files() {
+        let root = library("files", &[
+            ("Movies/Blade Runner (1982).mkv", 10),
+            ("Movies/Piper (2016).short", 0),
+            ("Movies/Blade Runner (1982).srt", 5),
+            ("Movies/notes.txt", 5),
+            ("Movies/.hidden.mkv", 5),
+        ]);
+
+        let nodes = scan_library(&root);
+
+        assert_eq!(vec![
+            ("Movies/Blade Runner (1982).mkv".to_string(), 10),
+            ("Movies/Piper (2016).short".to_string(), 0),
+        ], nodes);
+    }
+
+    #[test]
+    fn scan_release_folders() {
+        let root = library("folders", &[
+            ("Movies/Interstellar (2014)/Interstellar.mkv", 100),
+            ("Movies/Interstellar (2014)/Sample/sample.mkv", 7),
+            ("Movies/Interstellar (2014)/Extras/Making of.mkv", 20),
+            ("Movies/Interstellar (2014)/Subs/English.srt", 3),
+            ("Movies/Interstellar (2014)/interstellar-sample.mkv", 7),
+            ("Series/The Sopranos (1999-2007)/Season 1/01.mkv", 30),
+            ("Series/The Sopranos (1999-2007)/Season 2/01.mkv", 40),
+            ("Movies/Empty (2000)/cover.jpg", 1),
+        ]);
+
+        let nodes = scan_library(&root);
+
+        assert_eq!(vec![
+            ("Movies/Interstellar (2014)/".to_string(), 100),
+            ("Series/The Sopranos (1999-2007)/".to_string(), 70),
+        ], nodes);
+    }
+
+    #[test]
+    fn scan_groups_multi_file_releases() {
+        let root = library("parts", &[
+            ("Movies/Casablanca (1942) CD1.avi", 10),
+            ("Movies/Casablanca (1942) CD2.avi", 20),
+            ("Movies/Heat (1995) - Part 1.avi", 10),
+            ("Movies/Heat (1995) - Part 2.avi", 15),
+            ("Movies/Up (2009).avi", 5),
+        ]);
+
+        let nodes = scan_library(&root);
+
+        assert_eq!(vec![
+            ("Movies/Casablanca (1942) CD1.avi".to_string(), 30),
+            ("Movies/Heat (1995) - Part 1.avi".to_string(), 25),
+            ("Movies/Up (2009).avi".to_string(), 5),
+        ], nodes);
+    }
+
+    #[test]
+    fn scan_missing_root() {
+        let formats: Vec<String> = Vec::new();
+
+        assert!(scan("missing/library", &ScanConfig { ..Default::default() }, &formats).is_err());
+    }
+
+    #[test]
+    fn release_name_part_markers() {
+        assert_eq!(Some("Casablanca (1942)".to_string()), release_name("Casablanca (1942) CD1.avi"));
+        assert_eq!(Some("Heat (1995)".to_string()), release_name("Heat (1995) - Part 2.avi"));
+        assert_eq!(Some("Heat.1995".to_string()), release_name("Heat.1995.disc3.mkv"));
+        assert_eq!(None, release_name("Blade Runner 2049.mkv"));
+        assert_eq!(None, release_name("Apollo 13 (1995).mkv"));
+        assert_eq!(None, release_name("Abcd1.mkv"));
+    }
+
+    #[test]
+    fn matches_pattern_wildcards() {
+        assert!(matches_pattern("sample", "Sample"));
+        assert!(matches_pattern("*-sample.*", "movie-sample.mkv"));
+        assert!(matches_pattern("*.srt", "Movie.EN.SRT"));
+        assert!(matches_pattern(".*", ".DS_Store"));
+        assert!(!matches_pattern("sample", "samples"));
+        assert!(!matches_pattern("*-sample.*", "sample.mkv"));
+    }
+
+    #[test]
+    fn read_node_list_skips_comments() {
+        let nodes = read_node_list("result/movies.txt").unwrap();
+
+        assert_eq!(18, nodes.len());
+        assert!(nodes.iter().all(|node| !node.full_node_name.starts_with('#') && node.modified.is_none()));
+    }
+}
@@ -0,0 +1,104 @@
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+#[cfg(test)]
+mod scoring_tests {
+    use notitia::imdb::{Imdb, Rating};
+    use notitia::scoring::{ScoreContext, score_imdb, score_imdbs, select_imdb};
+
+    fn imdb(tconst: &str, titleType: &str, primaryTitle: &str, runtimeMinutes: Option<u32>, numVotes: u32) -> Imdb {
+        Imdb {
+            tconst: tconst.to_string(),
+            titleType: titleType.to_string(),
+            primaryTitle: primaryTitle.to_string(),
+            originalTitle: primaryTitle.to_string(),
+            runtimeMinutes,
+            ratings: Some(Rating { averageRating: 7.0, numVotes }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn score_imdb_perfect_match() {
+        let title = "Blade Runner".to_string();
+        let context = ScoreContext { title: &title, title_optional: &String::new(), format: "movie", aka_tconsts: &["tt0083658".to_string()] };
+
+        let score = score_imdb(&imdb("tt0083658", "movie", "Blade Runner", Some(117), 800000), &context, 800000);
+
+        assert!((score - 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn score_imdbs_prefers_node_format() {
+        let title = "Blade Runner".to_string();
+        let context = ScoreContext { title: &title, title_optional: &String::new(), format: "movie", aka_tconsts: &[] };
+        let mut imdbs = vec![
+            imdb("tt1", "short", "Blade Runner", Some(12), 900),
+            imdb("tt0083658", "movie", "Blade Runner", Some(117), 800),
+        ];
+
+        score_imdbs(&mut imdbs, &context);
+
+        assert_eq!("tt0083658", imdbs[0].tconst);
+        assert!(imdbs[0].score > imdbs[1].score);
+    }
+
+    #[test]
+    fn score_imdbs_original_title() {
+        let title = "Amelie".to_string();
+        let context = ScoreContext { title: &title, title_optional: &String::new(), format: "movie", aka_tconsts: &[] };
+        let mut original = imdb("tt1", "movie", "Amélie", Some(122), 100);
+        original.primaryTitle = "Le fabuleux destin d'Amélie Poulain".to_string();
+        original.originalTitle = "Amélie".to_string();
+        let mut imdbs = vec![original, imdb("tt2", "movie", "Amélie", Some(122), 100)];
+
+        score_imdbs(&mut imdbs, &context);
+
+        assert_eq!("tt2", imdbs[0].tconst);
+        assert!(imdbs[1].score.unwrap() > 0.5);
+    }
+
+    #[test]
+    fn score_imdbs_exact_year_before_off_by_one() {
+        let title = "Blade Runner".to_string();
+        let context = ScoreContext { title: &title, title_optional: &String::new(), format: "movie", aka_tconsts: &[] };
+        let off_by_one = Imdb { year_delta: Some(-1), ..imdb("tt1", "movie", "Blade Runner", Some(117), 800000) };
+        let exact_year = Imdb { year_delta: Some(0), ..imdb("tt0083658", "movie", "Blade Runner", None, 100) };
+        let mut imdbs = vec![off_by_one, exact_year];
+
+        score_imdbs(&mut imdbs, &context);
+
+        assert!(imdbs[1].score > imdbs[0].score);
+        assert_eq!("tt0083658", imdbs[0].tconst);
+        assert_eq!("tt0083658", select_imdb(&imdbs, 0.5).unwrap().tconst);
+    }
+
+    #[test]
+    fn select_imdb_above_threshold() {
+        let title = "Blade Runner".to_string();
+        let context = ScoreContext { title: &title, title_optional: &String::new(), format: "movie", aka_tconsts: &[] };
+        let mut imdbs = vec![
+            imdb("tt1", "tvMovie", "Blade Runner", None, 10),
+            imdb("tt0083658", "movie", "Blade Runner", Some(117), 800000),
+        ];
+        score_imdbs(&mut imdbs, &context);
+
+        assert_eq!("tt0083658", select_imdb(&imdbs, 0.8).unwrap().tconst);
+        assert_eq!(None, select_imdb(&imdbs, 0.95));
+    }
+
+    #[test]
+    fn select_imdb_tie() {
+        let title = "Blade Runner".to_string();
+        let context = ScoreContext { title: &title, title_optional: &String::new(), format: "movie", aka_tconsts: &[] };
+        let mut imdbs = vec![
+            imdb("tt1", "movie", "Blade Runner", Some(117), 100),
+            imdb("tt2", "movie", "Blade Runner", Some(117), 100),
+        ];
+        score_imdbs(&mut imdbs, &context);
+
+        assert_eq!(None, select_imdb(&imdbs, 0.5));
+        assert_eq!(None, select_imdb(&[], 0.5));
+    }
+}
@@ -2,24 +2,26 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
-#[path = "../src/utils.rs"] mod utils;
-#[path = "../src/tmdb.rs"] mod tmdb;
-#[path = "../src/imdb.rs"] mod imdb;
-
 #[cfg(test)]
 mod tmdb_tests {
     use std::fs;
-    use crate::imdb::{Imdb};
-    use crate::tmdb::{Tmdb, search_tmdbs, search_tmdbs_big, search_tmdbs_by_imdb, get_tmdbs};
+    use notitia::imdb::{Imdb};
+    use notitia::utils::{DEFAULT_SIMILARITY_THRESHOLD, BatchIndices};
+    use notitia::config::{Config, MatchingConfig};
+    use notitia::tmdb::{Tmdb, search_tmdbs, search_tmdbs_big, search_tmdbs_similar, search_tmdbs_by_imdb, get_tmdbs, get_tmdbs_batch, get_tmdb_by_id, get_tmdbs_by_id_batch};
+
+    fn matching(year_tolerance: u16) -> MatchingConfig {
+        MatchingConfig { similarity_threshold: DEFAULT_SIMILARITY_THRESHOLD, year_tolerance }
+    }
 
     #[test]
     fn search_tmdbs_found() {
-        let data = fs::read_to_string("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
+        let data = fs::read("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
         let mut tmdbs = Vec::<Tmdb>::new();
         let title = "Father of the Bride Part II";
         let year = "1995";
         
-        search_tmdbs(&data, &mut tmdbs, &title.to_string(), &year.to_string(), true).unwrap();
+        search_tmdbs(&data, &BatchIndices::default(), &mut tmdbs, title, year, true, &matching(0)).unwrap();
         
         assert_eq!(tmdbs.len(), 1);
         assert_eq!("tt0113041", tmdbs[0].imdb_id);
@@ -27,36 +29,36 @@ mod tmdb_tests {
 
     #[test]
     fn search_tmdbs_not_found_wrong_year() {
-        let data = fs::read_to_string("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
+        let data = fs::read("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
         let mut tmdbs = Vec::<Tmdb>::new();
         let title = "Father of the Bride Part II";
         let year = "1895";
         
-        search_tmdbs(&data, &mut tmdbs, &title.to_string(), &year.to_string(), true).unwrap();
+        search_tmdbs(&data, &BatchIndices::default(), &mut tmdbs, title, year, true, &matching(0)).unwrap();
         
         assert_eq!(tmdbs.len(), 0);
     }
 
     #[test]
     fn search_tmdbs_not_found() {
-        let data = fs::read_to_string("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
+        let data = fs::read("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
         let mut tmdbs = Vec::<Tmdb>::new();
         let title = "not found";
         let year = "2025";
         
-        search_tmdbs(&data, &mut tmdbs, &title.to_string(), &year.to_string(), true).unwrap();
+        search_tmdbs(&data, &BatchIndices::default(), &mut tmdbs, title, year, true, &matching(0)).unwrap();
         
         assert_eq!(tmdbs.len(), 0);
     }
     
     #[test]
     fn search_tmdbs_big_found() {
-        let data = fs::read_to_string("tests/files/tmdb/archive_test.csv").expect("Something went wrong reading the file");
+        let data = fs::read("tests/files/tmdb/archive_test.csv").expect("Something went wrong reading the file");
         let mut tmdbs = Vec::<Tmdb>::new();
         let title = "Black Panther: Wakanda Forever";
         let year = "2022";
         
-        search_tmdbs_big(&data, &mut tmdbs, &title.to_string(), &year.to_string(), true).unwrap();
+        search_tmdbs_big(&data, &BatchIndices::default(), &mut tmdbs, title, year, true, &matching(0)).unwrap();
         
         assert_eq!(tmdbs.len(), 1);
         assert_eq!("505642", tmdbs[0].id);
@@ -64,37 +66,37 @@ mod tmdb_tests {
     
     #[test]
     fn search_tmdbs_big_not_found_wrong_year() {
-        let data = fs::read_to_string("tests/files/tmdb/archive_test.csv").expect("Something went wrong reading the file");
+        let data = fs::read("tests/files/tmdb/archive_test.csv").expect("Something went wrong reading the file");
         let mut tmdbs = Vec::<Tmdb>::new();
         let title = "Black Panther: Wakanda Forever";
         let year = "1022";
         
-        search_tmdbs_big(&data, &mut tmdbs, &title.to_string(), &year.to_string(), true).unwrap();
+        search_tmdbs_big(&data, &BatchIndices::default(), &mut tmdbs, title, year, true, &matching(0)).unwrap();
         
         assert_eq!(tmdbs.len(), 0);
     }
 
     #[test]
     fn search_tmdbs_big_not_found() {
-        let data = fs::read_to_string("tests/files/tmdb/archive_test.csv").expect("Something went wrong reading the file");
+        let data = fs::read("tests/files/tmdb/archive_test.csv").expect("Something went wrong reading the file");
         let mut tmdbs = Vec::<Tmdb>::new();
         let title = "not found";
         let year = "2025";        
         
-        search_tmdbs_big(&data, &mut tmdbs, &title.to_string(), &year.to_string(), true).unwrap();
+        search_tmdbs_big(&data, &BatchIndices::default(), &mut tmdbs, title, year, true, &matching(0)).unwrap();
 
         assert_eq!(tmdbs.len(), 0);
     }
 
     #[test]
     fn search_tmdbs_by_imdb_imdb_list_empty_found() {
-        let data = fs::read_to_string("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
+        let data = fs::read("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
         let imdbs = Vec::<Imdb>::new();
         let title = "Father of the Bride Part II".to_string();
         let title_optional = String::new();
         let year = "1995".to_string();
         
-        let tmdbs = search_tmdbs_by_imdb(&data, &imdbs, (&title, &title_optional, &year));
+        let tmdbs = search_tmdbs_by_imdb(&data, &imdbs, (&title, &title_optional, &year), &matching(0));
 
         assert_eq!(tmdbs.len(), 1);
         assert_eq!("tt0113041", tmdbs[0].imdb_id);
@@ -102,13 +104,13 @@ mod tmdb_tests {
     
     #[test]
     fn search_tmdbs_by_imdb_imdb_list_empty_using_title_optional_found() {
-        let data = fs::read_to_string("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
+        let data = fs::read("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
         let imdbs = Vec::<Imdb>::new();
         let title = "no-found".to_string();
         let title_optional = "Father of the Bride Part II".to_string();
         let year = "1995".to_string();
         
-        let tmdbs = search_tmdbs_by_imdb(&data, &imdbs, (&title, &title_optional, &year));
+        let tmdbs = search_tmdbs_by_imdb(&data, &imdbs, (&title, &title_optional, &year), &matching(0));
 
         assert_eq!(tmdbs.len(), 1);
         assert_eq!("tt0113041", tmdbs[0].imdb_id);
@@ -116,41 +118,165 @@ mod tmdb_tests {
 
     #[test]
     fn search_tmdbs_by_imdb_one_item_in_imdb_list_found() {
-        let data = fs::read_to_string("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
-        let mut imdbs = Vec::<Imdb>::new();
-        imdbs.push(Imdb {
-            tconst: "tt0113041".to_string(),
-            titleType: String::new(),
+        let data = fs::read("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
+        let imdbs = vec![
+            Imdb {
+                tconst: "tt0113041".to_string(),
+                titleType: String::new(),
             
-            primaryTitle: String::new(),
-            originalTitle: String::new(),
-            year: String::new(),
-            runtimeMinutes: String::new(),
-            genres: String::new(),
-            ratings: None,
-        });
+                primaryTitle: String::new(),
+                originalTitle: String::new(),
+                year: None,
+                runtimeMinutes: None,
+                genres: Vec::new(),
+                ratings: None,
+                score: None,
+                similarity: None,
+                year_delta: None,
+            },
+        ];
         let title = String::new();
         let title_optional = String::new();
         let year = String::new();
         
-        let tmdbs = search_tmdbs_by_imdb(&data, &imdbs, (&title, &title_optional, &year));
+        let tmdbs = search_tmdbs_by_imdb(&data, &imdbs, (&title, &title_optional, &year), &matching(0));
         
         assert_eq!(tmdbs.len(), 1);
         assert_eq!("tt0113041", tmdbs[0].imdb_id);
     }
     
+    #[test]
+    fn search_tmdbs_by_imdb_more_than_one_item_in_imdb_list_found() {
+        let data = fs::read("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
+        let imdbs = vec![
+            Imdb { tconst: "tt0113041".to_string(), ..Default::default() },
+            Imdb { tconst: "tt0114885".to_string(), ..Default::default() },
+        ];
+        let title = String::new();
+        let title_optional = String::new();
+        let year = String::new();
+
+        let tmdbs = search_tmdbs_by_imdb(&data, &imdbs, (&title, &title_optional, &year), &matching(0));
+
+        assert_eq!(tmdbs.len(), 2);
+        assert_eq!("tt0113041", tmdbs[0].imdb_id);
+        assert_eq!("tt0114885", tmdbs[1].imdb_id);
+    }
+
     #[test]
     fn get_tmdbs_found() {
-        let data_tmdbs = fs::read_to_string("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
-        let data_tmdbs_big = fs::read_to_string("tests/files/tmdb/archive_test.csv").expect("Something went wrong reading the file");
+        let data_tmdbs = fs::read("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
+        let data_tmdbs_big = fs::read("tests/files/tmdb/archive_test.csv").expect("Something went wrong reading the file");
         let imdbs = Vec::<Imdb>::new();
         let title = "Father of the Bride Part II".to_string();
         let title_optional = String::new();
         let year = "1995".to_string();
         
-        let tmdbs = get_tmdbs(&data_tmdbs, &data_tmdbs_big, &imdbs, (&title, &title_optional, &year));
+        let tmdbs = get_tmdbs(&data_tmdbs, &data_tmdbs_big, &imdbs, (&title, &title_optional, &year), &matching(0));
+
+        assert_eq!(tmdbs.len(), 1);
+        assert_eq!("tt0113041", tmdbs[0].imdb_id);
+    }
+
+    #[test]
+    fn search_tmdbs_similar_found() {
+        let data = fs::read("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
+        let mut tmdbs = Vec::<Tmdb>::new();
+
+        search_tmdbs_similar(&data, &BatchIndices::default(), &mut tmdbs, "Father of the Bride Part 2", "1995", &matching(0)).unwrap();
+
+        assert_eq!(tmdbs.len(), 1);
+        assert_eq!("tt0113041", tmdbs[0].imdb_id);
+        assert_eq!(Some(1.0), tmdbs[0].similarity);
+    }
+
+    #[test]
+    fn search_tmdbs_by_imdb_similar_title_found() {
+        let data = fs::read("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
+        let imdbs = Vec::<Imdb>::new();
+
+        let tmdbs = search_tmdbs_by_imdb(&data, &imdbs, (&"Father of the Bride Part 2".to_string(), &String::new(), "1995"), &matching(0));
 
         assert_eq!(tmdbs.len(), 1);
         assert_eq!("tt0113041", tmdbs[0].imdb_id);
     }
+
+    #[test]
+    fn search_tmdbs_year_tolerance() {
+        let data = fs::read("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
+        let mut tmdbs = Vec::<Tmdb>::new();
+
+        search_tmdbs(&data, &BatchIndices::default(), &mut tmdbs, "Father of the Bride Part II", "1996", true, &matching(0)).unwrap();
+        assert_eq!(tmdbs.len(), 0);
+
+        search_tmdbs(&data, &BatchIndices::default(), &mut tmdbs, "Father of the Bride Part II", "1996", true, &matching(1)).unwrap();
+        assert_eq!(tmdbs.len(), 1);
+        assert_eq!(Some(-1), tmdbs[0].year_delta);
+    }
+
+    #[test]
+    fn get_tmdbs_batch_same_as_get_tmdbs() {
+        let data_tmdbs = fs::read("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
+        let data_tmdbs_big = fs::read("tests/files/tmdb/archive_test.csv").expect("Something went wrong reading the file");
+        let imdb = Imdb { tconst: "tt0113041".to_string(), ..Default::default() };
+        let node = |title: &str, title_optional: &str, year: &str| (title.to_string(), title_optional.to_string(), year.to_string());
+        let nodes = vec![
+            (Vec::new(), node("Father of the Bride Part II", "", "1995")),
+            (Vec::new(), node("not found", "Father of the Bride Part 2", "1995")),
+            (vec![imdb], node("not found", "", "1995")),
+            (Vec::new(), node("not found", "not found", "2025")),
+        ];
+
+        let tmdbs = get_tmdbs_batch(&data_tmdbs, &data_tmdbs_big, &nodes, &Config { matching: matching(0), threads: 2, ..Default::default() });
+
+        assert_eq!(nodes.len(), tmdbs.len());
+        for ((imdbs, (title, title_optional, year)), tmdbs) in nodes.iter().zip(&tmdbs) {
+            assert_eq!(&get_tmdbs(&data_tmdbs, &data_tmdbs_big, imdbs, (title, title_optional, year), &matching(0)), tmdbs);
+        }
+        assert_eq!("tt0113041", tmdbs[0][0].imdb_id);
+        assert_eq!("tt0113041", tmdbs[2][0].imdb_id);
+    }
+
+    #[test]
+    fn get_tmdb_by_id_found() {
+        let data_tmdbs = fs::read("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
+        let data_tmdbs_big = fs::read("tests/files/tmdb/archive_test.csv").expect("Something went wrong reading the file");
+
+        let tmdb = get_tmdb_by_id(&data_tmdbs, &data_tmdbs_big, "11862", "1994").unwrap().unwrap();
+        assert_eq!("tt0113041", tmdb.imdb_id);
+        assert_eq!(Some(1), tmdb.year_delta);
+        assert_eq!(None, tmdb.similarity);
+
+        let tmdb_big = get_tmdb_by_id(&data_tmdbs, &data_tmdbs_big, "505642", "2022").unwrap().unwrap();
+        assert_eq!("", tmdb_big.imdb_id);
+        assert_eq!(Some(0), tmdb_big.year_delta);
+    }
+
+    #[test]
+    fn get_tmdb_by_id_not_found() {
+        let data_tmdbs = fs::read("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
+        let data_tmdbs_big = fs::read("tests/files/tmdb/archive_test.csv").expect("Something went wrong reading the file");
+
+        // 0 is the budget of several movies, not an id
+        assert!(get_tmdb_by_id(&data_tmdbs, &data_tmdbs_big, "0", "1995").unwrap().is_none());
+        assert!(get_tmdb_by_id(&data_tmdbs, &data_tmdbs_big, "999999", "1995").unwrap().is_none());
+    }
+
+    #[test]
+    fn get_tmdbs_by_id_batch_found() {
+        let data_tmdbs = fs::read("tests/files/tmdb/movies_metadata_test.csv").expect("Something went wrong reading the file");
+        let data_tmdbs_big = fs::read("tests/files/tmdb/archive_test.csv").expect("Something went wrong reading the file");
+        let node = |tmdb_id: Option<&str>, tconst: Option<&str>, year: &str| (tmdb_id.map(String::from), tconst.map(String::from), year.to_string());
+        let nodes = vec![
+            node(None, Some("tt0113041"), "1995"),
+            node(Some("505642"), Some("tt0113041"), "2022"),
+            node(None, Some("tt0000000"), "1995"),
+            node(None, None, "1995"),
+        ];
+
+        let tmdbs = get_tmdbs_by_id_batch(&data_tmdbs, &data_tmdbs_big, &nodes);
+
+        assert_eq!(vec![vec!["11862"], vec!["505642"], vec![], vec![]], tmdbs.iter().map(|tmdbs| tmdbs.iter().map(|tmdb| tmdb.id.as_str()).collect::<Vec<&str>>()).collect::<Vec<Vec<&str>>>());
+        assert_eq!(get_tmdb_by_id(&data_tmdbs, &data_tmdbs_big, "11862", "1995").unwrap().as_ref(), tmdbs[0].first());
+    }
 }
@@ -2,16 +2,14 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
-#[path = "../src/utils.rs"] mod utils;
-
 #[cfg(test)]
 mod utils_tests {
     use std::fs;
-    use crate::utils::{remove_diacritics, title_ready, search_indices, get_line_by_index};
+    use notitia::utils::{remove_diacritics, title_ready, IGNORED_CHARS, search_indices, search_indices_without_diacritics, get_line_by_index, normalize_title, damerau_levenshtein, title_similarity, SimilarTitle, DEFAULT_SIMILARITY_THRESHOLD, year_delta, years_around, line_indices_containing, write_atomically, search_indices_batch, TitleSearcher, BatchIndices};
 
     #[test]
     fn get_line_by_index_test_beginning_of_file() {
-        let data = fs::read_to_string("tests/files/imdb/title.ratings_test.tsv").expect("Something went wrong reading the file");
+        let data = fs::read("tests/files/imdb/title.ratings_test.tsv").expect("Something went wrong reading the file");
         let index = 0;
 
         let result = get_line_by_index(&data, &index, false);
@@ -21,7 +19,7 @@ mod utils_tests {
 
     #[test]
     fn get_line_by_index_test_middle() {
-        let data = fs::read_to_string("tests/files/imdb/title.ratings_test.tsv").expect("Something went wrong reading the file");
+        let data = fs::read("tests/files/imdb/title.ratings_test.tsv").expect("Something went wrong reading the file");
         let index = 60;
 
         let result = get_line_by_index(&data, &index, true);
@@ -31,10 +29,10 @@ mod utils_tests {
 
     #[test]
     fn search_indices_one_found() {
-        let data = fs::read_to_string("tests/files/imdb/title.ratings_test.tsv").expect("Something went wrong reading the file");
+        let data = fs::read("tests/files/imdb/title.ratings_test.tsv").expect("Something went wrong reading the file");
         let text_to_find = "tt0325980".to_string();
 
-        let list_indexes = search_indices(&data, &text_to_find);
+        let list_indexes = search_indices(&data, &text_to_find).unwrap();
         let result = get_line_by_index(&data, &list_indexes[0], true);
 
         assert_eq!(1, list_indexes.len());
@@ -44,10 +42,10 @@ mod utils_tests {
 
     #[test]
     fn search_indices_two_found() {
-        let data = fs::read_to_string("tests/files/imdb/title.ratings_test.tsv").expect("Something went wrong reading the file");
+        let data = fs::read("tests/files/imdb/title.ratings_test.tsv").expect("Something went wrong reading the file");
         let text_to_find = "177".to_string();
 
-        let list_indexes = search_indices(&data, &text_to_find);
+        let list_indexes = search_indices(&data, &text_to_find).unwrap();
         let result1 = get_line_by_index(&data, &list_indexes[0], true);
         let result2 = get_line_by_index(&data, &list_indexes[1], true);
 
@@ -60,26 +58,212 @@ mod utils_tests {
 
     #[test]
     fn search_indices_not_found() {
-        let data = fs::read_to_string("tests/files/imdb/title.ratings_test.tsv").expect("Something went wrong reading the file");
+        let data = fs::read("tests/files/imdb/title.ratings_test.tsv").expect("Something went wrong reading the file");
         let text_to_find = "not-found".to_string();
 
-        let list_indexes = search_indices(&data, &text_to_find);
+        let list_indexes = search_indices(&data, &text_to_find).unwrap();
         
         assert_eq!(0, list_indexes.len());
     }
     
+    #[test]
+    fn search_indices_title_at_the_end_of_data() {
+        let data = "tconst\taverageRating\nt1\t177".as_bytes();
+
+        assert_eq!(vec![21], search_indices(data, &"177".to_string()).unwrap());
+        assert_eq!(0, search_indices(data, &"1770".to_string()).unwrap().len());
+    }
+
+    #[test]
+    fn search_indices_without_diacritics_found() {
+        let data = "id\ttitle\n1\tAmélie\n2\tEl señor de los anillos\n".as_bytes();
+
+        assert_eq!(vec![9], search_indices_without_diacritics(data, &"amelie".to_string()).unwrap());
+        assert_eq!(vec![19], search_indices_without_diacritics(data, &"El Senor".to_string()).unwrap());
+        assert_eq!(0, search_indices(data, &"amelie".to_string()).unwrap().len());
+    }
+
+    #[test]
+    fn search_indices_ignored_chars_and_case() {
+        let data = "id\ttitle\n1\tMr. Smith Goes to Washington\n2\tWHAT'S UP DOC?\n".as_bytes();
+
+        assert_eq!(vec![9], search_indices(data, &"\tmr smith goes".to_string()).unwrap());
+        assert_eq!(vec![40], search_indices(data, &"\twhat's up doc?\n".to_string()).unwrap());
+    }
+
+    #[test]
+    fn search_indices_one_index_per_line() {
+        let data = "id\ttitle\toriginal\n1\tUp\tUp\n".as_bytes();
+
+        assert_eq!(vec![18], search_indices(data, &"\tup".to_string()).unwrap());
+    }
+
+    #[test]
+    fn search_indices_batch_one_pass() {
+        let data = fs::read("tests/files/imdb/title.ratings_test.tsv").expect("Something went wrong reading the file");
+        let titles = vec!["tt0325980\t".to_string(), "177".to_string(), "not-found".to_string(), "\t177".to_string()];
+
+        let indices = search_indices_batch(&data, &titles).unwrap();
+
+        assert_eq!(titles.iter().map(|title| search_indices(&data, title).unwrap()).collect::<Vec<Vec<usize>>>(), indices);
+        assert_eq!(vec![170], indices[0]);
+        assert_eq!(vec![131, 213], indices[1]);
+        assert!(indices[2].is_empty());
+    }
+
+    #[test]
+    fn search_indices_batch_empty_title_every_line() {
+        let data = "id\ttitle\n1\tUp\n2\tHeat".as_bytes();
+
+        assert_eq!(vec![vec![0, 9, 14], vec![9]], search_indices_batch(data, &[String::new(), "\tup".to_string()]).unwrap());
+    }
+
+    #[test]
+    fn title_searcher_without_diacritics() {
+        let data = "id\ttitle\n1\tAmélie\n2\tEl señor de los anillos\n3\tAmelie\n".as_bytes();
+        let searcher = TitleSearcher::new(&["amelie".to_string(), "senor".to_string()], true).unwrap();
+
+        assert_eq!(vec![vec![9, 46], vec![19]], searcher.search(data));
+    }
+
+    #[test]
+    fn get_line_by_index_first_line() {
+        let data = "tconst\taverageRating\nt1\t177".as_bytes();
+
+        assert_eq!("tconst\taverageRating", get_line_by_index(data, &0, true));
+        assert_eq!("t1\t177", get_line_by_index(data, &21, true));
+    }
+
     #[test]
     fn title_ready_without_special_chars() {
-        assert_eq!("name without special chars", title_ready(&"name without special chars".to_string()));
+        assert_eq!("name without special chars", title_ready("name without special chars"));
     }
     
     #[test]
     fn title_ready_with_special_chars() {
-        assert_eq!("name with special chars", title_ready(&"'.name, with; special: chars?'".to_string()));
+        assert_eq!("name with special chars", title_ready("'.name, with; special: chars?'"));
+    }
+
+    #[test]
+    fn title_ready_removes_only_the_ignored_chars() {
+        assert_eq!(b":.,'?;\"", IGNORED_CHARS);
+        assert_eq!("name", title_ready(":.,'?;\"name"));
+        assert_eq!("!-&()[]/ name", title_ready("!-&()[]/ Name"));
     }
 
     #[test]
     fn remove_diacritics_some_accents() {
-        assert_eq!("aeiou", remove_diacritics(&"áéíóú".to_string()));
+        assert_eq!("aeiou", remove_diacritics("áéíóú"));
+    }
+
+    #[test]
+    fn normalize_title_tokens() {
+        assert_eq!("amelie", normalize_title("Amélie"));
+        assert_eq!("fast and furious", normalize_title("Fast & Furious"));
+        assert_eq!("rocky 2", normalize_title("Rocky II"));
+        assert_eq!("matrix", normalize_title("The  Matrix "));
+        assert_eq!("senor de los anillos", normalize_title("El señor de los anillos"));
+        assert_eq!("the", normalize_title("The"));
+        assert_eq!("mix", normalize_title("Mix"));
+    }
+
+    #[test]
+    fn damerau_levenshtein_distances() {
+        assert_eq!(0, damerau_levenshtein("matrix", "matrix"));
+        assert_eq!(1, damerau_levenshtein("matrix", "matirx"));
+        assert_eq!(1, damerau_levenshtein("matrix", "matrx"));
+        assert_eq!(3, damerau_levenshtein("kitten", "sitting"));
+        assert_eq!(4, damerau_levenshtein("", "abcd"));
+    }
+
+    #[test]
+    fn title_similarity_scores() {
+        assert_eq!(1.0, title_similarity("Amelie", "Amélie"));
+        assert_eq!(1.0, title_similarity("The Godfather Part II", "Godfather Part 2"));
+        assert_eq!(1.0, title_similarity("Fast and Furious", "Fast & Furious"));
+        assert!(title_similarity("Blade Runer", "Blade Runner") > 0.9);
+        assert!(title_similarity("Alien", "Aliens") < 0.85);
+    }
+
+    #[test]
+    fn similar_title_same_as_title_similarity_above_the_threshold() {
+        let titles = ["Blade Runner", "Blade Runer", "Runner Blade", "Bladerunner", "Blade Runner 2049", "Alien", "Aliens", "", "The Godfather Part II", "Godfather Part 2", "abab", "baba"];
+        for a in titles {
+            let similar = SimilarTitle::new(a);
+            for b in titles {
+                for threshold in [0.0, 0.5, DEFAULT_SIMILARITY_THRESHOLD, 1.0] {
+                    let similarity = title_similarity(a, b);
+                    let expected = if similarity >= threshold { similarity } else { 0.0 };
+                    assert_eq!(expected, similar.similarity(b, threshold), "'{}' '{}' {}", a, b, threshold);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn line_indices_containing_skips_header() {
+        let data = "id\tyear\n1\t1982\n2\t1983\n3\t1982".as_bytes();
+
+        assert_eq!(vec![8, 22], line_indices_containing(data, &["1982".to_string()]).unwrap());
+        assert_eq!(vec![8, 15, 22], line_indices_containing(data, &["1982".to_string(), "1983".to_string()]).unwrap());
+        assert_eq!(vec![8, 15, 22], line_indices_containing(data, &[String::new()]).unwrap());
+    }
+
+    #[test]
+    fn year_delta_within_tolerance() {
+        assert_eq!(Some(0), year_delta(Some(1982), "1982", 0));
+        assert_eq!(Some(-1), year_delta(Some(1981), "1982", 1));
+        assert_eq!(Some(1), year_delta(Some(1983), "1982", 1));
+        assert_eq!(None, year_delta(Some(1984), "1982", 1));
+        assert_eq!(None, year_delta(None, "1982", 1));
+        assert_eq!(None, year_delta(Some(1982), "", 1));
+    }
+
+    #[test]
+    fn years_around_current_year_first() {
+        assert_eq!(vec!["1982"], years_around("1982", 0));
+        assert_eq!(vec!["1982", "1981", "1983"], years_around("1982", 1));
+    }
+
+    #[test]
+    fn write_atomically_replaces_file() {
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join("movies.json").to_string_lossy().to_string();
+        fs::write(&path, "old").unwrap();
+
+        write_atomically(&path, b"new").unwrap();
+
+        assert_eq!("new", fs::read_to_string(&path).unwrap());
+        assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists());
+    }
+
+    #[test]
+    fn batch_indices_same_as_searching_each_pattern() {
+        let data = b"tconst\ttitle\tyear\ntt1\tBlade Runner\t1982\ntt2\tBlade Runner 2049\t2017\ntt3\tBrazil\t1985\n";
+        let titles = vec!["\tblade runner\t".to_string(), "brazil".to_string(), "not found".to_string()];
+        let years = vec!["\t1982".to_string(), "\t1985".to_string(), "\t2000".to_string()];
+        let mut batch = BatchIndices::default();
+
+        batch.add_titles(data, titles.clone()).unwrap();
+        batch.add_lines(data, years.clone()).unwrap();
+
+        for title in &titles {
+            assert_eq!(search_indices(data, title).unwrap(), batch.search_indices(data, title).unwrap());
+        }
+        assert_eq!(line_indices_containing(data, &years).unwrap(), batch.line_indices_containing(data, &years).unwrap());
+        assert_eq!(line_indices_containing(data, &years[1..2]).unwrap(), batch.line_indices_containing(data, &years[1..2]).unwrap());
+        assert!(batch.is_missing(&"not found".to_string()));
+        assert!(!batch.is_missing(&"brazil".to_string()));
+    }
+
+    #[test]
+    fn batch_indices_searches_patterns_not_in_the_batch() {
+        let data = "tconst\ttitle\ntt1\tBlade Runner\ntt2\tCafé Society\n".as_bytes();
+        let batch = BatchIndices::default();
+
+        assert_eq!(vec![13], batch.search_indices(data, &"blade runner".to_string()).unwrap());
+        assert_eq!(vec![30], batch.search_indices_without_diacritics(data, &"cafe society".to_string()).unwrap());
+        assert_eq!(vec![13, 30], batch.line_indices_containing(data, &["\t".to_string()]).unwrap());
+        assert!(!batch.is_missing(&"not found".to_string()));
     }
 }
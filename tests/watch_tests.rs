@@ -0,0 +1,71 @@
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+#[cfg(test)]
+mod watch_tests {
+    use std::fs;
+    use std::time::Duration;
+    use notitia::scan::ScannedNode;
+    use notitia::watch::{NodeChanges, Watcher, diff_nodes};
+
+    fn node(full_node_name: &str, size: u64, modified: Option<u64>) -> ScannedNode {
+        ScannedNode { full_node_name: full_node_name.to_string(), size, modified }
+    }
+
+    #[test]
+    fn diff_nodes_added_changed_removed() {
+        let previous = vec![
+            node("Movies/Up (2009).mkv", 10, Some(100)),
+            node("Movies/Heat (1995).mkv", 10, Some(100)),
+            node("Movies/Casablanca (1942).avi", 10, Some(100)),
+        ];
+        let current = vec![
+            node("Movies/Up (2009).mkv", 10, Some(100)),
+            node("Movies/Heat (1995).mkv", 20, Some(200)),
+            node("Movies/Casablanca (1942) [Colorized].avi", 10, Some(100)),
+        ];
+
+        let changes = diff_nodes(&previous, &current);
+
+        assert_eq!(NodeChanges {
+            added: vec![node("Movies/Casablanca (1942) [Colorized].avi", 10, Some(100))],
+            changed: vec![node("Movies/Heat (1995).mkv", 20, Some(200))],
+            removed: vec!["Movies/Casablanca (1942).avi".to_string()],
+        }, changes);
+    }
+
+    #[test]
+    fn diff_nodes_list_never_changes() {
+        let previous = vec![node("Movies/Up (2009).movie", 10, Some(100))];
+        let current = vec![node("Movies/Up (2009).movie", 0, None)];
+
+        assert!(diff_nodes(&previous, &current).is_empty());
+    }
+
+    #[test]
+    fn watcher_library_changes() {
+        let library = tempfile::tempdir().unwrap();
+        let watcher = Watcher::new(library.path()).unwrap();
+
+        fs::create_dir(library.path().join("Up (2009)")).unwrap();
+        fs::write(library.path().join("Up (2009)").join("Up.mkv"), "up").unwrap();
+
+        assert!(watcher.wait(Duration::from_millis(100), Some(Duration::from_secs(5))));
+        assert!(!watcher.wait(Duration::from_millis(100), Some(Duration::from_millis(200))));
+    }
+
+    #[test]
+    fn watcher_node_list_changes() {
+        let folder = tempfile::tempdir().unwrap();
+        let list = folder.path().join("movies.txt");
+        fs::write(&list, "Up (2009).movie\n").unwrap();
+        let watcher = Watcher::new(&list).unwrap();
+
+        fs::write(folder.path().join("movies.result.json"), "[]").unwrap();
+        assert!(!watcher.wait(Duration::from_millis(100), Some(Duration::from_millis(300))));
+
+        fs::write(&list, "Up (2009).movie\nHeat (1995).movie\n").unwrap();
+        assert!(watcher.wait(Duration::from_millis(100), Some(Duration::from_secs(5))));
+    }
+}